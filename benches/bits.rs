@@ -0,0 +1,66 @@
+//! Compares reading a slice directly against reading it through a `Cursor` and an unbuffered
+//! `File`
+//!
+//! Run with `cargo bench --bench bits`.
+
+use std::hint::black_box;
+use std::io::Cursor;
+use std::time::{Duration, Instant};
+
+use ranalyzer::bits::BitstreamReader;
+
+const BUF_LEN: usize = 16 * 1024 * 1024;
+/// Unbuffered file reads are much slower, so only a prefix of the buffer is read from the file
+const FILE_LEN: usize = 1024 * 1024;
+const ITERATIONS: u32 = 5;
+
+fn read_all<T: std::io::Read>(mut bs: BitstreamReader<T>) -> u64 {
+    let mut sum = 0u64;
+    let mut n = 1;
+    while let Ok(value) = bs.f(n) {
+        sum = sum.wrapping_add(value as u64);
+        n = n % 32 + 1;
+    }
+    sum
+}
+
+fn bench(name: &str, buf: &[u8], f: impl Fn(&[u8]) -> u64) -> u64 {
+    let mut best = Duration::MAX;
+    let mut result = 0;
+    for _ in 0..ITERATIONS {
+        let start = Instant::now();
+        result = black_box(f(black_box(buf)));
+        best = best.min(start.elapsed());
+    }
+
+    let throughput = buf.len() as f64 / best.as_secs_f64() / (1024.0 * 1024.0);
+    println!("{:<8} {:>10.2?} {:>10.1} MiB/s", name, best, throughput);
+    result
+}
+
+fn main() {
+    let buf: Vec<u8> = (0..BUF_LEN as u32)
+        .map(|i| (i.wrapping_mul(2654435761) >> 13) as u8)
+        .collect();
+
+    let cursor = bench("cursor", &buf, |buf| {
+        let mut cursor = Cursor::new(buf);
+        read_all(BitstreamReader::new(&mut cursor).unwrap())
+    });
+    let slice = bench("slice", &buf, |buf| {
+        read_all(BitstreamReader::from_slice(buf))
+    });
+    assert_eq!(cursor, slice);
+
+    let path = std::env::temp_dir().join(format!("ranalyzer-bench-{}", std::process::id()));
+    std::fs::write(&path, &buf[..FILE_LEN]).unwrap();
+    let file = bench("file", &buf[..FILE_LEN], |_| {
+        let mut file = std::fs::File::open(&path).unwrap();
+        read_all(BitstreamReader::new(&mut file).unwrap())
+    });
+    let slice = bench("slice", &buf[..FILE_LEN], |buf| {
+        read_all(BitstreamReader::from_slice(buf))
+    });
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(file, slice);
+}
@@ -0,0 +1,332 @@
+use alloc::vec::Vec;
+
+use crate::av1::obu::{iter_obus, push_leb128, Obu, ObuType, ParseOptions};
+use crate::av1::temporal_unit::{group_temporal_units, TemporalUnit};
+use crate::bits::BitstreamReader;
+use crate::Error;
+
+/// Iterates over the temporal units of a length delimited bitstream - Annex B
+pub struct AnnexBIterator<'a> {
+    buf: &'a [u8],
+    options: ParseOptions,
+}
+
+impl<'a> Iterator for AnnexBIterator<'a> {
+    type Item = Result<TemporalUnit<'a>, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.buf.is_empty() {
+            return None;
+        }
+
+        match split_length_delimited(self.buf).and_then(|(temporal_unit, rest)| {
+            Ok((parse_temporal_unit(temporal_unit, &self.options)?, rest))
+        }) {
+            Ok((temporal_unit, rest)) => {
+                self.buf = rest;
+                Some(Ok(temporal_unit))
+            }
+            Err(err) => {
+                // There's no way to resynchronize after a malformed length
+                self.buf = &[];
+                Some(Err(err))
+            }
+        }
+    }
+}
+
+/// Iterates over the temporal units of a buffer in the length delimited bitstream format
+/// from Annex B
+pub fn iter_annexb(buf: &[u8]) -> AnnexBIterator<'_> {
+    iter_annexb_with_options(buf, ParseOptions::default())
+}
+
+pub fn iter_annexb_with_options(buf: &[u8], options: ParseOptions) -> AnnexBIterator<'_> {
+    AnnexBIterator { buf, options }
+}
+
+/// temporal_unit() - B.2, the frame units of a temporal unit
+fn parse_temporal_unit<'a>(
+    mut buf: &'a [u8],
+    options: &ParseOptions,
+) -> Result<TemporalUnit<'a>, Error> {
+    let mut obus = Vec::new();
+    while !buf.is_empty() {
+        let (mut frame_unit, rest) = split_length_delimited(buf)?;
+        buf = rest;
+
+        // frame_unit() - B.2
+        while !frame_unit.is_empty() {
+            let (obu_buf, rest) = split_length_delimited(frame_unit)?;
+            frame_unit = rest;
+
+            let obu = Obu::from_buf_with_options(obu_buf, options)?;
+            if obu.buf().len() != obu_buf.len() {
+                return Err(Error::Invalid("obu_size doesn't match obu_length"));
+            }
+            obus.push(obu);
+        }
+    }
+
+    Ok(TemporalUnit { obus })
+}
+
+/// The number of OBUs trial parsed by `detect_framing`
+const DETECT_FRAMING_OBUS: usize = 8;
+
+/// How the OBUs of a bitstream are framed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Framing {
+    /// The low overhead bitstream format - 5.2, where every OBU has obu_size
+    LowOverhead,
+    /// The length delimited bitstream format - Annex B
+    AnnexB,
+    /// Neither format parses, or both do
+    Unknown,
+}
+
+/// Guesses the framing of a raw OBU dump by trial parsing its first few OBUs as each format.
+/// Both formats start with a temporal delimiter, and the low overhead format needs obu_size on
+/// every OBU, so only one of them usually parses.
+pub fn detect_framing(buf: &[u8]) -> Framing {
+    match (is_low_overhead(buf), is_annexb(buf)) {
+        (true, false) => Framing::LowOverhead,
+        (false, true) => Framing::AnnexB,
+        _ => Framing::Unknown,
+    }
+}
+
+fn is_low_overhead(buf: &[u8]) -> bool {
+    let mut obus = iter_obus(buf).take(DETECT_FRAMING_OBUS).peekable();
+    let starts_with_td = matches!(
+        obus.peek(),
+        Some(Ok(obu)) if obu.header.obu_type == ObuType::TemporalDelimiter
+    );
+    starts_with_td && obus.all(|obu| obu.is_ok_and(|obu| obu.header.obu_has_size_field))
+}
+
+fn is_annexb(buf: &[u8]) -> bool {
+    let mut obus = 0;
+    for temporal_unit in iter_annexb(buf) {
+        let Ok(temporal_unit) = temporal_unit else {
+            return false;
+        };
+        let first = temporal_unit.obus().first();
+        if !first.is_some_and(|obu| obu.header.obu_type == ObuType::TemporalDelimiter) {
+            return false;
+        }
+
+        obus += temporal_unit.obus().len();
+        if obus >= DETECT_FRAMING_OBUS {
+            break;
+        }
+    }
+    obus > 0
+}
+
+/// Converts a length delimited bitstream to the low overhead bitstream format, with every OBU
+/// given an obu_size
+pub fn annexb_to_low_overhead(buf: &[u8]) -> Result<Vec<u8>, Error> {
+    let mut out = Vec::with_capacity(buf.len());
+    for temporal_unit in iter_annexb(buf) {
+        for obu in temporal_unit?.obus() {
+            out.extend_from_slice(&obu.header_bytes(true));
+            out.extend_from_slice(obu.payload());
+        }
+    }
+    Ok(out)
+}
+
+/// Converts a low overhead bitstream to the length delimited bitstream format, with the
+/// obu_size fields dropped
+///
+/// Each frame unit starts at a frame header or frame OBU, with any OBUs before it (the
+/// temporal delimiter, sequence headers and metadata) joining it.
+pub fn low_overhead_to_annexb(buf: &[u8]) -> Result<Vec<u8>, Error> {
+    let mut out = Vec::with_capacity(buf.len());
+    for temporal_unit in group_temporal_units(buf)? {
+        let mut frame_units: Vec<Vec<u8>> = Vec::new();
+        let mut frame_unit = Vec::new();
+        let mut has_frame = false;
+        for obu in temporal_unit.obus() {
+            let starts_frame = matches!(obu.header.obu_type, ObuType::FrameHeader | ObuType::Frame);
+            if starts_frame && has_frame {
+                frame_units.push(core::mem::take(&mut frame_unit));
+            }
+            has_frame |= starts_frame;
+
+            let header = obu.header_bytes(false);
+            push_leb128(&mut frame_unit, (header.len() + obu.payload().len()) as u64);
+            frame_unit.extend_from_slice(&header);
+            frame_unit.extend_from_slice(obu.payload());
+        }
+        frame_units.push(frame_unit);
+
+        let mut temporal_unit = Vec::new();
+        for frame_unit in frame_units {
+            push_leb128(&mut temporal_unit, frame_unit.len() as u64);
+            temporal_unit.extend_from_slice(&frame_unit);
+        }
+        push_leb128(&mut out, temporal_unit.len() as u64);
+        out.extend_from_slice(&temporal_unit);
+    }
+    Ok(out)
+}
+
+/// Splits a leb128 size prefixed unit off the start of `buf`, returning the unit and the
+/// rest of the buffer
+fn split_length_delimited(buf: &[u8]) -> Result<(&[u8], &[u8]), Error> {
+    let mut reader = BitstreamReader::from_slice(buf);
+    let (size, len) = reader.leb128_with_len()?;
+
+    let start = len as usize;
+    let end = usize::try_from(size)
+        .ok()
+        .and_then(|size| start.checked_add(size))
+        .filter(|end| *end <= buf.len())
+        .ok_or(Error::Invalid("length exceeds the buffer"))?;
+    Ok((&buf[start..end], &buf[end..]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::av1::obu::{iter_obus, ObuType};
+    use crate::av1::test_fixtures::SEQ_64X64;
+    use alloc::vec;
+
+    #[test]
+    fn annexb_temporal_unit() {
+        // A temporal delimiter, sequence header and padding in one frame unit, then a second
+        // frame unit with a padding OBU that has its own obu_size
+        let mut annexb = vec![0x18, 0x12, 0x01, 0x10, 0x0b, 0x08];
+        annexb.extend_from_slice(&SEQ_64X64);
+        annexb.extend_from_slice(&[0x03, 0x78, 0xaa, 0xbb, 0x04, 0x03, 0x7a, 0x01, 0xcc]);
+        // A second temporal unit with just a temporal delimiter
+        annexb.extend_from_slice(&[0x03, 0x02, 0x01, 0x10]);
+
+        let mut low_overhead = vec![0x12, 0x00, 0x0a, 0x0a];
+        low_overhead.extend_from_slice(&SEQ_64X64);
+        low_overhead.extend_from_slice(&[0x7a, 0x02, 0xaa, 0xbb, 0x7a, 0x01, 0xcc, 0x12, 0x00]);
+
+        let temporal_units: Vec<TemporalUnit> =
+            iter_annexb(&annexb).map(|tu| tu.unwrap()).collect();
+        assert_eq!(temporal_units.len(), 2);
+        assert_eq!(temporal_units[1].obus().len(), 1);
+
+        let annexb_obus: Vec<Obu> = temporal_units
+            .iter()
+            .flat_map(|tu| tu.obus().iter().copied())
+            .collect();
+        let low_overhead_obus: Vec<Obu> =
+            iter_obus(&low_overhead).map(|obu| obu.unwrap()).collect();
+        assert_eq!(annexb_obus.len(), low_overhead_obus.len());
+        for (annexb_obu, obu) in annexb_obus.iter().zip(&low_overhead_obus) {
+            assert_eq!(annexb_obu.header.obu_type, obu.header.obu_type);
+            assert_eq!(annexb_obu.payload(), obu.payload());
+        }
+        assert_eq!(annexb_obus[1].header.obu_type, ObuType::SequenceHeader);
+    }
+
+    #[test]
+    fn annexb_obu_size_mismatch() {
+        // obu_length = 4 but obu_size = 1
+        const TEST_BUF: [u8; 7] = [0x06, 0x05, 0x04, 0x7a, 0x01, 0xcc, 0xdd];
+
+        let err = iter_annexb(&TEST_BUF).next().unwrap().unwrap_err();
+        assert!(matches!(err, Error::Invalid(_)));
+    }
+
+    #[test]
+    fn annexb_max_obu_size() {
+        const TEST_BUF: [u8; 7] = [0x06, 0x05, 0x04, 0x78, 0xaa, 0xbb, 0xcc];
+
+        assert!(iter_annexb(&TEST_BUF).next().unwrap().is_ok());
+        let options = ParseOptions {
+            max_obu_size: 2,
+            ..Default::default()
+        };
+        let err = iter_annexb_with_options(&TEST_BUF, options)
+            .next()
+            .unwrap()
+            .unwrap_err();
+        assert!(matches!(err, Error::Invalid(_)));
+    }
+
+    #[test]
+    fn annexb_truncated() {
+        // temporal_unit_size = 8 with only 3 bytes following
+        const TEST_BUF: [u8; 4] = [0x08, 0x02, 0x01, 0x10];
+
+        let mut temporal_units = iter_annexb(&TEST_BUF);
+        let err = temporal_units.next().unwrap().unwrap_err();
+        assert!(matches!(err, Error::Invalid(_)));
+        assert!(temporal_units.next().is_none());
+    }
+
+    #[test]
+    fn convert_round_trip() {
+        // Two temporal units, the first with a sequence header, metadata and two frame
+        // headers (one with a tile group) and the second with a frame and padding. The
+        // padding OBU has a two byte obu_size.
+        let mut low_overhead = vec![0x12, 0x00, 0x0a, 0x0a];
+        low_overhead.extend_from_slice(&SEQ_64X64);
+        low_overhead.extend_from_slice(&[0x2e, 0x28, 0x02, 0x01, 0x00]);
+        low_overhead.extend_from_slice(&[0x1a, 0x02, 0xaa, 0xbb, 0x22, 0x01, 0xcc]);
+        low_overhead.extend_from_slice(&[0x1a, 0x01, 0xdd]);
+        low_overhead.extend_from_slice(&[0x12, 0x00, 0x32, 0x03, 0x01, 0x02, 0x03]);
+        low_overhead.extend_from_slice(&[0x7a, 0x80, 0x01]);
+        low_overhead.extend_from_slice(&[0xee; 128]);
+
+        let annexb = low_overhead_to_annexb(&low_overhead).unwrap();
+        let temporal_units: Vec<TemporalUnit> =
+            iter_annexb(&annexb).map(|tu| tu.unwrap()).collect();
+        assert_eq!(temporal_units.len(), 2);
+        assert_eq!(temporal_units[0].obus().len(), 6);
+        assert_eq!(temporal_units[1].obus().len(), 3);
+        assert!(temporal_units
+            .iter()
+            .flat_map(|tu| tu.obus())
+            .all(|obu| !obu.header.obu_has_size_field));
+        // The first frame unit is 26 bytes, ending before the second frame header
+        assert_eq!(split_length_delimited(&annexb).unwrap().0[0], 0x1a);
+
+        assert_eq!(annexb_to_low_overhead(&annexb).unwrap(), low_overhead);
+    }
+
+    #[test]
+    fn detect_both_framings() {
+        let mut low_overhead = vec![0x12, 0x00, 0x0a, 0x0a];
+        low_overhead.extend_from_slice(&SEQ_64X64);
+        low_overhead.extend_from_slice(&[0x7a, 0x02, 0xaa, 0xbb]);
+        for _ in 0..10 {
+            low_overhead.extend_from_slice(&[0x12, 0x00, 0x7a, 0x01, 0xcc]);
+        }
+        let annexb = low_overhead_to_annexb(&low_overhead).unwrap();
+
+        assert_eq!(detect_framing(&low_overhead), Framing::LowOverhead);
+        assert_eq!(detect_framing(&annexb), Framing::AnnexB);
+        // Only the first few OBUs need to be complete
+        assert_eq!(
+            detect_framing(&low_overhead[..low_overhead.len() - 1]),
+            Framing::LowOverhead
+        );
+
+        assert_eq!(detect_framing(&[]), Framing::Unknown);
+        assert_eq!(detect_framing(&SEQ_64X64), Framing::Unknown);
+        // A low overhead stream that doesn't start with a temporal delimiter
+        assert_eq!(detect_framing(&low_overhead[2..]), Framing::Unknown);
+    }
+
+    #[test]
+    fn convert_errors() {
+        assert!(matches!(
+            low_overhead_to_annexb(&[0x0a, 0x00]),
+            Err(Error::Invalid(_))
+        ));
+        assert!(matches!(
+            annexb_to_low_overhead(&[0x08, 0x02, 0x01, 0x10]),
+            Err(Error::Invalid(_))
+        ));
+    }
+}
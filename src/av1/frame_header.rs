@@ -0,0 +1,3072 @@
+use alloc::vec;
+use alloc::vec::Vec;
+
+use crate::av1::obu::{
+    ObuExtensionHeader, SequenceHeaderObu, SELECT_INTEGER_MV, SELECT_SCREEN_CONTENT_TOOLS,
+};
+use crate::bits::{BitstreamReader, ByteRead};
+use crate::Error;
+
+pub const REFS_PER_FRAME: usize = 7;
+pub const TOTAL_REFS_PER_FRAME: usize = 8;
+pub const NUM_REF_FRAMES: usize = 8;
+pub const PRIMARY_REF_NONE: u8 = 7;
+pub const MAX_SEGMENTS: usize = 8;
+pub const SEG_LVL_ALT_Q: usize = 0;
+pub const SEG_LVL_REF_FRAME: usize = 5;
+pub const SEG_LVL_MAX: usize = 8;
+pub const MAX_LOOP_FILTER: i32 = 63;
+/// The number of CDEF strength sets that can be coded, 1 << 3 for the maximum cdef_bits
+pub const CDEF_STRENGTHS: usize = 8;
+pub const MAX_TILE_WIDTH: u32 = 4096;
+pub const MAX_TILE_AREA: u32 = 4096 * 2304;
+pub const MAX_TILE_ROWS: u32 = 64;
+pub const MAX_TILE_COLS: u32 = 64;
+pub const WARPEDMODEL_PREC_BITS: u8 = 16;
+pub const GM_ABS_TRANS_BITS: u8 = 12;
+pub const GM_ABS_TRANS_ONLY_BITS: u8 = 9;
+pub const GM_ABS_ALPHA_BITS: u8 = 12;
+pub const GM_ALPHA_PREC_BITS: u8 = 15;
+pub const GM_TRANS_PREC_BITS: u8 = 6;
+pub const GM_TRANS_ONLY_PREC_BITS: u8 = 3;
+
+pub const SUPERRES_NUM: u32 = 8;
+pub const SUPERRES_DENOM_MIN: u32 = 9;
+pub const SUPERRES_DENOM_BITS: u8 = 3;
+
+pub const KEY_FRAME: u8 = 0;
+pub const INTER_FRAME: u8 = 1;
+pub const INTRA_ONLY_FRAME: u8 = 2;
+pub const SWITCH_FRAME: u8 = 3;
+
+pub const LAST_FRAME: usize = 1;
+pub const LAST2_FRAME: usize = 2;
+pub const LAST3_FRAME: usize = 3;
+pub const GOLDEN_FRAME: usize = 4;
+pub const BWDREF_FRAME: usize = 5;
+pub const ALTREF2_FRAME: usize = 6;
+pub const ALTREF_FRAME: usize = 7;
+
+pub const IDENTITY: u8 = 0;
+pub const TRANSLATION: u8 = 1;
+pub const ROTZOOM: u8 = 2;
+pub const AFFINE: u8 = 3;
+
+pub const SWITCHABLE: u8 = 4;
+
+pub const RESTORE_NONE: u8 = 0;
+pub const RESTORE_WIENER: u8 = 1;
+pub const RESTORE_SGRPROJ: u8 = 2;
+pub const RESTORE_SWITCHABLE: u8 = 3;
+pub const RESTORATION_TILESIZE_MAX: u32 = 256;
+
+pub const MAX_NUM_Y_POINTS: usize = 14;
+pub const MAX_NUM_CHROMA_POINTS: usize = 10;
+/// The number of luma AR coefficients for the maximum ar_coeff_lag of 3
+pub const MAX_NUM_POS_LUMA: usize = 24;
+
+pub const ONLY_4X4: u8 = 0;
+pub const TX_MODE_LARGEST: u8 = 1;
+pub const TX_MODE_SELECT: u8 = 2;
+
+const SEGMENTATION_FEATURE_BITS: [u8; SEG_LVL_MAX] = [8, 6, 6, 6, 6, 3, 0, 0];
+const SEGMENTATION_FEATURE_SIGNED: [bool; SEG_LVL_MAX] =
+    [true, true, true, true, true, false, false, false];
+const SEGMENTATION_FEATURE_MAX: [i32; SEG_LVL_MAX] = [
+    255,
+    MAX_LOOP_FILTER,
+    MAX_LOOP_FILTER,
+    MAX_LOOP_FILTER,
+    MAX_LOOP_FILTER,
+    7,
+    0,
+    0,
+];
+
+const REMAP_LR_TYPE: [u8; 4] = [
+    RESTORE_NONE,
+    RESTORE_SWITCHABLE,
+    RESTORE_WIENER,
+    RESTORE_SGRPROJ,
+];
+const DEFAULT_LOOP_FILTER_REF_DELTAS: [i8; TOTAL_REFS_PER_FRAME] = [1, 0, 0, 0, -1, 0, -1, -1];
+const DEFAULT_GM_PARAMS: [[i32; 6]; NUM_REF_FRAMES] = [[
+    0,
+    0,
+    1 << WARPEDMODEL_PREC_BITS,
+    0,
+    0,
+    1 << WARPEDMODEL_PREC_BITS,
+]; NUM_REF_FRAMES];
+
+/// The frame_type of a frame header - 6.8.2
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum FrameType {
+    Key,
+    Inter,
+    IntraOnly,
+    Switch,
+}
+
+/// The frame dimensions decoded by frame_size() - 5.9.5
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct FrameSize {
+    /// FrameWidth, after any superres downscaling
+    pub frame_width: u32,
+    pub frame_height: u32,
+    /// UpscaledWidth, the width before superres downscaling
+    pub upscaled_width: u32,
+    pub superres_params: SuperresParams,
+}
+
+/// superres_params() - 5.9.8
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct SuperresParams {
+    pub use_superres: bool,
+    pub coded_denom: u8,
+    /// SuperresDenom, SUPERRES_NUM when superres isn't used
+    pub superres_denom: u32,
+}
+
+impl Default for SuperresParams {
+    fn default() -> Self {
+        Self {
+            use_superres: false,
+            coded_denom: 0,
+            superres_denom: SUPERRES_NUM,
+        }
+    }
+}
+
+impl SuperresParams {
+    /// FrameWidth for a frame with the given UpscaledWidth
+    pub fn frame_width(&self, upscaled_width: u32) -> u32 {
+        (upscaled_width * SUPERRES_NUM + self.superres_denom / 2) / self.superres_denom
+    }
+}
+
+/// quantization_params() - 5.9.12
+///
+/// The chroma deltas are zero for monochrome streams, and the V deltas and qm_v copy the U
+/// values when they aren't coded separately.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct QuantizationParams {
+    pub base_q_idx: u8,
+    pub delta_q_y_dc: i8,
+    pub diff_uv_delta: bool,
+    pub delta_q_u_dc: i8,
+    pub delta_q_u_ac: i8,
+    pub delta_q_v_dc: i8,
+    pub delta_q_v_ac: i8,
+    pub using_qmatrix: bool,
+    pub qm_y: u8,
+    pub qm_u: u8,
+    pub qm_v: u8,
+}
+
+impl QuantizationParams {
+    /// Whether all the DC and AC deltas are zero, as required for CodedLossless
+    pub fn deltas_zero(&self) -> bool {
+        self.delta_q_y_dc == 0
+            && self.delta_q_u_dc == 0
+            && self.delta_q_u_ac == 0
+            && self.delta_q_v_dc == 0
+            && self.delta_q_v_ac == 0
+    }
+}
+
+/// segmentation_params() - 5.9.14
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct SegmentationParams {
+    pub segmentation_enabled: bool,
+    pub segmentation_update_map: bool,
+    pub segmentation_temporal_update: bool,
+    pub segmentation_update_data: bool,
+    /// FeatureEnabled, including any values carried over from the primary reference frame
+    pub feature_enabled: [[bool; SEG_LVL_MAX]; MAX_SEGMENTS],
+    /// FeatureData, including any values carried over from the primary reference frame
+    pub feature_data: [[i16; SEG_LVL_MAX]; MAX_SEGMENTS],
+    /// SegIdPreSkip
+    pub seg_id_pre_skip: bool,
+    /// LastActiveSegId
+    pub last_active_seg_id: u8,
+}
+
+/// loop_filter_params() - 5.9.11
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct LoopFilterParams {
+    pub loop_filter_level: [u8; 4],
+    pub loop_filter_sharpness: u8,
+    pub loop_filter_delta_enabled: bool,
+    pub loop_filter_delta_update: bool,
+    /// loop_filter_ref_deltas, including any values carried over from the primary reference
+    /// frame
+    pub loop_filter_ref_deltas: [i8; TOTAL_REFS_PER_FRAME],
+    pub loop_filter_mode_deltas: [i8; 2],
+}
+
+impl Default for LoopFilterParams {
+    fn default() -> Self {
+        Self {
+            loop_filter_level: [0; 4],
+            loop_filter_sharpness: 0,
+            loop_filter_delta_enabled: false,
+            loop_filter_delta_update: false,
+            loop_filter_ref_deltas: DEFAULT_LOOP_FILTER_REF_DELTAS,
+            loop_filter_mode_deltas: [0; 2],
+        }
+    }
+}
+
+/// cdef_params() - 5.9.19
+///
+/// Only the first 1 << cdef_bits strengths are coded, the rest are zero. A coded secondary
+/// strength of 3 is stored as 4.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct CdefParams {
+    /// CdefDamping
+    pub cdef_damping: u8,
+    pub cdef_bits: u8,
+    pub cdef_y_pri_strength: [u8; CDEF_STRENGTHS],
+    pub cdef_y_sec_strength: [u8; CDEF_STRENGTHS],
+    pub cdef_uv_pri_strength: [u8; CDEF_STRENGTHS],
+    pub cdef_uv_sec_strength: [u8; CDEF_STRENGTHS],
+}
+
+impl Default for CdefParams {
+    fn default() -> Self {
+        Self {
+            cdef_damping: 3,
+            cdef_bits: 0,
+            cdef_y_pri_strength: [0; CDEF_STRENGTHS],
+            cdef_y_sec_strength: [0; CDEF_STRENGTHS],
+            cdef_uv_pri_strength: [0; CDEF_STRENGTHS],
+            cdef_uv_sec_strength: [0; CDEF_STRENGTHS],
+        }
+    }
+}
+
+/// lr_params() - 5.9.20
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct LoopRestorationParams {
+    /// FrameRestorationType of each plane, one of the RESTORE_* values
+    pub frame_restoration_type: [u8; 3],
+    /// UsesLr
+    pub uses_lr: bool,
+    /// LoopRestorationSize of each plane in samples, zero when loop restoration isn't used
+    pub loop_restoration_size: [u32; 3],
+}
+
+/// global_motion_params() - 5.9.24
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct GlobalMotionParams {
+    /// GmType of each reference frame, one of IDENTITY, TRANSLATION, ROTZOOM or AFFINE
+    pub gm_type: [u8; NUM_REF_FRAMES],
+    /// gm_params, the warp model of each reference frame
+    pub gm_params: [[i32; 6]; NUM_REF_FRAMES],
+}
+
+impl Default for GlobalMotionParams {
+    fn default() -> Self {
+        Self {
+            gm_type: [IDENTITY; NUM_REF_FRAMES],
+            gm_params: DEFAULT_GM_PARAMS,
+        }
+    }
+}
+
+/// film_grain_params() - 5.9.30
+///
+/// All zeros when grain isn't applied. When update_grain is 0 the parameters are loaded from
+/// the reference frame at film_grain_params_ref_idx, keeping this frame's grain_seed.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct FilmGrainParams {
+    pub apply_grain: bool,
+    pub grain_seed: u16,
+    pub update_grain: bool,
+    pub film_grain_params_ref_idx: u8,
+    pub num_y_points: u8,
+    pub point_y_value: [u8; MAX_NUM_Y_POINTS],
+    pub point_y_scaling: [u8; MAX_NUM_Y_POINTS],
+    pub chroma_scaling_from_luma: bool,
+    pub num_cb_points: u8,
+    pub point_cb_value: [u8; MAX_NUM_CHROMA_POINTS],
+    pub point_cb_scaling: [u8; MAX_NUM_CHROMA_POINTS],
+    pub num_cr_points: u8,
+    pub point_cr_value: [u8; MAX_NUM_CHROMA_POINTS],
+    pub point_cr_scaling: [u8; MAX_NUM_CHROMA_POINTS],
+    pub grain_scaling_minus_8: u8,
+    pub ar_coeff_lag: u8,
+    pub ar_coeffs_y_plus_128: [u8; MAX_NUM_POS_LUMA],
+    pub ar_coeffs_cb_plus_128: [u8; MAX_NUM_POS_LUMA + 1],
+    pub ar_coeffs_cr_plus_128: [u8; MAX_NUM_POS_LUMA + 1],
+    pub ar_coeff_shift_minus_6: u8,
+    pub grain_scale_shift: u8,
+    pub cb_mult: u8,
+    pub cb_luma_mult: u8,
+    pub cb_offset: u16,
+    pub cr_mult: u8,
+    pub cr_luma_mult: u8,
+    pub cr_offset: u16,
+    pub overlap_flag: bool,
+    pub clip_to_restricted_range: bool,
+}
+
+impl FilmGrainParams {
+    /// Whether the parameters were loaded from the reference frame at
+    /// film_grain_params_ref_idx rather than coded in this frame header
+    pub fn is_inherited(&self) -> bool {
+        self.apply_grain && !self.update_grain
+    }
+}
+
+/// frame_size() - 5.9.5
+pub fn parse_frame_size<T: ByteRead>(
+    reader: &mut BitstreamReader<T>,
+    seq: &SequenceHeaderObu,
+    frame_size_override_flag: bool,
+) -> Result<FrameSize, Error> {
+    let (frame_width, frame_height) = if frame_size_override_flag {
+        let frame_width_minus_1 = reader.f(seq.frame_width_bits_minus_1 + 1)?;
+        let frame_height_minus_1 = reader.f(seq.frame_height_bits_minus_1 + 1)?;
+        (frame_width_minus_1 + 1, frame_height_minus_1 + 1)
+    } else {
+        (
+            seq.max_frame_width_minus_1 + 1,
+            seq.max_frame_height_minus_1 + 1,
+        )
+    };
+
+    superres_frame_size(reader, seq, frame_width, frame_height)
+}
+
+/// superres_params() - 5.9.8
+pub fn parse_superres_params<T: ByteRead>(
+    reader: &mut BitstreamReader<T>,
+    seq: &SequenceHeaderObu,
+) -> Result<SuperresParams, Error> {
+    let use_superres = seq.enable_superres && reader.f1()?;
+    if !use_superres {
+        return Ok(SuperresParams::default());
+    }
+
+    let coded_denom = reader.f(SUPERRES_DENOM_BITS)? as u8;
+    Ok(SuperresParams {
+        use_superres,
+        coded_denom,
+        superres_denom: coded_denom as u32 + SUPERRES_DENOM_MIN,
+    })
+}
+
+/// superres_params() - 5.9.8, applied to the upscaled frame size
+fn superres_frame_size<T: ByteRead>(
+    reader: &mut BitstreamReader<T>,
+    seq: &SequenceHeaderObu,
+    upscaled_width: u32,
+    frame_height: u32,
+) -> Result<FrameSize, Error> {
+    let superres_params = parse_superres_params(reader, seq)?;
+    Ok(FrameSize {
+        frame_width: superres_params.frame_width(upscaled_width),
+        frame_height,
+        upscaled_width,
+        superres_params,
+    })
+}
+
+/// render_size() - 5.9.6
+pub fn parse_render_size<T: ByteRead>(
+    reader: &mut BitstreamReader<T>,
+    frame_size: &FrameSize,
+) -> Result<(u32, u32), Error> {
+    let render_and_frame_size_different = reader.f1()?;
+    if render_and_frame_size_different {
+        let render_width_minus_1 = reader.f(16)?;
+        let render_height_minus_1 = reader.f(16)?;
+        Ok((render_width_minus_1 + 1, render_height_minus_1 + 1))
+    } else {
+        Ok((frame_size.upscaled_width, frame_size.frame_height))
+    }
+}
+
+/// frame_header_obu() and uncompressed_header() - 5.9.1, 5.9.2
+///
+/// Frames that are shown with show_existing_frame take their frame_type, order_hint and
+/// dimensions from the reference frame being shown.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct FrameHeaderObu {
+    pub show_existing_frame: bool,
+    pub frame_to_show_map_idx: u8,
+    /// display_frame_id, when show_existing_frame is set and frame ids are present
+    pub display_frame_id: Option<u32>,
+    /// frame_presentation_time, when the decoder model codes it for shown frames
+    pub frame_presentation_time: Option<u32>,
+    pub frame_type: u8,
+    pub frame_is_intra: bool,
+    pub show_frame: bool,
+    pub showable_frame: bool,
+    pub error_resilient_mode: bool,
+    pub disable_cdf_update: bool,
+    pub allow_screen_content_tools: bool,
+    pub force_integer_mv: bool,
+    pub current_frame_id: u32,
+    pub frame_size_override_flag: bool,
+    pub order_hint: u32,
+    pub primary_ref_frame: u8,
+    pub refresh_frame_flags: u8,
+    /// ref_frame_idx, the slots used as LAST_FRAME through ALTREF_FRAME, all zero for intra
+    /// frames
+    pub ref_frame_idx: [u8; REFS_PER_FRAME],
+    /// delta_frame_id_minus_1 for each of ref_frame_idx, when frame ids are present in an inter
+    /// frame
+    pub delta_frame_id_minus_1: Option<[u32; REFS_PER_FRAME]>,
+    pub frame_size: FrameSize,
+    pub render_width: u32,
+    pub render_height: u32,
+    pub allow_intrabc: bool,
+    pub allow_high_precision_mv: bool,
+    pub interpolation_filter: u8,
+    pub is_motion_mode_switchable: bool,
+    pub use_ref_frame_mvs: bool,
+    pub disable_frame_end_update_cdf: bool,
+    pub tile_info: TileInfo,
+    pub quantization_params: QuantizationParams,
+    pub segmentation_params: SegmentationParams,
+    pub delta_q_present: bool,
+    pub delta_q_res: u8,
+    pub delta_lf_present: bool,
+    pub delta_lf_res: u8,
+    pub delta_lf_multi: bool,
+    pub coded_lossless: bool,
+    pub all_lossless: bool,
+    pub loop_filter_params: LoopFilterParams,
+    pub cdef_params: CdefParams,
+    pub lr_params: LoopRestorationParams,
+    pub tx_mode: u8,
+    pub reference_select: bool,
+    pub skip_mode_present: bool,
+    pub allow_warped_motion: bool,
+    pub reduced_tx_set: bool,
+    pub global_motion_params: GlobalMotionParams,
+    pub film_grain_params: FilmGrainParams,
+
+    // State saved into the reference frames refreshed by this frame - 7.20
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub(crate) ref_order_hint: Option<[u32; NUM_REF_FRAMES]>,
+}
+
+impl FrameHeaderObu {
+    /// The frame_type, which for show_existing_frame is that of the frame being shown
+    pub fn frame_type(&self) -> FrameType {
+        // frame_type is read with f(2), so anything else is a SWITCH_FRAME
+        match self.frame_type {
+            KEY_FRAME => FrameType::Key,
+            INTER_FRAME => FrameType::Inter,
+            INTRA_ONLY_FRAME => FrameType::IntraOnly,
+            _ => FrameType::Switch,
+        }
+    }
+
+    /// Whether this is a coded key frame, which starts a new random access point. Showing an
+    /// existing key frame isn't one, as it depends on the earlier frame that coded it.
+    pub fn is_keyframe(&self) -> bool {
+        !self.show_existing_frame && self.frame_type() == FrameType::Key
+    }
+
+    /// Whether the frame is a key frame or intra only frame
+    pub fn is_intra(&self) -> bool {
+        matches!(self.frame_type(), FrameType::Key | FrameType::IntraOnly)
+    }
+
+    /// The reference frame slot shown by a show_existing_frame frame header
+    pub fn shown_frame_slot(&self) -> Option<usize> {
+        self.show_existing_frame
+            .then_some(self.frame_to_show_map_idx as usize)
+    }
+
+    /// The reference frame slots set in refresh_frame_flags, in increasing order
+    pub fn refreshed_slots(&self) -> impl Iterator<Item = usize> + '_ {
+        (0..NUM_REF_FRAMES).filter(|i| self.refresh_frame_flags & (1 << i) != 0)
+    }
+}
+
+impl Default for FrameHeaderObu {
+    fn default() -> Self {
+        Self {
+            show_existing_frame: false,
+            frame_to_show_map_idx: 0,
+            display_frame_id: None,
+            frame_presentation_time: None,
+            frame_type: KEY_FRAME,
+            frame_is_intra: true,
+            show_frame: true,
+            showable_frame: false,
+            error_resilient_mode: false,
+            disable_cdf_update: false,
+            allow_screen_content_tools: false,
+            force_integer_mv: false,
+            current_frame_id: 0,
+            frame_size_override_flag: false,
+            order_hint: 0,
+            primary_ref_frame: PRIMARY_REF_NONE,
+            refresh_frame_flags: 0,
+            ref_frame_idx: [0; REFS_PER_FRAME],
+            delta_frame_id_minus_1: None,
+            frame_size: FrameSize::default(),
+            render_width: 0,
+            render_height: 0,
+            allow_intrabc: false,
+            allow_high_precision_mv: false,
+            interpolation_filter: 0,
+            is_motion_mode_switchable: false,
+            use_ref_frame_mvs: false,
+            disable_frame_end_update_cdf: false,
+            tile_info: TileInfo::default(),
+            quantization_params: QuantizationParams::default(),
+            segmentation_params: SegmentationParams::default(),
+            delta_q_present: false,
+            delta_q_res: 0,
+            delta_lf_present: false,
+            delta_lf_res: 0,
+            delta_lf_multi: false,
+            coded_lossless: false,
+            all_lossless: false,
+            loop_filter_params: LoopFilterParams::default(),
+            cdef_params: CdefParams::default(),
+            lr_params: LoopRestorationParams::default(),
+            tx_mode: ONLY_4X4,
+            reference_select: false,
+            skip_mode_present: false,
+            allow_warped_motion: false,
+            reduced_tx_set: false,
+            global_motion_params: GlobalMotionParams::default(),
+            film_grain_params: FilmGrainParams::default(),
+            ref_order_hint: None,
+        }
+    }
+}
+
+/// The tile layout decoded by tile_info() - 5.9.15
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct TileInfo {
+    pub uniform_tile_spacing_flag: bool,
+    pub tile_cols: u32,
+    pub tile_rows: u32,
+    pub tile_cols_log2: u32,
+    pub tile_rows_log2: u32,
+    /// MiColStarts, the starting column of each tile in units of 4x4 blocks, followed by
+    /// MiCols
+    pub mi_col_starts: Vec<u32>,
+    /// MiRowStarts, the starting row of each tile in units of 4x4 blocks, followed by
+    /// MiRows
+    pub mi_row_starts: Vec<u32>,
+    pub context_update_tile_id: u32,
+    /// TileSizeBytes, zero when the frame has a single tile
+    pub tile_size_bytes: u8,
+}
+
+/// The state kept for a reference frame slot - 7.20
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RefFrame {
+    pub valid: bool,
+    pub frame_id: u32,
+    pub frame_type: u8,
+    pub order_hint: u32,
+    pub frame_size: FrameSize,
+    pub render_width: u32,
+    pub render_height: u32,
+    pub(crate) gm_params: [[i32; 6]; NUM_REF_FRAMES],
+    pub(crate) loop_filter_ref_deltas: [i8; TOTAL_REFS_PER_FRAME],
+    pub(crate) loop_filter_mode_deltas: [i8; 2],
+    pub(crate) feature_enabled: [[bool; SEG_LVL_MAX]; MAX_SEGMENTS],
+    pub(crate) feature_data: [[i16; SEG_LVL_MAX]; MAX_SEGMENTS],
+    pub(crate) film_grain_params: FilmGrainParams,
+}
+
+impl Default for RefFrame {
+    fn default() -> Self {
+        Self {
+            valid: false,
+            frame_id: 0,
+            frame_type: KEY_FRAME,
+            order_hint: 0,
+            frame_size: FrameSize::default(),
+            render_width: 0,
+            render_height: 0,
+            gm_params: DEFAULT_GM_PARAMS,
+            loop_filter_ref_deltas: DEFAULT_LOOP_FILTER_REF_DELTAS,
+            loop_filter_mode_deltas: [0; 2],
+            feature_enabled: [[false; SEG_LVL_MAX]; MAX_SEGMENTS],
+            feature_data: [[0; SEG_LVL_MAX]; MAX_SEGMENTS],
+            film_grain_params: FilmGrainParams::default(),
+        }
+    }
+}
+
+/// The NUM_REF_FRAMES reference frame slots that frame headers are parsed against
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct RefFrames {
+    slots: [RefFrame; NUM_REF_FRAMES],
+}
+
+impl RefFrames {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn get(&self, idx: usize) -> &RefFrame {
+        &self.slots[idx]
+    }
+
+    /// Reference frame update process - 7.20, run once a frame has been decoded
+    pub fn update(&mut self, frame: &FrameHeaderObu) {
+        if frame.show_existing_frame {
+            // The frame loading process - 7.21 makes the shown key frame the current frame
+            if frame.frame_type == KEY_FRAME {
+                let shown = self.slots[frame.frame_to_show_map_idx as usize].clone();
+                self.slots.fill(shown);
+            }
+            return;
+        }
+
+        if frame.frame_type == KEY_FRAME && frame.show_frame {
+            self.slots.fill(RefFrame::default());
+        }
+        if let Some(ref_order_hint) = frame.ref_order_hint {
+            for (slot, order_hint) in self.slots.iter_mut().zip(ref_order_hint) {
+                if slot.order_hint != order_hint {
+                    slot.valid = false;
+                    slot.order_hint = order_hint;
+                }
+            }
+        }
+
+        for (i, slot) in self.slots.iter_mut().enumerate() {
+            if frame.refresh_frame_flags & (1 << i) == 0 {
+                continue;
+            }
+
+            *slot = RefFrame {
+                valid: true,
+                frame_id: frame.current_frame_id,
+                frame_type: frame.frame_type,
+                order_hint: frame.order_hint,
+                frame_size: frame.frame_size,
+                render_width: frame.render_width,
+                render_height: frame.render_height,
+                gm_params: frame.global_motion_params.gm_params,
+                loop_filter_ref_deltas: frame.loop_filter_params.loop_filter_ref_deltas,
+                loop_filter_mode_deltas: frame.loop_filter_params.loop_filter_mode_deltas,
+                feature_enabled: frame.segmentation_params.feature_enabled,
+                feature_data: frame.segmentation_params.feature_data,
+                film_grain_params: frame.film_grain_params,
+            };
+        }
+    }
+}
+
+/// The state frame headers are parsed against: the most recent sequence header and the
+/// reference frames. It owns all of it, so it can be kept between OBUs from different buffers.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SequenceHeaderContext {
+    pub sequence_header: SequenceHeaderObu,
+    pub refs: RefFrames,
+}
+
+impl SequenceHeaderContext {
+    pub fn new(sequence_header: SequenceHeaderObu) -> Self {
+        Self {
+            sequence_header,
+            refs: RefFrames::new(),
+        }
+    }
+
+    /// Parses a frame header and runs the reference frame update process - 7.20 with it
+    pub fn parse_frame_header<T: ByteRead>(
+        &mut self,
+        reader: &mut BitstreamReader<T>,
+        extension: Option<ObuExtensionHeader>,
+    ) -> Result<FrameHeaderObu, Error> {
+        let frame_header =
+            parse_frame_header(reader, &self.sequence_header, &self.refs, extension)?;
+        self.refs.update(&frame_header);
+        Ok(frame_header)
+    }
+}
+
+/// Checks that a frame header could have been parsed against a sequence header, to catch
+/// frame headers paired with the wrong sequence header
+pub fn check_consistency(seq: &SequenceHeaderObu, frame: &FrameHeaderObu) -> Result<(), Error> {
+    let frame_size = &frame.frame_size;
+    if frame_size.upscaled_width > seq.max_frame_width_minus_1 + 1
+        || frame_size.frame_width > frame_size.upscaled_width
+    {
+        return Err(Error::Invalid(
+            "frame width exceeds max_frame_width_minus_1",
+        ));
+    }
+    if frame_size.frame_height > seq.max_frame_height_minus_1 + 1 {
+        return Err(Error::Invalid(
+            "frame height exceeds max_frame_height_minus_1",
+        ));
+    }
+
+    if seq.frame_id_numbers_present_flag {
+        let id_len = seq.additional_frame_id_length_minus_1 + seq.delta_frame_id_length_minus_2 + 3;
+        if frame.current_frame_id >> id_len != 0 {
+            return Err(Error::Invalid("current_frame_id wider than idLen"));
+        }
+    } else if frame.current_frame_id != 0 {
+        return Err(Error::Invalid(
+            "current_frame_id without frame_id_numbers_present_flag",
+        ));
+    }
+
+    if seq.enable_order_hint {
+        let order_hint_bits = seq.order_hint_bits();
+        let too_wide = |order_hint: &u32| order_hint >> order_hint_bits != 0;
+        if too_wide(&frame.order_hint) || frame.ref_order_hint.iter().flatten().any(too_wide) {
+            return Err(Error::Invalid("order_hint wider than OrderHintBits"));
+        }
+    } else if frame.order_hint != 0
+        || frame.ref_order_hint.is_some()
+        || frame.use_ref_frame_mvs
+        || frame.skip_mode_present
+    {
+        return Err(Error::Invalid("order hints used without enable_order_hint"));
+    }
+    Ok(())
+}
+
+/// The indices of coded frames in the order they're displayed
+///
+/// Frames are shown either with show_frame or later from a reference slot with
+/// show_existing_frame. With enable_order_hint the shown frames are sorted by their order hints,
+/// unwrapped with get_relative_dist() so they keep increasing past the OrderHintBits range, and
+/// restarting after each shown key frame. Without it the frames are taken in the order they're
+/// shown. Hidden frames that are never shown are left out.
+pub fn display_order(seq: &SequenceHeaderObu, frames: &[FrameHeaderObu]) -> Vec<usize> {
+    let mut slots = [None; NUM_REF_FRAMES];
+    let mut order = Vec::new();
+    for (i, frame) in frames.iter().enumerate() {
+        let shown = if frame.show_existing_frame {
+            slots[frame.frame_to_show_map_idx as usize]
+        } else {
+            frame.show_frame.then_some(i)
+        };
+        order.extend(shown);
+
+        // A shown key frame refreshes every slot with itself
+        let current = if frame.show_existing_frame {
+            shown
+        } else {
+            Some(i)
+        };
+        for (j, slot) in slots.iter_mut().enumerate() {
+            if frame.refresh_frame_flags & (1 << j) != 0 {
+                *slot = current;
+            }
+        }
+    }
+    if !seq.enable_order_hint {
+        return order;
+    }
+
+    // Order hints in coded order, carried past wrap arounds. show_existing_frame headers don't
+    // have one of their own, so they're skipped.
+    let mut unwrapped = vec![0i64; frames.len()];
+    let mut prev: Option<(u32, i64)> = None;
+    let mut max = 0;
+    for (i, frame) in frames.iter().enumerate() {
+        if frame.show_existing_frame {
+            continue;
+        }
+        unwrapped[i] = match prev {
+            Some(_) if frame.frame_type == KEY_FRAME && frame.show_frame => max + 1,
+            Some((hint, value)) => value + get_relative_dist(seq, frame.order_hint, hint) as i64,
+            None => 0,
+        };
+        max = max.max(unwrapped[i]);
+        prev = Some((frame.order_hint, unwrapped[i]));
+    }
+    order.sort_by_key(|&i| unwrapped[i]);
+    order
+}
+
+/// The indices of frames whose frame ids break the requirements of 6.8.2, as happens when
+/// frames have been dropped
+///
+/// A frame is flagged when current_frame_id doesn't move forward by less than half of the id
+/// range, or when its references' ids don't match the ids it expects from delta_frame_id_minus_1
+/// or display_frame_id. Shown key frames start over. Nothing is flagged without
+/// frame_id_numbers_present_flag.
+pub fn frame_id_discontinuities(seq: &SequenceHeaderObu, frames: &[FrameHeaderObu]) -> Vec<usize> {
+    let mut discontinuities = Vec::new();
+    if !seq.frame_id_numbers_present_flag {
+        return discontinuities;
+    }
+
+    let id_len = seq.additional_frame_id_length_minus_1 + seq.delta_frame_id_length_minus_2 + 3;
+    let mut ref_frame_ids = [None; NUM_REF_FRAMES];
+    let mut prev_frame_id = None;
+    for (i, frame) in frames.iter().enumerate() {
+        if frame.show_existing_frame {
+            let shown = ref_frame_ids[frame.frame_to_show_map_idx as usize];
+            if matches!((frame.display_frame_id, shown), (Some(a), Some(b)) if a != b) {
+                discontinuities.push(i);
+            }
+            if frame.frame_type == KEY_FRAME {
+                ref_frame_ids.fill(shown);
+                prev_frame_id = shown;
+            }
+            continue;
+        }
+
+        let current_frame_id = frame.current_frame_id;
+        let mut broken = false;
+        if let Some(prev_frame_id) = prev_frame_id {
+            if frame.frame_type != KEY_FRAME || !frame.show_frame {
+                let diff_frame_id = if current_frame_id > prev_frame_id {
+                    current_frame_id - prev_frame_id
+                } else {
+                    (1 << id_len) + current_frame_id - prev_frame_id
+                };
+                broken = diff_frame_id >= 1 << (id_len - 1);
+            }
+        }
+        if let Some(delta_frame_id_minus_1) = frame.delta_frame_id_minus_1 {
+            for (idx, delta) in frame.ref_frame_idx.iter().zip(delta_frame_id_minus_1) {
+                let expected_frame_id =
+                    ((1 << id_len) + current_frame_id - (delta + 1)) % (1 << id_len);
+                if ref_frame_ids[*idx as usize].is_some_and(|id| id != expected_frame_id) {
+                    broken = true;
+                }
+            }
+        }
+        if broken {
+            discontinuities.push(i);
+        }
+
+        prev_frame_id = Some(current_frame_id);
+        for (j, ref_frame_id) in ref_frame_ids.iter_mut().enumerate() {
+            if frame.refresh_frame_flags & (1 << j) != 0 {
+                *ref_frame_id = Some(current_frame_id);
+            }
+        }
+    }
+    discontinuities
+}
+
+/// order_hint and ref_order_hint[] - 5.9.2, which take up OrderHintBits bits and are absent (0)
+/// without enable_order_hint
+fn read_order_hint<T: ByteRead>(
+    reader: &mut BitstreamReader<T>,
+    order_hint_bits: u8,
+) -> Result<u32, Error> {
+    if order_hint_bits == 0 {
+        return Ok(0);
+    }
+    reader.f(order_hint_bits)
+}
+
+/// get_relative_dist() - 5.9.3
+fn get_relative_dist(seq: &SequenceHeaderObu, a: u32, b: u32) -> i32 {
+    if !seq.enable_order_hint {
+        return 0;
+    }
+
+    let diff = a as i32 - b as i32;
+    let m = 1 << (seq.order_hint_bits() - 1);
+    (diff & (m - 1)) - (diff & m)
+}
+
+/// uncompressed_header() - 5.9.2
+///
+/// `refs` is the reference frame state before this frame, and is not modified; call
+/// [`RefFrames::update`] once the frame has been dealt with.
+pub fn parse_frame_header<T: ByteRead>(
+    reader: &mut BitstreamReader<T>,
+    seq: &SequenceHeaderObu,
+    refs: &RefFrames,
+    extension: Option<ObuExtensionHeader>,
+) -> Result<FrameHeaderObu, Error> {
+    let mut fh = FrameHeaderObu::default();
+
+    let id_len = if seq.frame_id_numbers_present_flag {
+        seq.additional_frame_id_length_minus_1 + seq.delta_frame_id_length_minus_2 + 3
+    } else {
+        0
+    };
+    let all_frames = u8::MAX;
+    let order_hint_bits = seq.order_hint_bits();
+    let temporal_point_info_present = seq.decoder_model_info.is_some()
+        && !seq
+            .timing_info
+            .as_ref()
+            .is_some_and(|timing_info| timing_info.equal_picture_interval);
+
+    if seq.reduced_still_picture_header {
+        fh.frame_type = KEY_FRAME;
+        fh.frame_is_intra = true;
+        fh.show_frame = true;
+        fh.showable_frame = false;
+        fh.error_resilient_mode = true;
+    } else {
+        fh.show_existing_frame = reader.f1()?;
+        if fh.show_existing_frame {
+            fh.frame_to_show_map_idx = reader.f(3)? as u8;
+            if temporal_point_info_present {
+                fh.frame_presentation_time = parse_temporal_point_info(reader, seq)?;
+            }
+            if seq.frame_id_numbers_present_flag {
+                fh.display_frame_id = Some(reader.f(id_len)?);
+            }
+
+            let shown = refs.get(fh.frame_to_show_map_idx as usize);
+            fh.frame_type = shown.frame_type;
+            fh.frame_is_intra = shown.frame_type == KEY_FRAME;
+            fh.order_hint = shown.order_hint;
+            fh.frame_size = shown.frame_size;
+            fh.render_width = shown.render_width;
+            fh.render_height = shown.render_height;
+            if seq.film_grain_params_present {
+                fh.film_grain_params = shown.film_grain_params;
+            }
+            fh.refresh_frame_flags = if shown.frame_type == KEY_FRAME {
+                all_frames
+            } else {
+                0
+            };
+            return Ok(fh);
+        }
+
+        fh.frame_type = reader.f(2)? as u8;
+        fh.frame_is_intra = fh.frame_type == INTRA_ONLY_FRAME || fh.frame_type == KEY_FRAME;
+        fh.show_frame = reader.f1()?;
+        if fh.show_frame && temporal_point_info_present {
+            fh.frame_presentation_time = parse_temporal_point_info(reader, seq)?;
+        }
+        fh.showable_frame = if fh.show_frame {
+            fh.frame_type != KEY_FRAME
+        } else {
+            reader.f1()?
+        };
+        fh.error_resilient_mode =
+            if fh.frame_type == SWITCH_FRAME || (fh.frame_type == KEY_FRAME && fh.show_frame) {
+                true
+            } else {
+                reader.f1()?
+            };
+    }
+
+    // A shown key frame resets all of the reference frames
+    let mut ref_order_hint = [0; NUM_REF_FRAMES];
+    if !(fh.frame_type == KEY_FRAME && fh.show_frame) {
+        ref_order_hint = refs.slots.each_ref().map(|slot| slot.order_hint);
+    }
+
+    fh.disable_cdf_update = reader.f1()?;
+    fh.allow_screen_content_tools =
+        if seq.seq_force_screen_content_tools == SELECT_SCREEN_CONTENT_TOOLS {
+            reader.f1()?
+        } else {
+            seq.seq_force_screen_content_tools != 0
+        };
+    fh.force_integer_mv = if fh.allow_screen_content_tools {
+        if seq.seq_force_integer_mv == SELECT_INTEGER_MV {
+            reader.f1()?
+        } else {
+            seq.seq_force_integer_mv != 0
+        }
+    } else {
+        false
+    };
+    if fh.frame_is_intra {
+        fh.force_integer_mv = true;
+    }
+
+    fh.current_frame_id = if seq.frame_id_numbers_present_flag {
+        reader.f(id_len)?
+    } else {
+        0
+    };
+
+    fh.frame_size_override_flag = if fh.frame_type == SWITCH_FRAME {
+        true
+    } else if seq.reduced_still_picture_header {
+        false
+    } else {
+        reader.f1()?
+    };
+    fh.order_hint = read_order_hint(reader, order_hint_bits)?;
+    fh.primary_ref_frame = if fh.frame_is_intra || fh.error_resilient_mode {
+        PRIMARY_REF_NONE
+    } else {
+        reader.f(3)? as u8
+    };
+
+    if let Some(decoder_model_info) = &seq.decoder_model_info {
+        let buffer_removal_time_present_flag = reader.f1()?;
+        if buffer_removal_time_present_flag {
+            let (temporal_id, spatial_id) = extension
+                .map(|ext| (ext.temporal_id, ext.spatial_id))
+                .unwrap_or((0, 0));
+            for op in 0..=seq.operating_points_cnt_minus_1 as usize {
+                if !seq.decoder_model_present_for_this_op[op] {
+                    continue;
+                }
+
+                let op_pt_idc = seq.operating_point_idc[op];
+                let in_temporal_layer = (op_pt_idc >> temporal_id) & 1 != 0;
+                let in_spatial_layer = (op_pt_idc >> (spatial_id + 8)) & 1 != 0;
+                if op_pt_idc == 0 || (in_temporal_layer && in_spatial_layer) {
+                    let _buffer_removal_time =
+                        reader.f(decoder_model_info.buffer_removal_time_length_minus_1 + 1)?;
+                }
+            }
+        }
+    }
+
+    fh.refresh_frame_flags =
+        if fh.frame_type == SWITCH_FRAME || (fh.frame_type == KEY_FRAME && fh.show_frame) {
+            all_frames
+        } else {
+            reader.f(8)? as u8
+        };
+    if (!fh.frame_is_intra || fh.refresh_frame_flags != all_frames)
+        && fh.error_resilient_mode
+        && seq.enable_order_hint
+    {
+        for order_hint in ref_order_hint.iter_mut() {
+            *order_hint = read_order_hint(reader, order_hint_bits)?;
+        }
+        fh.ref_order_hint = Some(ref_order_hint);
+    }
+
+    let mut ref_frame_idx = [0; REFS_PER_FRAME];
+    if fh.frame_is_intra {
+        fh.frame_size = parse_frame_size(reader, seq, fh.frame_size_override_flag)?;
+        (fh.render_width, fh.render_height) = parse_render_size(reader, &fh.frame_size)?;
+        if fh.allow_screen_content_tools
+            && fh.frame_size.upscaled_width == fh.frame_size.frame_width
+        {
+            fh.allow_intrabc = reader.f1()?;
+        }
+    } else {
+        let frame_refs_short_signaling = seq.enable_order_hint && reader.f1()?;
+        if frame_refs_short_signaling {
+            let last_frame_idx = reader.f(3)? as usize;
+            let gold_frame_idx = reader.f(3)? as usize;
+            ref_frame_idx = set_frame_refs(
+                seq,
+                &ref_order_hint,
+                fh.order_hint,
+                last_frame_idx,
+                gold_frame_idx,
+            );
+        }
+        let mut delta_frame_id_minus_1 = [0; REFS_PER_FRAME];
+        for (idx, delta) in ref_frame_idx.iter_mut().zip(&mut delta_frame_id_minus_1) {
+            if !frame_refs_short_signaling {
+                *idx = reader.f(3)? as usize;
+            }
+            if seq.frame_id_numbers_present_flag {
+                *delta = reader.f(seq.delta_frame_id_length_minus_2 + 2)?;
+            }
+        }
+        fh.ref_frame_idx = ref_frame_idx.map(|idx| idx as u8);
+        if seq.frame_id_numbers_present_flag {
+            fh.delta_frame_id_minus_1 = Some(delta_frame_id_minus_1);
+        }
+
+        if fh.frame_size_override_flag && !fh.error_resilient_mode {
+            // frame_size_with_refs() - 5.9.7
+            let mut found_ref = None;
+            for idx in ref_frame_idx {
+                if reader.f1()? {
+                    found_ref = Some(refs.get(idx));
+                    break;
+                }
+            }
+
+            match found_ref {
+                Some(ref_frame) if !ref_frame.valid => {
+                    return Err(Error::Invalid("frame size taken from an invalid reference"));
+                }
+                Some(ref_frame) => {
+                    fh.frame_size = superres_frame_size(
+                        reader,
+                        seq,
+                        ref_frame.frame_size.upscaled_width,
+                        ref_frame.frame_size.frame_height,
+                    )?;
+                    fh.render_width = ref_frame.render_width;
+                    fh.render_height = ref_frame.render_height;
+                }
+                None => {
+                    fh.frame_size = parse_frame_size(reader, seq, fh.frame_size_override_flag)?;
+                    (fh.render_width, fh.render_height) =
+                        parse_render_size(reader, &fh.frame_size)?;
+                }
+            }
+        } else {
+            fh.frame_size = parse_frame_size(reader, seq, fh.frame_size_override_flag)?;
+            (fh.render_width, fh.render_height) = parse_render_size(reader, &fh.frame_size)?;
+        }
+
+        fh.allow_high_precision_mv = if fh.force_integer_mv {
+            false
+        } else {
+            reader.f1()?
+        };
+
+        // read_interpolation_filter() - 5.9.10
+        let is_filter_switchable = reader.f1()?;
+        fh.interpolation_filter = if is_filter_switchable {
+            SWITCHABLE
+        } else {
+            reader.f(2)? as u8
+        };
+
+        fh.is_motion_mode_switchable = reader.f1()?;
+        fh.use_ref_frame_mvs = if fh.error_resilient_mode || !seq.enable_ref_frame_mvs {
+            false
+        } else {
+            reader.f1()?
+        };
+    }
+
+    fh.disable_frame_end_update_cdf = if seq.reduced_still_picture_header || fh.disable_cdf_update {
+        true
+    } else {
+        reader.f1()?
+    };
+
+    // setup_past_independence() or load_previous() - 7.20, 7.21
+    let prev_gm_params = if fh.primary_ref_frame == PRIMARY_REF_NONE {
+        DEFAULT_GM_PARAMS
+    } else {
+        let prev = refs.get(ref_frame_idx[fh.primary_ref_frame as usize]);
+        fh.loop_filter_params.loop_filter_ref_deltas = prev.loop_filter_ref_deltas;
+        fh.loop_filter_params.loop_filter_mode_deltas = prev.loop_filter_mode_deltas;
+        fh.segmentation_params.feature_enabled = prev.feature_enabled;
+        fh.segmentation_params.feature_data = prev.feature_data;
+        prev.gm_params
+    };
+
+    fh.tile_info = parse_tile_info(reader, seq, &fh.frame_size)?;
+    fh.quantization_params = parse_quantization_params(reader, seq)?;
+    let base_q_idx = fh.quantization_params.base_q_idx;
+    fh.segmentation_params = parse_segmentation_params(reader, &fh)?;
+
+    // delta_q_params() - 5.9.17
+    if base_q_idx > 0 {
+        fh.delta_q_present = reader.f1()?;
+    }
+    if fh.delta_q_present {
+        fh.delta_q_res = reader.f(2)? as u8;
+    }
+
+    // delta_lf_params() - 5.9.18
+    if fh.delta_q_present {
+        if !fh.allow_intrabc {
+            fh.delta_lf_present = reader.f1()?;
+        }
+        if fh.delta_lf_present {
+            fh.delta_lf_res = reader.f(2)? as u8;
+            fh.delta_lf_multi = reader.f1()?;
+        }
+    }
+
+    fh.coded_lossless = fh.quantization_params.deltas_zero()
+        && (0..MAX_SEGMENTS).all(|segment_id| get_qindex(&fh, base_q_idx, segment_id) == 0);
+    fh.all_lossless =
+        fh.coded_lossless && fh.frame_size.frame_width == fh.frame_size.upscaled_width;
+
+    fh.loop_filter_params = parse_loop_filter_params(reader, seq, &fh)?;
+    fh.cdef_params = parse_cdef_params(reader, seq, &fh)?;
+    fh.lr_params = parse_lr_params(reader, seq, &fh)?;
+
+    // read_tx_mode() - 5.9.21
+    fh.tx_mode = if fh.coded_lossless {
+        ONLY_4X4
+    } else if reader.f1()? {
+        TX_MODE_SELECT
+    } else {
+        TX_MODE_LARGEST
+    };
+
+    // frame_reference_mode() - 5.9.23
+    fh.reference_select = if fh.frame_is_intra {
+        false
+    } else {
+        reader.f1()?
+    };
+
+    if skip_mode_allowed(seq, &fh, &ref_order_hint, &ref_frame_idx) {
+        fh.skip_mode_present = reader.f1()?;
+    }
+
+    fh.allow_warped_motion =
+        if fh.frame_is_intra || fh.error_resilient_mode || !seq.enable_warped_motion {
+            false
+        } else {
+            reader.f1()?
+        };
+    fh.reduced_tx_set = reader.f1()?;
+
+    fh.global_motion_params = parse_global_motion_params(reader, &fh, &prev_gm_params)?;
+    fh.film_grain_params = parse_film_grain_params(reader, seq, &fh, refs)?;
+
+    Ok(fh)
+}
+
+/// temporal_point_info() - 5.9.31, returning frame_presentation_time
+fn parse_temporal_point_info<T: ByteRead>(
+    reader: &mut BitstreamReader<T>,
+    seq: &SequenceHeaderObu,
+) -> Result<Option<u32>, Error> {
+    match &seq.decoder_model_info {
+        Some(decoder_model_info) => Ok(Some(
+            reader.f(decoder_model_info.frame_presentation_time_length_minus_1 + 1)?,
+        )),
+        None => Ok(None),
+    }
+}
+
+/// set_frame_refs() - 7.8
+fn set_frame_refs(
+    seq: &SequenceHeaderObu,
+    ref_order_hint: &[u32; NUM_REF_FRAMES],
+    order_hint: u32,
+    last_frame_idx: usize,
+    gold_frame_idx: usize,
+) -> [usize; REFS_PER_FRAME] {
+    let mut ref_frame_idx = [None; REFS_PER_FRAME];
+    ref_frame_idx[0] = Some(last_frame_idx);
+    ref_frame_idx[GOLDEN_FRAME - LAST_FRAME] = Some(gold_frame_idx);
+
+    let mut used_frame = [false; NUM_REF_FRAMES];
+    used_frame[last_frame_idx] = true;
+    used_frame[gold_frame_idx] = true;
+
+    let cur_frame_hint = 1 << (seq.order_hint_bits() - 1);
+    let shifted_order_hints =
+        ref_order_hint.map(|hint| cur_frame_hint + get_relative_dist(seq, hint, order_hint));
+
+    // find_latest_backward(), find_earliest_backward() and find_latest_forward()
+    let find = |used_frame: &[bool; NUM_REF_FRAMES], backward: bool, latest: bool| {
+        let mut found: Option<(usize, i32)> = None;
+        for (i, &hint) in shifted_order_hints.iter().enumerate() {
+            if used_frame[i] || (hint >= cur_frame_hint) != backward {
+                continue;
+            }
+            let better = match found {
+                None => true,
+                Some((_, best)) if latest => hint >= best,
+                Some((_, best)) => hint < best,
+            };
+            if better {
+                found = Some((i, hint));
+            }
+        }
+        found.map(|(i, _)| i)
+    };
+
+    for (ref_frame, latest) in [
+        (ALTREF_FRAME, true),
+        (BWDREF_FRAME, false),
+        (ALTREF2_FRAME, false),
+    ] {
+        if let Some(idx) = find(&used_frame, true, latest) {
+            ref_frame_idx[ref_frame - LAST_FRAME] = Some(idx);
+            used_frame[idx] = true;
+        }
+    }
+
+    for ref_frame in [
+        LAST2_FRAME,
+        LAST3_FRAME,
+        BWDREF_FRAME,
+        ALTREF2_FRAME,
+        ALTREF_FRAME,
+    ] {
+        if ref_frame_idx[ref_frame - LAST_FRAME].is_none() {
+            if let Some(idx) = find(&used_frame, false, true) {
+                ref_frame_idx[ref_frame - LAST_FRAME] = Some(idx);
+                used_frame[idx] = true;
+            }
+        }
+    }
+
+    // Any references left over use the frame with the earliest order hint
+    let mut earliest = 0;
+    for (i, &hint) in shifted_order_hints.iter().enumerate() {
+        if hint < shifted_order_hints[earliest] {
+            earliest = i;
+        }
+    }
+    ref_frame_idx.map(|idx| idx.unwrap_or(earliest))
+}
+
+/// tile_log2() - 5.9.16
+fn tile_log2(blk_size: u32, target: u32) -> u32 {
+    let mut k = 0;
+    while (blk_size << k) < target {
+        k += 1;
+    }
+    k
+}
+
+/// MiCols and MiRows from compute_image_size() - 5.9.9, the frame size in 4x4 blocks rounded
+/// up to a multiple of 8x8
+pub fn compute_mi_dims(width: u32, height: u32) -> (u32, u32) {
+    (2 * width.div_ceil(8), 2 * height.div_ceil(8))
+}
+
+/// sbCols and sbRows from tile_info() - 5.9.15, the number of 64x64 or 128x128 superblocks
+/// covering MiCols by MiRows
+pub fn sb_cols_rows(mi_cols: u32, mi_rows: u32, use_128x128_superblock: bool) -> (u32, u32) {
+    let sb_mi_size = if use_128x128_superblock { 32 } else { 16 };
+    (mi_cols.div_ceil(sb_mi_size), mi_rows.div_ceil(sb_mi_size))
+}
+
+/// tile_info() - 5.9.15
+pub fn parse_tile_info<T: ByteRead>(
+    reader: &mut BitstreamReader<T>,
+    seq: &SequenceHeaderObu,
+    frame_size: &FrameSize,
+) -> Result<TileInfo, Error> {
+    let (mi_cols, mi_rows) = compute_mi_dims(frame_size.frame_width, frame_size.frame_height);
+    let (sb_cols, sb_rows) = sb_cols_rows(mi_cols, mi_rows, seq.use_128x128_superblock);
+    let sb_shift = if seq.use_128x128_superblock { 5 } else { 4 };
+    let sb_size = sb_shift + 2;
+    let max_tile_width_sb = MAX_TILE_WIDTH >> sb_size;
+    let mut max_tile_area_sb = MAX_TILE_AREA >> (2 * sb_size);
+    let min_log2_tile_cols = tile_log2(max_tile_width_sb, sb_cols);
+    let max_log2_tile_cols = tile_log2(1, sb_cols.min(MAX_TILE_COLS));
+    let max_log2_tile_rows = tile_log2(1, sb_rows.min(MAX_TILE_ROWS));
+    let min_log2_tiles = min_log2_tile_cols.max(tile_log2(max_tile_area_sb, sb_rows * sb_cols));
+
+    let mut tile_info = TileInfo {
+        uniform_tile_spacing_flag: reader.f1()?,
+        ..Default::default()
+    };
+    if tile_info.uniform_tile_spacing_flag {
+        tile_info.tile_cols_log2 = min_log2_tile_cols;
+        while tile_info.tile_cols_log2 < max_log2_tile_cols {
+            let increment_tile_cols_log2 = reader.f1()?;
+            if !increment_tile_cols_log2 {
+                break;
+            }
+            tile_info.tile_cols_log2 += 1;
+        }
+        let tile_width_sb =
+            (sb_cols + (1 << tile_info.tile_cols_log2) - 1) >> tile_info.tile_cols_log2;
+        tile_info.mi_col_starts = (0..sb_cols)
+            .step_by(tile_width_sb as usize)
+            .map(|start_sb| start_sb << sb_shift)
+            .collect();
+
+        let min_log2_tile_rows = min_log2_tiles.saturating_sub(tile_info.tile_cols_log2);
+        tile_info.tile_rows_log2 = min_log2_tile_rows;
+        while tile_info.tile_rows_log2 < max_log2_tile_rows {
+            let increment_tile_rows_log2 = reader.f1()?;
+            if !increment_tile_rows_log2 {
+                break;
+            }
+            tile_info.tile_rows_log2 += 1;
+        }
+        let tile_height_sb =
+            (sb_rows + (1 << tile_info.tile_rows_log2) - 1) >> tile_info.tile_rows_log2;
+        tile_info.mi_row_starts = (0..sb_rows)
+            .step_by(tile_height_sb as usize)
+            .map(|start_sb| start_sb << sb_shift)
+            .collect();
+    } else {
+        let mut widest_tile_sb = 0;
+        let mut start_sb = 0;
+        while start_sb < sb_cols {
+            tile_info.mi_col_starts.push(start_sb << sb_shift);
+            let max_width = (sb_cols - start_sb).min(max_tile_width_sb);
+            let width_in_sbs_minus_1 = reader.ns(max_width)?;
+            let size_sb = width_in_sbs_minus_1 + 1;
+            widest_tile_sb = widest_tile_sb.max(size_sb);
+            start_sb += size_sb;
+        }
+        tile_info.tile_cols_log2 = tile_log2(1, tile_info.mi_col_starts.len() as u32);
+
+        if min_log2_tiles > 0 {
+            max_tile_area_sb = (sb_rows * sb_cols) >> (min_log2_tiles + 1);
+        } else {
+            max_tile_area_sb = sb_rows * sb_cols;
+        }
+        let max_tile_height_sb = (max_tile_area_sb / widest_tile_sb).max(1);
+
+        let mut start_sb = 0;
+        while start_sb < sb_rows {
+            tile_info.mi_row_starts.push(start_sb << sb_shift);
+            let max_height = (sb_rows - start_sb).min(max_tile_height_sb);
+            let height_in_sbs_minus_1 = reader.ns(max_height)?;
+            start_sb += height_in_sbs_minus_1 + 1;
+        }
+        tile_info.tile_rows_log2 = tile_log2(1, tile_info.mi_row_starts.len() as u32);
+    }
+
+    tile_info.tile_cols = tile_info.mi_col_starts.len() as u32;
+    tile_info.tile_rows = tile_info.mi_row_starts.len() as u32;
+    tile_info.mi_col_starts.push(mi_cols);
+    tile_info.mi_row_starts.push(mi_rows);
+
+    if tile_info.tile_cols_log2 > 0 || tile_info.tile_rows_log2 > 0 {
+        tile_info.context_update_tile_id =
+            reader.f((tile_info.tile_rows_log2 + tile_info.tile_cols_log2) as u8)?;
+        let tile_size_bytes_minus_1 = reader.f(2)? as u8;
+        tile_info.tile_size_bytes = tile_size_bytes_minus_1 + 1;
+    }
+    Ok(tile_info)
+}
+
+/// quantization_params() - 5.9.12
+pub fn parse_quantization_params<T: ByteRead>(
+    reader: &mut BitstreamReader<T>,
+    seq: &SequenceHeaderObu,
+) -> Result<QuantizationParams, Error> {
+    let mut qp = QuantizationParams {
+        base_q_idx: reader.f(8)? as u8,
+        delta_q_y_dc: reader.delta_q()? as i8,
+        ..Default::default()
+    };
+    if !seq.color_config.mono_chrome {
+        qp.diff_uv_delta = seq.color_config.separate_uv_delta_q && reader.f1()?;
+        qp.delta_q_u_dc = reader.delta_q()? as i8;
+        qp.delta_q_u_ac = reader.delta_q()? as i8;
+        if qp.diff_uv_delta {
+            qp.delta_q_v_dc = reader.delta_q()? as i8;
+            qp.delta_q_v_ac = reader.delta_q()? as i8;
+        } else {
+            qp.delta_q_v_dc = qp.delta_q_u_dc;
+            qp.delta_q_v_ac = qp.delta_q_u_ac;
+        }
+    }
+
+    qp.using_qmatrix = reader.f1()?;
+    if qp.using_qmatrix {
+        qp.qm_y = reader.f(4)? as u8;
+        qp.qm_u = reader.f(4)? as u8;
+        qp.qm_v = if seq.color_config.separate_uv_delta_q {
+            reader.f(4)? as u8
+        } else {
+            qp.qm_u
+        };
+    }
+    Ok(qp)
+}
+
+/// segmentation_params() - 5.9.14
+///
+/// The features start from `fh.segmentation_params`, which holds the values loaded from the
+/// primary reference frame.
+pub fn parse_segmentation_params<T: ByteRead>(
+    reader: &mut BitstreamReader<T>,
+    fh: &FrameHeaderObu,
+) -> Result<SegmentationParams, Error> {
+    let mut seg = SegmentationParams {
+        segmentation_enabled: reader.f1()?,
+        ..Default::default()
+    };
+    if !seg.segmentation_enabled {
+        return Ok(seg);
+    }
+
+    seg.feature_enabled = fh.segmentation_params.feature_enabled;
+    seg.feature_data = fh.segmentation_params.feature_data;
+    if fh.primary_ref_frame == PRIMARY_REF_NONE {
+        seg.segmentation_update_map = true;
+        seg.segmentation_update_data = true;
+    } else {
+        seg.segmentation_update_map = reader.f1()?;
+        if seg.segmentation_update_map {
+            seg.segmentation_temporal_update = reader.f1()?;
+        }
+        seg.segmentation_update_data = reader.f1()?;
+    }
+
+    if seg.segmentation_update_data {
+        for i in 0..MAX_SEGMENTS {
+            for j in 0..SEG_LVL_MAX {
+                let feature_enabled = reader.f1()?;
+                let mut clipped_value = 0;
+                if feature_enabled {
+                    let bits_to_read = SEGMENTATION_FEATURE_BITS[j];
+                    let limit = SEGMENTATION_FEATURE_MAX[j];
+                    clipped_value = if SEGMENTATION_FEATURE_SIGNED[j] {
+                        reader.su(1 + bits_to_read)?.clamp(-limit, limit)
+                    } else {
+                        (reader.f(bits_to_read)? as i32).clamp(0, limit)
+                    };
+                }
+                seg.feature_enabled[i][j] = feature_enabled;
+                seg.feature_data[i][j] = clipped_value as i16;
+            }
+        }
+    }
+
+    for i in 0..MAX_SEGMENTS {
+        for j in 0..SEG_LVL_MAX {
+            if seg.feature_enabled[i][j] {
+                seg.last_active_seg_id = i as u8;
+                seg.seg_id_pre_skip |= j >= SEG_LVL_REF_FRAME;
+            }
+        }
+    }
+    Ok(seg)
+}
+
+/// get_qindex() - 7.12.2, ignoring any delta_q
+fn get_qindex(fh: &FrameHeaderObu, base_q_idx: u8, segment_id: usize) -> u8 {
+    let seg = &fh.segmentation_params;
+    if seg.feature_enabled[segment_id][SEG_LVL_ALT_Q] {
+        let data = seg.feature_data[segment_id][SEG_LVL_ALT_Q] as i32;
+        (base_q_idx as i32 + data).clamp(0, 255) as u8
+    } else {
+        base_q_idx
+    }
+}
+
+/// loop_filter_params() - 5.9.11
+///
+/// The deltas start from `fh.loop_filter_params`, which holds the values loaded from the
+/// primary reference frame.
+pub fn parse_loop_filter_params<T: ByteRead>(
+    reader: &mut BitstreamReader<T>,
+    seq: &SequenceHeaderObu,
+    fh: &FrameHeaderObu,
+) -> Result<LoopFilterParams, Error> {
+    if fh.coded_lossless || fh.allow_intrabc {
+        return Ok(LoopFilterParams::default());
+    }
+
+    let mut lf = LoopFilterParams {
+        loop_filter_ref_deltas: fh.loop_filter_params.loop_filter_ref_deltas,
+        loop_filter_mode_deltas: fh.loop_filter_params.loop_filter_mode_deltas,
+        ..Default::default()
+    };
+    lf.loop_filter_level[0] = reader.f(6)? as u8;
+    lf.loop_filter_level[1] = reader.f(6)? as u8;
+    if !seq.color_config.mono_chrome
+        && (lf.loop_filter_level[0] != 0 || lf.loop_filter_level[1] != 0)
+    {
+        lf.loop_filter_level[2] = reader.f(6)? as u8;
+        lf.loop_filter_level[3] = reader.f(6)? as u8;
+    }
+    lf.loop_filter_sharpness = reader.f(3)? as u8;
+    lf.loop_filter_delta_enabled = reader.f1()?;
+    if lf.loop_filter_delta_enabled {
+        lf.loop_filter_delta_update = reader.f1()?;
+        if lf.loop_filter_delta_update {
+            for delta in lf.loop_filter_ref_deltas.iter_mut() {
+                if reader.f1()? {
+                    *delta = reader.su(1 + 6)? as i8;
+                }
+            }
+            for delta in lf.loop_filter_mode_deltas.iter_mut() {
+                if reader.f1()? {
+                    *delta = reader.su(1 + 6)? as i8;
+                }
+            }
+        }
+    }
+    Ok(lf)
+}
+
+/// cdef_params() - 5.9.19
+pub fn parse_cdef_params<T: ByteRead>(
+    reader: &mut BitstreamReader<T>,
+    seq: &SequenceHeaderObu,
+    fh: &FrameHeaderObu,
+) -> Result<CdefParams, Error> {
+    let mut cdef = CdefParams::default();
+    if fh.coded_lossless || fh.allow_intrabc || !seq.enable_cdef {
+        return Ok(cdef);
+    }
+
+    let cdef_damping_minus_3 = reader.f(2)? as u8;
+    cdef.cdef_damping = cdef_damping_minus_3 + 3;
+    cdef.cdef_bits = reader.f(2)? as u8;
+    for i in 0..(1 << cdef.cdef_bits) {
+        cdef.cdef_y_pri_strength[i] = reader.f(4)? as u8;
+        cdef.cdef_y_sec_strength[i] = read_cdef_sec_strength(reader)?;
+        if !seq.color_config.mono_chrome {
+            cdef.cdef_uv_pri_strength[i] = reader.f(4)? as u8;
+            cdef.cdef_uv_sec_strength[i] = read_cdef_sec_strength(reader)?;
+        }
+    }
+    Ok(cdef)
+}
+
+fn read_cdef_sec_strength<T: ByteRead>(reader: &mut BitstreamReader<T>) -> Result<u8, Error> {
+    match reader.f(2)? as u8 {
+        3 => Ok(4),
+        strength => Ok(strength),
+    }
+}
+
+/// lr_params() - 5.9.20
+pub fn parse_lr_params<T: ByteRead>(
+    reader: &mut BitstreamReader<T>,
+    seq: &SequenceHeaderObu,
+    fh: &FrameHeaderObu,
+) -> Result<LoopRestorationParams, Error> {
+    let mut lr = LoopRestorationParams::default();
+    if fh.all_lossless || fh.allow_intrabc || !seq.enable_restoration {
+        return Ok(lr);
+    }
+
+    let num_planes = if seq.color_config.mono_chrome { 1 } else { 3 };
+    let mut uses_chroma_lr = false;
+    for i in 0..num_planes {
+        let lr_type = reader.f(2)? as usize;
+        lr.frame_restoration_type[i] = REMAP_LR_TYPE[lr_type];
+        if lr.frame_restoration_type[i] != RESTORE_NONE {
+            lr.uses_lr = true;
+            uses_chroma_lr |= i > 0;
+        }
+    }
+
+    if lr.uses_lr {
+        let mut lr_unit_shift = reader.f1()? as u8;
+        if seq.use_128x128_superblock {
+            lr_unit_shift += 1;
+        } else if lr_unit_shift != 0 {
+            let lr_unit_extra_shift = reader.f1()? as u8;
+            lr_unit_shift += lr_unit_extra_shift;
+        }
+        lr.loop_restoration_size[0] = RESTORATION_TILESIZE_MAX >> (2 - lr_unit_shift);
+
+        let lr_uv_shift =
+            if seq.color_config.subsampling_x && seq.color_config.subsampling_y && uses_chroma_lr {
+                reader.f1()? as u8
+            } else {
+                0
+            };
+        lr.loop_restoration_size[1] = lr.loop_restoration_size[0] >> lr_uv_shift;
+        lr.loop_restoration_size[2] = lr.loop_restoration_size[0] >> lr_uv_shift;
+    }
+    Ok(lr)
+}
+
+/// skip_mode_params() - 5.9.22, returning skipModeAllowed
+fn skip_mode_allowed(
+    seq: &SequenceHeaderObu,
+    fh: &FrameHeaderObu,
+    ref_order_hint: &[u32; NUM_REF_FRAMES],
+    ref_frame_idx: &[usize; REFS_PER_FRAME],
+) -> bool {
+    if fh.frame_is_intra || !fh.reference_select || !seq.enable_order_hint {
+        return false;
+    }
+
+    let mut forward_hint = None;
+    let mut backward_hint = None;
+    for &idx in ref_frame_idx {
+        let ref_hint = ref_order_hint[idx];
+        let dist = get_relative_dist(seq, ref_hint, fh.order_hint);
+        if dist < 0 {
+            if forward_hint.is_none_or(|hint| get_relative_dist(seq, ref_hint, hint) > 0) {
+                forward_hint = Some(ref_hint);
+            }
+        } else if dist > 0
+            && backward_hint.is_none_or(|hint| get_relative_dist(seq, ref_hint, hint) < 0)
+        {
+            backward_hint = Some(ref_hint);
+        }
+    }
+
+    match (forward_hint, backward_hint) {
+        (None, _) => false,
+        (Some(_), Some(_)) => true,
+        (Some(forward_hint), None) => ref_frame_idx
+            .iter()
+            .any(|&idx| get_relative_dist(seq, ref_order_hint[idx], forward_hint) < 0),
+    }
+}
+
+/// global_motion_params() - 5.9.24
+///
+/// `prev_gm_params` are the PrevGmParams the parameters are coded relative to, from the
+/// primary reference frame.
+pub fn parse_global_motion_params<T: ByteRead>(
+    reader: &mut BitstreamReader<T>,
+    fh: &FrameHeaderObu,
+    prev_gm_params: &[[i32; 6]; NUM_REF_FRAMES],
+) -> Result<GlobalMotionParams, Error> {
+    let mut gm = GlobalMotionParams::default();
+    if fh.frame_is_intra {
+        return Ok(gm);
+    }
+
+    for (ref_frame, prev_gm_params) in prev_gm_params.iter().enumerate().skip(LAST_FRAME) {
+        let is_global = reader.f1()?;
+        let gm_type = if !is_global {
+            IDENTITY
+        } else if reader.f1()? {
+            ROTZOOM
+        } else if reader.f1()? {
+            TRANSLATION
+        } else {
+            AFFINE
+        };
+        gm.gm_type[ref_frame] = gm_type;
+
+        let gm_params = &mut gm.gm_params[ref_frame];
+        let mut read_param = |gm_params: &mut [i32; 6], idx: usize| -> Result<(), Error> {
+            gm_params[idx] = read_global_param(
+                reader,
+                gm_type,
+                fh.allow_high_precision_mv,
+                prev_gm_params[idx],
+                idx,
+            )?;
+            Ok(())
+        };
+
+        if gm_type >= ROTZOOM {
+            read_param(gm_params, 2)?;
+            read_param(gm_params, 3)?;
+            if gm_type == AFFINE {
+                read_param(gm_params, 4)?;
+                read_param(gm_params, 5)?;
+            } else {
+                gm_params[4] = -gm_params[3];
+                gm_params[5] = gm_params[2];
+            }
+        }
+        if gm_type >= TRANSLATION {
+            read_param(gm_params, 0)?;
+            read_param(gm_params, 1)?;
+        }
+    }
+    Ok(gm)
+}
+
+/// read_global_param() - 5.9.25
+fn read_global_param<T: ByteRead>(
+    reader: &mut BitstreamReader<T>,
+    gm_type: u8,
+    allow_high_precision_mv: bool,
+    prev_gm_param: i32,
+    idx: usize,
+) -> Result<i32, Error> {
+    let (abs_bits, prec_bits) = if idx < 2 {
+        if gm_type == TRANSLATION {
+            let hp = !allow_high_precision_mv as u8;
+            (GM_ABS_TRANS_ONLY_BITS - hp, GM_TRANS_ONLY_PREC_BITS - hp)
+        } else {
+            (GM_ABS_TRANS_BITS, GM_TRANS_PREC_BITS)
+        }
+    } else {
+        (GM_ABS_ALPHA_BITS, GM_ALPHA_PREC_BITS)
+    };
+
+    let prec_diff = WARPEDMODEL_PREC_BITS - prec_bits;
+    let (round, sub) = if idx % 3 == 2 {
+        (1 << WARPEDMODEL_PREC_BITS, 1 << prec_bits)
+    } else {
+        (0, 0)
+    };
+    let mx = 1 << abs_bits;
+    let r = (prev_gm_param >> prec_diff) - sub;
+    Ok((decode_signed_subexp_with_ref(reader, -mx, mx + 1, r)? << prec_diff) + round)
+}
+
+/// decode_signed_subexp_with_ref() - 5.9.26
+pub(crate) fn decode_signed_subexp_with_ref<T: ByteRead>(
+    reader: &mut BitstreamReader<T>,
+    low: i32,
+    high: i32,
+    r: i32,
+) -> Result<i32, Error> {
+    let x = decode_unsigned_subexp_with_ref(reader, (high - low) as u32, (r - low) as u32)?;
+    Ok(x as i32 + low)
+}
+
+/// decode_unsigned_subexp_with_ref() - 5.9.27
+pub(crate) fn decode_unsigned_subexp_with_ref<T: ByteRead>(
+    reader: &mut BitstreamReader<T>,
+    mx: u32,
+    r: u32,
+) -> Result<u32, Error> {
+    let v = decode_subexp(reader, mx)?;
+    if (r << 1) <= mx {
+        Ok(inverse_recenter(r, v))
+    } else {
+        Ok(mx - 1 - inverse_recenter(mx - 1 - r, v))
+    }
+}
+
+/// decode_subexp() - 5.9.28
+pub(crate) fn decode_subexp<T: ByteRead>(
+    reader: &mut BitstreamReader<T>,
+    num_syms: u32,
+) -> Result<u32, Error> {
+    let mut i = 0;
+    let mut mk = 0;
+    let k = 3;
+    loop {
+        let b2 = if i > 0 { k + i - 1 } else { k };
+        let a = 1 << b2;
+        if num_syms <= mk + 3 * a {
+            return Ok(reader.ns(num_syms - mk)? + mk);
+        }
+
+        let subexp_more_bits = reader.f1()?;
+        if subexp_more_bits {
+            i += 1;
+            mk += a;
+        } else {
+            return Ok(reader.f(b2 as u8)? + mk);
+        }
+    }
+}
+
+/// inverse_recenter() - 5.9.29
+pub(crate) fn inverse_recenter(r: u32, v: u32) -> u32 {
+    if v > 2 * r {
+        v
+    } else if v & 1 != 0 {
+        r - ((v + 1) >> 1)
+    } else {
+        r + (v >> 1)
+    }
+}
+
+/// film_grain_params() - 5.9.30
+pub fn parse_film_grain_params<T: ByteRead>(
+    reader: &mut BitstreamReader<T>,
+    seq: &SequenceHeaderObu,
+    fh: &FrameHeaderObu,
+    refs: &RefFrames,
+) -> Result<FilmGrainParams, Error> {
+    // reset_grain_params()
+    let mut fg = FilmGrainParams::default();
+    if !seq.film_grain_params_present || (!fh.show_frame && !fh.showable_frame) {
+        return Ok(fg);
+    }
+
+    fg.apply_grain = reader.f1()?;
+    if !fg.apply_grain {
+        return Ok(fg);
+    }
+
+    fg.grain_seed = reader.f(16)? as u16;
+    fg.update_grain = fh.frame_type != INTER_FRAME || reader.f1()?;
+    if !fg.update_grain {
+        // load_grain_params()
+        let film_grain_params_ref_idx = reader.f(3)? as u8;
+        if !fh.ref_frame_idx.contains(&film_grain_params_ref_idx) {
+            return Err(Error::Invalid(
+                "film_grain_params_ref_idx isn't one of ref_frame_idx",
+            ));
+        }
+        // Everything but grain_seed comes from the reference frame, which has to have grain
+        let stored = refs
+            .get(film_grain_params_ref_idx as usize)
+            .film_grain_params;
+        if !stored.apply_grain {
+            return Err(Error::Invalid(
+                "film grain loaded from a reference frame without grain",
+            ));
+        }
+        return Ok(FilmGrainParams {
+            grain_seed: fg.grain_seed,
+            update_grain: false,
+            film_grain_params_ref_idx,
+            ..stored
+        });
+    }
+
+    let color_config = &seq.color_config;
+    fg.num_y_points = reader.f(4)? as u8;
+    if fg.num_y_points as usize > MAX_NUM_Y_POINTS {
+        return Err(Error::Invalid("num_y_points is greater than 14"));
+    }
+    for i in 0..fg.num_y_points as usize {
+        fg.point_y_value[i] = reader.f(8)? as u8;
+        fg.point_y_scaling[i] = reader.f(8)? as u8;
+    }
+    fg.chroma_scaling_from_luma = !color_config.mono_chrome && reader.f1()?;
+    if !(color_config.mono_chrome
+        || fg.chroma_scaling_from_luma
+        || (color_config.subsampling_x && color_config.subsampling_y && fg.num_y_points == 0))
+    {
+        fg.num_cb_points = reader.f(4)? as u8;
+        if fg.num_cb_points as usize > MAX_NUM_CHROMA_POINTS {
+            return Err(Error::Invalid("num_cb_points is greater than 10"));
+        }
+        for i in 0..fg.num_cb_points as usize {
+            fg.point_cb_value[i] = reader.f(8)? as u8;
+            fg.point_cb_scaling[i] = reader.f(8)? as u8;
+        }
+        fg.num_cr_points = reader.f(4)? as u8;
+        if fg.num_cr_points as usize > MAX_NUM_CHROMA_POINTS {
+            return Err(Error::Invalid("num_cr_points is greater than 10"));
+        }
+        for i in 0..fg.num_cr_points as usize {
+            fg.point_cr_value[i] = reader.f(8)? as u8;
+            fg.point_cr_scaling[i] = reader.f(8)? as u8;
+        }
+    }
+
+    fg.grain_scaling_minus_8 = reader.f(2)? as u8;
+    fg.ar_coeff_lag = reader.f(2)? as u8;
+    let num_pos_luma = 2 * fg.ar_coeff_lag as usize * (fg.ar_coeff_lag as usize + 1);
+    let num_pos_chroma = if fg.num_y_points > 0 {
+        for coeff in &mut fg.ar_coeffs_y_plus_128[..num_pos_luma] {
+            *coeff = reader.f(8)? as u8;
+        }
+        num_pos_luma + 1
+    } else {
+        num_pos_luma
+    };
+    if fg.chroma_scaling_from_luma || fg.num_cb_points > 0 {
+        for coeff in &mut fg.ar_coeffs_cb_plus_128[..num_pos_chroma] {
+            *coeff = reader.f(8)? as u8;
+        }
+    }
+    if fg.chroma_scaling_from_luma || fg.num_cr_points > 0 {
+        for coeff in &mut fg.ar_coeffs_cr_plus_128[..num_pos_chroma] {
+            *coeff = reader.f(8)? as u8;
+        }
+    }
+    fg.ar_coeff_shift_minus_6 = reader.f(2)? as u8;
+    fg.grain_scale_shift = reader.f(2)? as u8;
+    if fg.num_cb_points > 0 {
+        fg.cb_mult = reader.f(8)? as u8;
+        fg.cb_luma_mult = reader.f(8)? as u8;
+        fg.cb_offset = reader.f(9)? as u16;
+    }
+    if fg.num_cr_points > 0 {
+        fg.cr_mult = reader.f(8)? as u8;
+        fg.cr_luma_mult = reader.f(8)? as u8;
+        fg.cr_offset = reader.f(9)? as u16;
+    }
+    fg.overlap_flag = reader.f1()?;
+    fg.clip_to_restricted_range = reader.f1()?;
+    Ok(fg)
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+    use crate::av1::obu::{
+        parse_sequence_header, SequenceHeaderDecoderModelInfo, SequenceHeaderTimingInfo,
+    };
+    use crate::av1::test_fixtures::{
+        HIDDEN_INTER_FRAME_64X64, KEY_FRAME_64X64, SEQ_64X64, SEQ_STILL,
+    };
+    use std::io::Cursor;
+
+    // The 64x64 frame headers with 5 bit frame ids, current_frame_id = 3 and 4
+    const KEY_FRAME_64X64_FRAME_ID: [u8; 27] = [
+        0x10, 0xc0, 0x15, 0x78, 0xec, 0x4f, 0xc7, 0x4e, 0xfb, 0xfe, 0x00, 0xff, 0x00, 0x41, 0xc0,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x02, 0x8a, 0x28, 0xa0, 0x82, 0x08, 0x80,
+    ];
+    const HIDDEN_INTER_FRAME_64X64_FRAME_ID: [u8; 26] = [
+        0x28, 0x40, 0x88, 0x08, 0x01, 0x80, 0x00, 0x00, 0x00, 0x00, 0x01, 0x6d, 0x8e, 0xc0, 0xfa,
+        0xf3, 0xec, 0xb0, 0x28, 0xa2, 0x8a, 0x08, 0x20, 0x14, 0x00, 0x00,
+    ];
+
+    fn seq_64x64() -> SequenceHeaderObu {
+        let mut bytes = Cursor::new(&SEQ_64X64);
+        let mut bs = BitstreamReader::new(&mut bytes).unwrap();
+        parse_sequence_header(&mut bs).unwrap()
+    }
+
+    fn frame_header(
+        buf: &[u8],
+        seq: &SequenceHeaderObu,
+        refs: &RefFrames,
+    ) -> (FrameHeaderObu, u64) {
+        let mut bytes = Cursor::new(buf);
+        let mut bs = BitstreamReader::new(&mut bytes).unwrap();
+        let fh = parse_frame_header(&mut bs, seq, refs, None).unwrap();
+        (fh, bs.position())
+    }
+
+    fn seq_1080p() -> SequenceHeaderObu {
+        SequenceHeaderObu {
+            frame_width_bits_minus_1: 10,
+            frame_height_bits_minus_1: 10,
+            max_frame_width_minus_1: 1919,
+            max_frame_height_minus_1: 1079,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn frame_size_from_sequence_header() {
+        const TEST_BUF: [u8; 1] = [0x00];
+
+        let mut bytes = Cursor::new(&TEST_BUF);
+        let mut bs = BitstreamReader::new(&mut bytes).unwrap();
+        let size = parse_frame_size(&mut bs, &seq_1080p(), false).unwrap();
+        assert_eq!(
+            size,
+            FrameSize {
+                frame_width: 1920,
+                frame_height: 1080,
+                upscaled_width: 1920,
+                ..Default::default()
+            }
+        );
+        assert_eq!(bs.position(), 0);
+
+        assert_eq!(parse_render_size(&mut bs, &size).unwrap(), (1920, 1080));
+        assert_eq!(bs.position(), 1);
+    }
+
+    #[test]
+    fn frame_size_override() {
+        // frame_width_minus_1 = 1279, frame_height_minus_1 = 719
+        const TEST_BUF: [u8; 3] = [0b10011111, 0b11101011, 0b00111100];
+
+        let mut bytes = Cursor::new(&TEST_BUF);
+        let mut bs = BitstreamReader::new(&mut bytes).unwrap();
+        let size = parse_frame_size(&mut bs, &seq_1080p(), true).unwrap();
+        assert_eq!(size.frame_width, 1280);
+        assert_eq!(size.frame_height, 720);
+        assert_eq!(size.upscaled_width, 1280);
+        assert_eq!(bs.position(), 22);
+    }
+
+    #[test]
+    fn frame_size_superres() {
+        // use_superres = 1, coded_denom = 7
+        const TEST_BUF: [u8; 1] = [0b11110000];
+
+        let seq = SequenceHeaderObu {
+            enable_superres: true,
+            ..seq_1080p()
+        };
+
+        let mut bytes = Cursor::new(&TEST_BUF);
+        let mut bs = BitstreamReader::new(&mut bytes).unwrap();
+        let size = parse_frame_size(&mut bs, &seq, false).unwrap();
+        assert_eq!(size.upscaled_width, 1920);
+        assert_eq!(size.frame_width, 960);
+        assert_eq!(size.frame_height, 1080);
+
+        // The render size defaults to the upscaled size
+        assert_eq!(parse_render_size(&mut bs, &size).unwrap(), (1920, 1080));
+    }
+
+    #[test]
+    fn superres_params_denom_range() {
+        // use_superres = 1, coded_denom = 0, then use_superres = 1, coded_denom = 7
+        const TEST_BUF: [u8; 1] = [0b10001111];
+
+        let seq = SequenceHeaderObu {
+            enable_superres: true,
+            ..seq_1080p()
+        };
+
+        let mut bytes = Cursor::new(&TEST_BUF);
+        let mut bs = BitstreamReader::new(&mut bytes).unwrap();
+        let min = parse_superres_params(&mut bs, &seq).unwrap();
+        assert_eq!(min.coded_denom, 0);
+        assert_eq!(min.superres_denom, SUPERRES_DENOM_MIN);
+        assert_eq!(min.frame_width(1920), 1707);
+
+        let max = parse_superres_params(&mut bs, &seq).unwrap();
+        assert_eq!(max.coded_denom, 7);
+        assert_eq!(max.superres_denom, 16);
+        assert_eq!(max.frame_width(1920), 960);
+        assert_eq!(max.frame_width(1921), 961);
+        assert_eq!(bs.position(), 8);
+    }
+
+    #[test]
+    fn superres_params_disabled() {
+        const TEST_BUF: [u8; 1] = [0xff];
+
+        let mut bytes = Cursor::new(&TEST_BUF);
+        let mut bs = BitstreamReader::new(&mut bytes).unwrap();
+        let superres_params = parse_superres_params(&mut bs, &seq_1080p()).unwrap();
+        assert_eq!(superres_params, SuperresParams::default());
+        assert_eq!(superres_params.frame_width(1920), 1920);
+        assert_eq!(bs.position(), 0);
+    }
+
+    #[test]
+    fn quantization_params_diff_uv_delta() {
+        // base_q_idx = 100, DeltaQYDc = -3, diff_uv_delta = 1, DeltaQUDc = 0, DeltaQUAc = 5,
+        // DeltaQVDc = -64, DeltaQVAc = 0, using_qmatrix = 1, qm_y = 15, qm_u = 3, qm_v = 9
+        const TEST_BUF: [u8; 6] = [
+            0b01100100, 0b11111101, 0b10100001, 0b01110000, 0b00011111, 0b00111001,
+        ];
+
+        let mut seq = seq_1080p();
+        seq.color_config.separate_uv_delta_q = true;
+
+        let mut bs = BitstreamReader::from_slice(&TEST_BUF);
+        let qp = parse_quantization_params(&mut bs, &seq).unwrap();
+        assert_eq!(
+            qp,
+            QuantizationParams {
+                base_q_idx: 100,
+                delta_q_y_dc: -3,
+                diff_uv_delta: true,
+                delta_q_u_dc: 0,
+                delta_q_u_ac: 5,
+                delta_q_v_dc: -64,
+                delta_q_v_ac: 0,
+                using_qmatrix: true,
+                qm_y: 15,
+                qm_u: 3,
+                qm_v: 9,
+            }
+        );
+        assert!(!qp.deltas_zero());
+        assert_eq!(bs.position(), 48);
+    }
+
+    #[test]
+    fn quantization_params_monochrome() {
+        // base_q_idx = 0, DeltaQYDc = 0, using_qmatrix = 1, qm_y = 5, qm_u = 6
+        const TEST_BUF: [u8; 3] = [0b00000000, 0b01010101, 0b10000000];
+
+        let mut seq = seq_1080p();
+        seq.color_config.mono_chrome = true;
+
+        let mut bs = BitstreamReader::from_slice(&TEST_BUF);
+        let qp = parse_quantization_params(&mut bs, &seq).unwrap();
+        assert_eq!(qp.base_q_idx, 0);
+        assert!(qp.deltas_zero());
+        assert!(qp.using_qmatrix);
+        assert_eq!((qp.qm_y, qp.qm_u, qp.qm_v), (5, 6, 6));
+        assert_eq!(bs.position(), 18);
+    }
+
+    #[test]
+    fn loop_filter_params_delta_update() {
+        // loop_filter_level = [10, 20, 5, 63], loop_filter_sharpness = 3,
+        // loop_filter_delta_enabled = 1, loop_filter_delta_update = 1, then updates of
+        // loop_filter_ref_deltas[1] = -2, loop_filter_ref_deltas[6] = 20 and
+        // loop_filter_mode_deltas[0] = 3
+        const TEST_BUF: [u8; 8] = [
+            0b00101001, 0b01000001, 0b01111111, 0b01111011, 0b11111000, 0b00100101, 0b00010000,
+            0b01100000,
+        ];
+
+        // A delta loaded from the primary reference frame that isn't updated
+        let mut fh = FrameHeaderObu::default();
+        fh.loop_filter_params.loop_filter_ref_deltas[2] = 5;
+
+        let mut bs = BitstreamReader::from_slice(&TEST_BUF);
+        let lf = parse_loop_filter_params(&mut bs, &seq_1080p(), &fh).unwrap();
+        assert_eq!(lf.loop_filter_level, [10, 20, 5, 63]);
+        assert_eq!(lf.loop_filter_sharpness, 3);
+        assert!(lf.loop_filter_delta_enabled);
+        assert!(lf.loop_filter_delta_update);
+        assert_eq!(lf.loop_filter_ref_deltas, [1, -2, 5, 0, -1, 0, 20, -1]);
+        assert_eq!(lf.loop_filter_mode_deltas, [3, 0]);
+        assert_eq!(bs.position(), 60);
+    }
+
+    #[test]
+    fn loop_filter_params_intrabc() {
+        const TEST_BUF: [u8; 1] = [0xff];
+
+        let mut fh = FrameHeaderObu {
+            allow_intrabc: true,
+            ..Default::default()
+        };
+        fh.loop_filter_params.loop_filter_mode_deltas = [1, 1];
+
+        let mut bs = BitstreamReader::from_slice(&TEST_BUF);
+        let lf = parse_loop_filter_params(&mut bs, &seq_1080p(), &fh).unwrap();
+        assert_eq!(lf, LoopFilterParams::default());
+        assert_eq!(bs.position(), 0);
+    }
+
+    #[test]
+    fn cdef_params_two_strengths() {
+        // cdef_damping_minus_3 = 2, cdef_bits = 1, then strengths y = (9, 3), uv = (0, 1) and
+        // y = (15, 2), uv = (4, 3)
+        const TEST_BUF: [u8; 4] = [0b10011001, 0b11000001, 0b11111001, 0b00110000];
+
+        let seq = SequenceHeaderObu {
+            enable_cdef: true,
+            ..seq_1080p()
+        };
+
+        let mut bs = BitstreamReader::from_slice(&TEST_BUF);
+        let cdef = parse_cdef_params(&mut bs, &seq, &FrameHeaderObu::default()).unwrap();
+        assert_eq!(cdef.cdef_damping, 5);
+        assert_eq!(cdef.cdef_bits, 1);
+        assert_eq!(cdef.cdef_y_pri_strength, [9, 15, 0, 0, 0, 0, 0, 0]);
+        assert_eq!(cdef.cdef_y_sec_strength, [4, 2, 0, 0, 0, 0, 0, 0]);
+        assert_eq!(cdef.cdef_uv_pri_strength, [0, 4, 0, 0, 0, 0, 0, 0]);
+        assert_eq!(cdef.cdef_uv_sec_strength, [1, 4, 0, 0, 0, 0, 0, 0]);
+        assert_eq!(bs.position(), 28);
+
+        // Nothing is coded when the sequence header disables CDEF
+        let mut bs = BitstreamReader::from_slice(&TEST_BUF);
+        let cdef = parse_cdef_params(&mut bs, &seq_1080p(), &FrameHeaderObu::default()).unwrap();
+        assert_eq!(cdef, CdefParams::default());
+        assert_eq!(bs.position(), 0);
+    }
+
+    #[test]
+    fn lr_params_switchable_wiener() {
+        // lr_type = (1, 2, 0), lr_unit_shift = 1, lr_unit_extra_shift = 1, lr_uv_shift = 1
+        const TEST_BUF: [u8; 2] = [0b01100011, 0b10000000];
+
+        let mut seq = SequenceHeaderObu {
+            enable_restoration: true,
+            ..seq_1080p()
+        };
+        seq.color_config.subsampling_x = true;
+        seq.color_config.subsampling_y = true;
+
+        let mut bs = BitstreamReader::from_slice(&TEST_BUF);
+        let lr = parse_lr_params(&mut bs, &seq, &FrameHeaderObu::default()).unwrap();
+        assert_eq!(
+            lr.frame_restoration_type,
+            [RESTORE_SWITCHABLE, RESTORE_WIENER, RESTORE_NONE]
+        );
+        assert!(lr.uses_lr);
+        assert_eq!(lr.loop_restoration_size, [256, 128, 128]);
+        assert_eq!(bs.position(), 9);
+    }
+
+    #[test]
+    fn lr_params_128x128_superblock() {
+        // lr_type = (3, 0, 0), lr_unit_shift = 0, with no lr_uv_shift as chroma isn't restored
+        const TEST_BUF: [u8; 1] = [0b11000000];
+
+        let mut seq = SequenceHeaderObu {
+            enable_restoration: true,
+            use_128x128_superblock: true,
+            ..seq_1080p()
+        };
+        seq.color_config.subsampling_x = true;
+        seq.color_config.subsampling_y = true;
+
+        let mut bs = BitstreamReader::from_slice(&TEST_BUF);
+        let lr = parse_lr_params(&mut bs, &seq, &FrameHeaderObu::default()).unwrap();
+        assert_eq!(lr.frame_restoration_type[0], RESTORE_SGRPROJ);
+        assert_eq!(lr.loop_restoration_size, [128, 128, 128]);
+        assert_eq!(bs.position(), 7);
+    }
+
+    #[test]
+    fn segmentation_params_features() {
+        // segmentation_enabled = 1, then segment 0 with SEG_LVL_ALT_Q = -256, and segment 3
+        // with SEG_LVL_ALT_LF_Y_V = -5 and SEG_LVL_REF_FRAME = 4
+        const TEST_BUF: [u8; 11] = [
+            0b11100000, 0b00000000, 0b00000000, 0b00000000, 0b00011111, 0b01100011, 0b00000000,
+            0b00000000, 0b00000000, 0b00000000, 0b00000000,
+        ];
+
+        let mut bs = BitstreamReader::from_slice(&TEST_BUF);
+        let seg = parse_segmentation_params(&mut bs, &FrameHeaderObu::default()).unwrap();
+        assert!(seg.segmentation_enabled);
+        assert!(seg.segmentation_update_map);
+        assert!(seg.segmentation_update_data);
+
+        let mut feature_enabled = [[false; SEG_LVL_MAX]; MAX_SEGMENTS];
+        let mut feature_data = [[0; SEG_LVL_MAX]; MAX_SEGMENTS];
+        feature_enabled[0][SEG_LVL_ALT_Q] = true;
+        feature_data[0][SEG_LVL_ALT_Q] = -255;
+        feature_enabled[3][1] = true;
+        feature_data[3][1] = -5;
+        feature_enabled[3][SEG_LVL_REF_FRAME] = true;
+        feature_data[3][SEG_LVL_REF_FRAME] = 4;
+        assert_eq!(seg.feature_enabled, feature_enabled);
+        assert_eq!(seg.feature_data, feature_data);
+
+        assert!(seg.seg_id_pre_skip);
+        assert_eq!(seg.last_active_seg_id, 3);
+        assert_eq!(bs.position(), 84);
+    }
+
+    #[test]
+    fn global_motion_params_identity() {
+        // is_global = 0 for every reference frame
+        const TEST_BUF: [u8; 1] = [0b00000001];
+
+        let fh = FrameHeaderObu {
+            frame_type: INTER_FRAME,
+            frame_is_intra: false,
+            ..Default::default()
+        };
+
+        let mut bs = BitstreamReader::from_slice(&TEST_BUF);
+        let gm = parse_global_motion_params(&mut bs, &fh, &DEFAULT_GM_PARAMS).unwrap();
+        assert_eq!(gm, GlobalMotionParams::default());
+        assert_eq!(bs.position(), 7);
+    }
+
+    #[test]
+    fn inverse_recenter_values() {
+        // Values alternate below and above r, then continue past 2 * r
+        let recentered: Vec<u32> = (0..=12).map(|v| inverse_recenter(5, v)).collect();
+        assert_eq!(recentered, [5, 4, 6, 3, 7, 2, 8, 1, 9, 0, 10, 11, 12]);
+        assert_eq!(inverse_recenter(0, 0), 0);
+        assert_eq!(inverse_recenter(0, 1), 1);
+    }
+
+    #[test]
+    fn decode_subexp_values() {
+        // num_syms <= 3 * 8 is coded entirely with ns(24)
+        let mut bs = BitstreamReader::from_slice(&[0b01010000]);
+        assert_eq!(decode_subexp(&mut bs, 24).unwrap(), 5);
+        assert_eq!(bs.position(), 4);
+
+        // subexp_more_bits = 0, then k = 3 bits
+        let mut bs = BitstreamReader::from_slice(&[0b01010000]);
+        assert_eq!(decode_subexp(&mut bs, 1000).unwrap(), 5);
+        assert_eq!(bs.position(), 4);
+
+        // One more, then another 3 bits offset by 8
+        let mut bs = BitstreamReader::from_slice(&[0b10110000]);
+        assert_eq!(decode_subexp(&mut bs, 1000).unwrap(), 14);
+        assert_eq!(bs.position(), 5);
+
+        // Two more, then 4 bits offset by 16
+        let mut bs = BitstreamReader::from_slice(&[0b11011110]);
+        assert_eq!(decode_subexp(&mut bs, 1000).unwrap(), 31);
+        assert_eq!(bs.position(), 7);
+
+        // Two more, then the remaining 24 symbols with ns(24)
+        let mut bs = BitstreamReader::from_slice(&[0b11111111]);
+        assert_eq!(decode_subexp(&mut bs, 40).unwrap(), 39);
+        assert_eq!(bs.position(), 7);
+    }
+
+    #[test]
+    fn decode_subexp_with_ref_values() {
+        // r in the lower half is recentered around r, in the upper half around mx - 1 - r
+        let mut bs = BitstreamReader::from_slice(&[0b01010000]);
+        assert_eq!(
+            decode_unsigned_subexp_with_ref(&mut bs, 1000, 100).unwrap(),
+            97
+        );
+        let mut bs = BitstreamReader::from_slice(&[0b01010000]);
+        assert_eq!(
+            decode_unsigned_subexp_with_ref(&mut bs, 1000, 900).unwrap(),
+            903
+        );
+
+        // The range of an alpha global motion parameter, with coded values of 0, 1 and 2
+        let mx = 1 << GM_ABS_ALPHA_BITS;
+        let mut bs = BitstreamReader::from_slice(&[0b00000001, 0b00100000]);
+        assert_eq!(
+            decode_signed_subexp_with_ref(&mut bs, -mx, mx + 1, 0).unwrap(),
+            0
+        );
+        assert_eq!(
+            decode_signed_subexp_with_ref(&mut bs, -mx, mx + 1, 0).unwrap(),
+            -1
+        );
+        assert_eq!(
+            decode_signed_subexp_with_ref(&mut bs, -mx, mx + 1, 0).unwrap(),
+            1
+        );
+        assert_eq!(bs.position(), 12);
+
+        // A reference at the bottom of the range isn't recentered
+        let mut bs = BitstreamReader::from_slice(&[0b01010000]);
+        assert_eq!(
+            decode_signed_subexp_with_ref(&mut bs, -mx, mx + 1, -mx).unwrap(),
+            5 - mx
+        );
+    }
+
+    #[test]
+    fn global_motion_params_rotzoom() {
+        // LAST_FRAME has is_global = 1, is_rot_zoom = 1, with subexp coded values of 8, 1, 2
+        // and 1 for params 2, 3, 0 and 1, then is_global = 0 for the other reference frames
+        const TEST_BUF: [u8; 4] = [0b11100000, 0b00100100, 0b00100000, 0b00000000];
+
+        let fh = FrameHeaderObu {
+            frame_type: INTER_FRAME,
+            frame_is_intra: false,
+            ..Default::default()
+        };
+
+        let mut bs = BitstreamReader::from_slice(&TEST_BUF);
+        let gm = parse_global_motion_params(&mut bs, &fh, &DEFAULT_GM_PARAMS).unwrap();
+        assert_eq!(gm.gm_type[LAST_FRAME], ROTZOOM);
+        assert_eq!(gm.gm_params[LAST_FRAME], [1024, -1024, 65544, -2, 2, 65544]);
+        assert_eq!(gm.gm_type[LAST2_FRAME..], [IDENTITY; 6]);
+        assert_eq!(
+            gm.gm_params[LAST2_FRAME..],
+            DEFAULT_GM_PARAMS[LAST2_FRAME..]
+        );
+        assert_eq!(bs.position(), 25);
+    }
+
+    fn seq_film_grain() -> SequenceHeaderObu {
+        let mut seq = SequenceHeaderObu {
+            film_grain_params_present: true,
+            ..seq_1080p()
+        };
+        seq.color_config.subsampling_x = true;
+        seq.color_config.subsampling_y = true;
+        seq
+    }
+
+    #[test]
+    fn film_grain_params() {
+        // apply_grain = 1, grain_seed = 0x1234, luma points (0, 20) and (255, 40), a cb point
+        // (128, 30), no cr points, grain_scaling_minus_8 = 3, ar_coeff_lag = 1, 4 luma and 5 cb
+        // AR coefficients, ar_coeff_shift_minus_6 = 1, grain_scale_shift = 0, cb_mult = 200,
+        // cb_luma_mult = 190, cb_offset = 300, overlap_flag = 1, clip_to_restricted_range = 0
+        const TEST_BUF: [u8; 24] = [
+            0x89, 0x1a, 0x10, 0x00, 0xa7, 0xf9, 0x40, 0x60, 0x07, 0x83, 0x60, 0x9f, 0xa0, 0x23,
+            0x20, 0x20, 0x5f, 0xe0, 0x19, 0x13, 0x22, 0xfa, 0x59, 0x00,
+        ];
+
+        let mut bs = BitstreamReader::from_slice(&TEST_BUF);
+        let fg = parse_film_grain_params(
+            &mut bs,
+            &seq_film_grain(),
+            &FrameHeaderObu::default(),
+            &RefFrames::new(),
+        )
+        .unwrap();
+        assert!(fg.apply_grain);
+        assert_eq!(fg.grain_seed, 0x1234);
+        assert!(fg.update_grain);
+        assert_eq!(fg.num_y_points, 2);
+        assert_eq!(fg.point_y_value[..2], [0, 255]);
+        assert_eq!(fg.point_y_scaling[..2], [20, 40]);
+        assert!(!fg.chroma_scaling_from_luma);
+        assert_eq!(fg.num_cb_points, 1);
+        assert_eq!((fg.point_cb_value[0], fg.point_cb_scaling[0]), (128, 30));
+        assert_eq!(fg.num_cr_points, 0);
+        assert_eq!(fg.grain_scaling_minus_8, 3);
+        assert_eq!(fg.ar_coeff_lag, 1);
+        assert_eq!(fg.ar_coeffs_y_plus_128[..5], [130, 126, 128, 140, 0]);
+        assert_eq!(fg.ar_coeffs_cb_plus_128[..6], [128, 129, 127, 128, 100, 0]);
+        assert_eq!(fg.ar_coeffs_cr_plus_128, [0; MAX_NUM_POS_LUMA + 1]);
+        assert_eq!(fg.ar_coeff_shift_minus_6, 1);
+        assert_eq!(fg.grain_scale_shift, 0);
+        assert_eq!((fg.cb_mult, fg.cb_luma_mult, fg.cb_offset), (200, 190, 300));
+        assert_eq!((fg.cr_mult, fg.cr_luma_mult, fg.cr_offset), (0, 0, 0));
+        assert!(fg.overlap_flag);
+        assert!(!fg.clip_to_restricted_range);
+        assert_eq!(bs.position(), 185);
+    }
+
+    #[test]
+    fn film_grain_params_load_from_ref() {
+        // apply_grain = 1, grain_seed = 0xabcd, update_grain = 0, film_grain_params_ref_idx = 2
+        const TEST_BUF: [u8; 3] = [0b11010101, 0b11100110, 0b10010000];
+
+        let stored = FilmGrainParams {
+            apply_grain: true,
+            grain_seed: 0x1234,
+            update_grain: true,
+            num_y_points: 1,
+            point_y_value: [64; MAX_NUM_Y_POINTS],
+            grain_scale_shift: 2,
+            ..Default::default()
+        };
+        let mut refs = RefFrames::new();
+        refs.update(&FrameHeaderObu {
+            frame_type: INTER_FRAME,
+            refresh_frame_flags: 1 << 2,
+            film_grain_params: stored,
+            ..Default::default()
+        });
+
+        let mut fh = FrameHeaderObu {
+            frame_type: INTER_FRAME,
+            frame_is_intra: false,
+            ref_frame_idx: [0, 1, 2, 3, 4, 5, 6],
+            ..Default::default()
+        };
+        let mut bs = BitstreamReader::from_slice(&TEST_BUF);
+        let fg = parse_film_grain_params(&mut bs, &seq_film_grain(), &fh, &refs).unwrap();
+        assert!(fg.is_inherited());
+        assert!(!stored.is_inherited());
+        assert_eq!(
+            fg,
+            FilmGrainParams {
+                grain_seed: 0xabcd,
+                update_grain: false,
+                film_grain_params_ref_idx: 2,
+                ..stored
+            }
+        );
+        assert_eq!(bs.position(), 21);
+
+        // Slot 3 holds a frame that was coded without grain, which can't be inherited from
+        const REF_IDX_3: [u8; 3] = [0b11010101, 0b11100110, 0b10011000];
+        refs.update(&FrameHeaderObu {
+            frame_type: INTER_FRAME,
+            refresh_frame_flags: 1 << 3,
+            film_grain_params: FilmGrainParams {
+                apply_grain: false,
+                ..stored
+            },
+            ..Default::default()
+        });
+        let mut bs = BitstreamReader::from_slice(&REF_IDX_3);
+        assert!(matches!(
+            parse_film_grain_params(&mut bs, &seq_film_grain(), &fh, &refs),
+            Err(Error::Invalid(_))
+        ));
+
+        // The slot must be one of the frame's references
+        fh.ref_frame_idx = [0, 1, 3, 4, 5, 6, 7];
+        let mut bs = BitstreamReader::from_slice(&TEST_BUF);
+        assert!(matches!(
+            parse_film_grain_params(&mut bs, &seq_film_grain(), &fh, &refs),
+            Err(Error::Invalid(_))
+        ));
+    }
+
+    #[test]
+    fn film_grain_params_load_without_grain() {
+        // apply_grain = 1, grain_seed = 0xabcd, update_grain = 0, film_grain_params_ref_idx = 2
+        const TEST_BUF: [u8; 3] = [0b11010101, 0b11100110, 0b10010000];
+
+        // Slot 2 was never refreshed, so it has no film grain parameters
+        let fh = FrameHeaderObu {
+            frame_type: INTER_FRAME,
+            frame_is_intra: false,
+            ref_frame_idx: [0, 1, 2, 3, 4, 5, 6],
+            ..Default::default()
+        };
+        let mut bs = BitstreamReader::from_slice(&TEST_BUF);
+        assert!(matches!(
+            parse_film_grain_params(&mut bs, &seq_film_grain(), &fh, &RefFrames::new()),
+            Err(Error::Invalid(_))
+        ));
+    }
+
+    #[test]
+    fn render_size_different() {
+        // render_width_minus_1 = 1919, render_height_minus_1 = 799
+        const TEST_BUF: [u8; 5] = [0b10000011, 0b10111111, 0b10000001, 0b10001111, 0b10000000];
+
+        let size = FrameSize {
+            frame_width: 1920,
+            frame_height: 1080,
+            upscaled_width: 1920,
+            ..Default::default()
+        };
+
+        let mut bytes = Cursor::new(&TEST_BUF);
+        let mut bs = BitstreamReader::new(&mut bytes).unwrap();
+        assert_eq!(parse_render_size(&mut bs, &size).unwrap(), (1920, 800));
+        assert_eq!(bs.position(), 33);
+    }
+
+    #[test]
+    fn key_frame_header() {
+        let (fh, position) = frame_header(&KEY_FRAME_64X64, &seq_64x64(), &RefFrames::new());
+        assert_eq!(position, 204);
+
+        assert!(!fh.show_existing_frame);
+        assert_eq!(fh.frame_type, KEY_FRAME);
+        assert!(fh.frame_is_intra);
+        assert!(fh.show_frame);
+        assert!(!fh.showable_frame);
+        assert!(fh.error_resilient_mode);
+        assert_eq!(fh.order_hint, 0);
+        assert_eq!(fh.primary_ref_frame, PRIMARY_REF_NONE);
+        assert_eq!(fh.refresh_frame_flags, 0xff);
+        assert_eq!(
+            fh.frame_size,
+            FrameSize {
+                frame_width: 64,
+                frame_height: 64,
+                upscaled_width: 64,
+                ..Default::default()
+            }
+        );
+        assert_eq!((fh.render_width, fh.render_height), (64, 64));
+        assert_eq!(fh.tx_mode, TX_MODE_LARGEST);
+        assert!(fh.reduced_tx_set);
+    }
+
+    #[test]
+    fn context_from_cloned_sequence_header() {
+        let mut context = {
+            let buf = SEQ_64X64.to_vec();
+            let seq = parse_sequence_header(&mut BitstreamReader::from_slice(&buf)).unwrap();
+            SequenceHeaderContext::new(seq.clone())
+        };
+        assert_eq!(context.sequence_header, seq_64x64());
+
+        let mut bs = BitstreamReader::from_slice(&KEY_FRAME_64X64);
+        let key_frame = context.parse_frame_header(&mut bs, None).unwrap();
+        assert_eq!(key_frame.frame_type, KEY_FRAME);
+        assert!((0..NUM_REF_FRAMES).all(|i| context.refs.get(i).valid));
+
+        // A copy of the context parses the next frame the same way
+        let mut copy = context.clone();
+        let mut bs = BitstreamReader::from_slice(&HIDDEN_INTER_FRAME_64X64);
+        let fh = copy.parse_frame_header(&mut bs, None).unwrap();
+        assert_eq!(bs.position(), 164);
+        assert_eq!(fh.frame_type, INTER_FRAME);
+        assert_eq!(fh.order_hint, 4);
+        assert_eq!(fh.frame_size.frame_width, 64);
+        assert_eq!(copy.refs.get(1).order_hint, 4);
+        assert_eq!(context.refs.get(1).order_hint, 0);
+    }
+
+    #[test]
+    fn consistent_headers() {
+        let seq = seq_64x64();
+        let mut refs = RefFrames::new();
+        let (key_frame, _) = frame_header(&KEY_FRAME_64X64, &seq, &refs);
+        assert!(check_consistency(&seq, &key_frame).is_ok());
+        refs.update(&key_frame);
+        let (inter_frame, _) = frame_header(&HIDDEN_INTER_FRAME_64X64, &seq, &refs);
+        assert!(check_consistency(&seq, &inter_frame).is_ok());
+
+        // The 64x64 frame paired with the sequence header of a smaller stream
+        let small_seq = SequenceHeaderObu {
+            max_frame_width_minus_1: 31,
+            ..seq.clone()
+        };
+        assert!(matches!(
+            check_consistency(&small_seq, &key_frame),
+            Err(Error::Invalid(_))
+        ));
+
+        let frame_id = FrameHeaderObu {
+            current_frame_id: 1,
+            ..key_frame.clone()
+        };
+        assert!(matches!(
+            check_consistency(&seq, &frame_id),
+            Err(Error::Invalid(_))
+        ));
+
+        let no_order_hint = SequenceHeaderObu {
+            enable_order_hint: false,
+            ..seq.clone()
+        };
+        assert!(matches!(
+            check_consistency(&no_order_hint, &inter_frame),
+            Err(Error::Invalid(_))
+        ));
+    }
+
+    #[test]
+    fn frame_ids() {
+        let seq = SequenceHeaderObu {
+            frame_id_numbers_present_flag: true,
+            delta_frame_id_length_minus_2: 2,
+            additional_frame_id_length_minus_1: 0,
+            ..seq_64x64()
+        };
+        let mut refs = RefFrames::new();
+        let (key_frame, position) = frame_header(&KEY_FRAME_64X64_FRAME_ID, &seq, &refs);
+        assert_eq!(position, 204 + 5);
+        assert_eq!(key_frame.current_frame_id, 3);
+        assert_eq!(key_frame.delta_frame_id_minus_1, None);
+        refs.update(&key_frame);
+
+        let (inter_frame, position) = frame_header(&HIDDEN_INTER_FRAME_64X64_FRAME_ID, &seq, &refs);
+        assert_eq!(position, 164 + 5 + 7 * 4);
+        assert_eq!(inter_frame.current_frame_id, 4);
+        assert_eq!(
+            inter_frame.delta_frame_id_minus_1,
+            Some([0; REFS_PER_FRAME])
+        );
+        assert_eq!(inter_frame.order_hint, 4);
+        assert!(check_consistency(&seq, &inter_frame).is_ok());
+
+        let frames = [key_frame.clone(), inter_frame.clone()];
+        assert!(frame_id_discontinuities(&seq, &frames).is_empty());
+        assert!(frame_id_discontinuities(&seq_64x64(), &frames).is_empty());
+
+        // A repeated id, a jump of half of the 5 bit id range (as if frames 5 to 19 were
+        // dropped), then a frame whose delta_frame_id_minus_1 doesn't lead back to the key
+        // frame in slot 0
+        let next = |current_frame_id, delta_frame_id_minus_1| FrameHeaderObu {
+            current_frame_id,
+            delta_frame_id_minus_1: Some([delta_frame_id_minus_1; REFS_PER_FRAME]),
+            ref_frame_idx: [0; REFS_PER_FRAME],
+            refresh_frame_flags: 0,
+            ..inter_frame.clone()
+        };
+        let frames = [
+            key_frame.clone(),
+            inter_frame.clone(),
+            next(4, 0),
+            next(20, 16),
+            next(21, 17),
+            next(22, 15),
+        ];
+        assert_eq!(frame_id_discontinuities(&seq, &frames), vec![2, 3, 5]);
+
+        // Ids wrap around, and a shown key frame starts over
+        let frames = [
+            FrameHeaderObu {
+                current_frame_id: 30,
+                ..key_frame.clone()
+            },
+            next(1, 2),
+            key_frame,
+        ];
+        assert!(frame_id_discontinuities(&seq, &frames).is_empty());
+    }
+
+    #[test]
+    fn display_order_pyramid() {
+        // A key frame, then a hidden frame four ahead and a hidden frame two ahead, each shown
+        // with show_existing_frame after the frame before it. With OrderHintBits = 4 the order
+        // hints wrap around from 15 to 0.
+        let seq = SequenceHeaderObu {
+            enable_order_hint: true,
+            order_hint_bits_minus_1: 3,
+            ..Default::default()
+        };
+        let frame = |order_hint, show_frame, refresh_frame_flags| FrameHeaderObu {
+            frame_type: INTER_FRAME,
+            order_hint,
+            show_frame,
+            refresh_frame_flags,
+            ..Default::default()
+        };
+        let show_existing = |frame_to_show_map_idx| FrameHeaderObu {
+            show_existing_frame: true,
+            frame_to_show_map_idx,
+            ..Default::default()
+        };
+        let key_frame = |order_hint| FrameHeaderObu {
+            frame_type: KEY_FRAME,
+            ..frame(order_hint, true, 0xff)
+        };
+        let frames = [
+            key_frame(14),
+            frame(2, false, 1 << 1),
+            frame(0, false, 1 << 2),
+            frame(15, true, 0),
+            show_existing(2),
+            frame(1, true, 0),
+            show_existing(1),
+            // Never shown
+            frame(3, false, 1 << 3),
+        ];
+
+        assert_eq!(display_order(&seq, &frames), vec![0, 3, 2, 5, 1]);
+        assert!(display_order(&seq, &[]).is_empty());
+
+        // The same frames without enable_order_hint, where all the hints are 0
+        let no_order_hint = SequenceHeaderObu {
+            enable_order_hint: false,
+            ..Default::default()
+        };
+        let frames_without_hints = frames.clone().map(|frame| FrameHeaderObu {
+            order_hint: 0,
+            ..frame
+        });
+        assert_eq!(
+            display_order(&no_order_hint, &frames_without_hints),
+            vec![0, 3, 2, 5, 1]
+        );
+
+        // A second key frame restarts the order hints, after frames that had wrapped around
+        let mut frames = frames.to_vec();
+        frames.extend([
+            key_frame(0),
+            frame(2, false, 1 << 1),
+            frame(1, true, 0),
+            show_existing(1),
+        ]);
+        assert_eq!(display_order(&seq, &frames), vec![0, 3, 2, 5, 1, 8, 10, 9]);
+    }
+
+    #[test]
+    fn inter_frame_header() {
+        let seq = seq_64x64();
+        let mut refs = RefFrames::new();
+        let (key_frame, _) = frame_header(&KEY_FRAME_64X64, &seq, &refs);
+        refs.update(&key_frame);
+        assert!((0..NUM_REF_FRAMES).all(|i| refs.get(i).valid));
+
+        let (fh, position) = frame_header(&HIDDEN_INTER_FRAME_64X64, &seq, &refs);
+        assert_eq!(position, 164);
+        assert_eq!(fh.frame_type, INTER_FRAME);
+        assert!(!fh.frame_is_intra);
+        assert!(!fh.show_frame);
+        assert!(fh.showable_frame);
+        assert!(!fh.error_resilient_mode);
+        assert_eq!(fh.order_hint, 4);
+        assert_eq!(fh.primary_ref_frame, 2);
+        assert_eq!(fh.refresh_frame_flags, 0b00000010);
+        assert_eq!(fh.refreshed_slots().collect::<Vec<_>>(), [1]);
+        assert_eq!(fh.ref_frame_idx, [0, 3, 0, 0, 0, 0, 0]);
+        assert_eq!(fh.frame_size.frame_width, 64);
+        assert_eq!(fh.frame_size.frame_height, 64);
+        assert!(!fh.reference_select);
+
+        refs.update(&fh);
+        assert_eq!(refs.get(0).order_hint, 0);
+        assert_eq!(refs.get(1).order_hint, 4);
+        assert_eq!(refs.get(1).frame_type, INTER_FRAME);
+    }
+
+    #[test]
+    fn show_existing_frame_header() {
+        // show_existing_frame = 1, frame_to_show_map_idx = 1
+        const TEST_BUF: [u8; 1] = [0b10010000];
+
+        let seq = seq_64x64();
+        let mut refs = RefFrames::new();
+        let (key_frame, _) = frame_header(&KEY_FRAME_64X64, &seq, &refs);
+        refs.update(&key_frame);
+
+        // Showing a key frame refreshes every reference frame
+        let (fh, position) = frame_header(&TEST_BUF, &seq, &refs);
+        assert_eq!(position, 4);
+        assert!(fh.show_existing_frame);
+        assert_eq!(fh.frame_to_show_map_idx, 1);
+        assert_eq!(fh.frame_type, KEY_FRAME);
+        assert_eq!(fh.refresh_frame_flags, 0xff);
+        assert_eq!(fh.frame_size.frame_width, 64);
+
+        let (inter_frame, _) = frame_header(&HIDDEN_INTER_FRAME_64X64, &seq, &refs);
+        refs.update(&inter_frame);
+
+        let (fh, _) = frame_header(&TEST_BUF, &seq, &refs);
+        assert_eq!(fh.frame_type, INTER_FRAME);
+        assert_eq!(fh.order_hint, 4);
+        assert_eq!(fh.refresh_frame_flags, 0);
+    }
+
+    #[test]
+    fn show_existing_frame_presentation_time() {
+        // show_existing_frame = 1, frame_to_show_map_idx = 5, frame_presentation_time = 0x2a5
+        const TEST_BUF: [u8; 2] = [0b11011010, 0b10010100];
+
+        let seq = SequenceHeaderObu {
+            timing_info: Some(SequenceHeaderTimingInfo::default()),
+            decoder_model_info: Some(SequenceHeaderDecoderModelInfo {
+                frame_presentation_time_length_minus_1: 9,
+                ..Default::default()
+            }),
+            ..seq_64x64()
+        };
+        let mut refs = RefFrames::new();
+        let (key_frame, _) = frame_header(&KEY_FRAME_64X64, &seq_64x64(), &refs);
+        refs.update(&key_frame);
+
+        let (fh, position) = frame_header(&TEST_BUF, &seq, &refs);
+        assert_eq!(fh.shown_frame_slot(), Some(5));
+        assert_eq!(fh.frame_presentation_time, Some(0x2a5));
+        assert_eq!(fh.frame_size.frame_width, 64);
+        assert_eq!(position, 14);
+
+        assert_eq!(key_frame.shown_frame_slot(), None);
+        assert_eq!(key_frame.frame_presentation_time, None);
+    }
+
+    #[test]
+    fn refreshed_slots() {
+        let fh = FrameHeaderObu {
+            refresh_frame_flags: 0b10100101,
+            ..Default::default()
+        };
+        assert_eq!(fh.refreshed_slots().collect::<Vec<_>>(), [0, 2, 5, 7]);
+
+        let fh = FrameHeaderObu::default();
+        assert_eq!(fh.refreshed_slots().next(), None);
+    }
+
+    #[test]
+    fn frame_type_classification() {
+        let seq = seq_64x64();
+        let mut refs = RefFrames::new();
+        let (key_frame, _) = frame_header(&KEY_FRAME_64X64, &seq, &refs);
+        assert_eq!(key_frame.frame_type(), FrameType::Key);
+        assert!(key_frame.is_keyframe());
+        assert!(key_frame.is_intra());
+        refs.update(&key_frame);
+
+        let (inter_frame, _) = frame_header(&HIDDEN_INTER_FRAME_64X64, &seq, &refs);
+        assert_eq!(inter_frame.frame_type(), FrameType::Inter);
+        assert!(!inter_frame.is_keyframe());
+        assert!(!inter_frame.is_intra());
+
+        let intra_only = FrameHeaderObu {
+            frame_type: INTRA_ONLY_FRAME,
+            ..Default::default()
+        };
+        assert_eq!(intra_only.frame_type(), FrameType::IntraOnly);
+        assert!(!intra_only.is_keyframe());
+        assert!(intra_only.is_intra());
+
+        let switch = FrameHeaderObu {
+            frame_type: SWITCH_FRAME,
+            frame_is_intra: false,
+            ..Default::default()
+        };
+        assert_eq!(switch.frame_type(), FrameType::Switch);
+        assert!(!switch.is_keyframe());
+        assert!(!switch.is_intra());
+
+        // show_existing_frame of the key frame takes its frame type, but isn't a key frame
+        let (shown_key_frame, _) = frame_header(&[0b10010000], &seq, &refs);
+        assert_eq!(shown_key_frame.frame_type(), FrameType::Key);
+        assert!(!shown_key_frame.is_keyframe());
+        assert!(shown_key_frame.is_intra());
+    }
+
+    #[test]
+    fn reduced_still_picture_frame_header() {
+        // SEQ_STILL, then a frame header with disable_cdf_update = 0,
+        // allow_screen_content_tools = 0 and render_and_frame_size_different = 0
+        let mut bytes = Cursor::new(&SEQ_STILL);
+        let mut bs = BitstreamReader::new(&mut bytes).unwrap();
+        let seq = parse_sequence_header(&mut bs).unwrap();
+
+        let mut bytes = Cursor::new(&[0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00]);
+        let mut bs = BitstreamReader::new(&mut bytes).unwrap();
+        let fh = parse_frame_header(&mut bs, &seq, &RefFrames::new(), None).unwrap();
+        assert_eq!(fh.frame_type, KEY_FRAME);
+        assert!(fh.show_frame);
+        assert!(!fh.show_existing_frame);
+        assert_eq!(fh.refresh_frame_flags, 0xff);
+        assert!(fh.disable_frame_end_update_cdf);
+        assert_eq!(fh.frame_size.frame_width, 64);
+    }
+
+    #[test]
+    fn order_hint_enabled_and_disabled() {
+        const TEST_BUF: [u8; 2] = [0b1011_0011, 0b1000_0000];
+
+        let mut reader = BitstreamReader::from_slice(&TEST_BUF);
+        assert_eq!(read_order_hint(&mut reader, 7).unwrap(), 0b101_1001);
+        assert_eq!(reader.position(), 7);
+        assert_eq!(read_order_hint(&mut reader, 0).unwrap(), 0);
+        assert_eq!(reader.position(), 7);
+        assert_eq!(read_order_hint(&mut reader, 8).unwrap(), 0b1100_0000);
+        assert_eq!(reader.position(), 15);
+
+        let seq = SequenceHeaderObu {
+            enable_order_hint: false,
+            order_hint_bits_minus_1: 6,
+            ..Default::default()
+        };
+        assert_eq!(seq.order_hint_bits(), 0);
+        assert_eq!(get_relative_dist(&seq, 5, 3), 0);
+    }
+
+    #[test]
+    fn mi_and_sb_dims() {
+        assert_eq!(compute_mi_dims(64, 64), (16, 16));
+        assert_eq!(sb_cols_rows(16, 16, false), (1, 1));
+        assert_eq!(sb_cols_rows(16, 16, true), (1, 1));
+
+        assert_eq!(compute_mi_dims(1920, 1080), (480, 270));
+        assert_eq!(sb_cols_rows(480, 270, false), (30, 17));
+        assert_eq!(sb_cols_rows(480, 270, true), (15, 9));
+
+        // Sizes that aren't a multiple of 8 round up to the next 8x8 block
+        assert_eq!(compute_mi_dims(1, 1), (2, 2));
+        assert_eq!(compute_mi_dims(100, 57), (26, 16));
+        assert_eq!(sb_cols_rows(26, 16, false), (2, 1));
+        assert_eq!(sb_cols_rows(26, 16, true), (1, 1));
+        assert_eq!(compute_mi_dims(65536, 65535), (16384, 16384));
+    }
+
+    #[test]
+    fn tile_info_uniform() {
+        // uniform_tile_spacing_flag = 1, increment_tile_cols_log2 = 1, 1, 0,
+        // increment_tile_rows_log2 = 1, 0, context_update_tile_id = 5, tile_size_bytes_minus_1 = 3
+        const TEST_BUF: [u8; 2] = [0b11101010, 0b11100000];
+
+        let size = FrameSize {
+            frame_width: 1920,
+            frame_height: 1080,
+            upscaled_width: 1920,
+            ..Default::default()
+        };
+
+        let mut bytes = Cursor::new(&TEST_BUF);
+        let mut bs = BitstreamReader::new(&mut bytes).unwrap();
+        let tile_info = parse_tile_info(&mut bs, &seq_1080p(), &size).unwrap();
+        assert_eq!(bs.position(), 11);
+        assert!(tile_info.uniform_tile_spacing_flag);
+        assert_eq!(tile_info.tile_cols_log2, 2);
+        assert_eq!(tile_info.tile_rows_log2, 1);
+        assert_eq!(tile_info.tile_cols, 4);
+        assert_eq!(tile_info.tile_rows, 2);
+        assert_eq!(tile_info.mi_col_starts, vec![0, 128, 256, 384, 480]);
+        assert_eq!(tile_info.mi_row_starts, vec![0, 144, 270]);
+        assert_eq!(tile_info.context_update_tile_id, 5);
+        assert_eq!(tile_info.tile_size_bytes, 4);
+    }
+
+    #[test]
+    fn tile_info_single_tile() {
+        // uniform_tile_spacing_flag = 1, a single superblock leaves nothing else to signal
+        const TEST_BUF: [u8; 1] = [0b10000000];
+
+        let (fh, _) = frame_header(&KEY_FRAME_64X64, &seq_64x64(), &RefFrames::new());
+        assert_eq!(fh.tile_info.tile_cols, 1);
+        assert_eq!(fh.tile_info.tile_rows, 1);
+        assert_eq!(fh.tile_info.mi_col_starts, vec![0, 16]);
+
+        let mut bytes = Cursor::new(&TEST_BUF);
+        let mut bs = BitstreamReader::new(&mut bytes).unwrap();
+        let tile_info = parse_tile_info(&mut bs, &seq_64x64(), &fh.frame_size).unwrap();
+        assert_eq!(bs.position(), 1);
+        assert_eq!(tile_info.tile_cols, 1);
+        assert_eq!(tile_info.tile_rows, 1);
+        assert_eq!(tile_info.context_update_tile_id, 0);
+        assert_eq!(tile_info.tile_size_bytes, 0);
+    }
+
+    #[test]
+    fn tile_info_explicit() {
+        // uniform_tile_spacing_flag = 0, width_in_sbs_minus_1 = 9, 19, height_in_sbs_minus_1 = 16,
+        // context_update_tile_id = 1, tile_size_bytes_minus_1 = 1
+        const TEST_BUF: [u8; 3] = [0b00101111, 0b11111111, 0b10100000];
+
+        let size = FrameSize {
+            frame_width: 1920,
+            frame_height: 1080,
+            upscaled_width: 1920,
+            ..Default::default()
+        };
+
+        let mut bytes = Cursor::new(&TEST_BUF);
+        let mut bs = BitstreamReader::new(&mut bytes).unwrap();
+        let tile_info = parse_tile_info(&mut bs, &seq_1080p(), &size).unwrap();
+        assert_eq!(bs.position(), 19);
+        assert!(!tile_info.uniform_tile_spacing_flag);
+        assert_eq!(tile_info.tile_cols, 2);
+        assert_eq!(tile_info.tile_rows, 1);
+        assert_eq!(tile_info.tile_cols_log2, 1);
+        assert_eq!(tile_info.tile_rows_log2, 0);
+        assert_eq!(tile_info.mi_col_starts, vec![0, 160, 480]);
+        assert_eq!(tile_info.mi_row_starts, vec![0, 270]);
+        assert_eq!(tile_info.context_update_tile_id, 1);
+        assert_eq!(tile_info.tile_size_bytes, 2);
+    }
+}
@@ -0,0 +1,315 @@
+use alloc::vec::Vec;
+
+use crate::av1::frame_header::FrameHeaderObu;
+use crate::av1::obu::SequenceHeaderObu;
+
+/// The limits a level places on a stream - A.3
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct LevelLimits {
+    /// Luma samples in a frame
+    pub max_pic_size: u32,
+    pub max_h_size: u32,
+    pub max_v_size: u32,
+    /// Luma samples shown per second
+    pub max_display_rate: u64,
+    /// Luma samples decoded per second
+    pub max_decode_rate: u64,
+    /// Frame headers per second
+    pub max_header_rate: u32,
+    pub max_tiles: u32,
+    pub max_tile_cols: u32,
+}
+
+/// seq_level_idx, MaxPicSize, MaxHSize, MaxVSize, MaxDisplayRate, MaxDecodeRate,
+/// MaxHeaderRate, MaxTiles and MaxTileCols of each defined level
+#[rustfmt::skip]
+const LEVEL_TABLE: [[u64; 9]; 18] = [
+    [0, 147456, 2048, 1152, 4423680, 5529600, 150, 8, 4], // 2.0
+    [1, 278784, 2816, 1584, 8363520, 10454400, 150, 8, 4], // 2.1
+    [4, 665856, 4352, 2448, 19975680, 24969600, 150, 16, 6], // 3.0
+    [5, 1065024, 5504, 3096, 31950720, 39938400, 150, 16, 6], // 3.1
+    [8, 2359296, 6144, 3456, 70778880, 77856768, 300, 32, 8], // 4.0
+    [9, 2359296, 6144, 3456, 141557760, 155713536, 300, 32, 8], // 4.1
+    [12, 8912896, 8192, 4352, 267386880, 273715200, 300, 64, 8], // 5.0
+    [13, 8912896, 8192, 4352, 534773760, 547430400, 300, 64, 8], // 5.1
+    [14, 8912896, 8192, 4352, 1069547520, 1094860800, 300, 64, 8], // 5.2
+    [15, 8912896, 8192, 4352, 1069547520, 1176502272, 300, 64, 8], // 5.3
+    [16, 35651584, 16384, 8704, 1069547520, 1176502272, 300, 128, 16], // 6.0
+    [17, 35651584, 16384, 8704, 2139095040, 2189721600, 300, 128, 16], // 6.1
+    [18, 35651584, 16384, 8704, 4278190080, 4379443200, 300, 128, 16], // 6.2
+    [19, 35651584, 16384, 8704, 4278190080, 4706009088, 300, 128, 16], // 6.3
+    [20, 142606336, 32768, 17408, 4278190080, 4706009088, 300, 256, 32], // 7.0
+    [21, 142606336, 32768, 17408, 8556380160, 8758886400, 300, 256, 32], // 7.1
+    [22, 142606336, 32768, 17408, 17112760320, 17517772800, 300, 256, 32], // 7.2
+    [23, 142606336, 32768, 17408, 17112760320, 18824036352, 300, 256, 32], // 7.3
+];
+
+/// The limits of the level a seq_level_idx stands for, or None for the reserved levels and
+/// level 31, which has no limits
+pub fn level_limits(seq_level_idx: u8) -> Option<LevelLimits> {
+    let row = LEVEL_TABLE
+        .iter()
+        .find(|row| row[0] == seq_level_idx as u64)?;
+    Some(LevelLimits {
+        max_pic_size: row[1] as u32,
+        max_h_size: row[2] as u32,
+        max_v_size: row[3] as u32,
+        max_display_rate: row[4],
+        max_decode_rate: row[5],
+        max_header_rate: row[6] as u32,
+        max_tiles: row[7] as u32,
+        max_tile_cols: row[8] as u32,
+    })
+}
+
+/// A level limit that a stream exceeds
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum LevelConstraint {
+    PicSize,
+    HSize,
+    VSize,
+    Tiles,
+    TileCols,
+    DisplayRate,
+}
+
+/// A frame, or the stream as a whole, exceeding a limit of the signaled level
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct LevelViolation {
+    pub constraint: LevelConstraint,
+    /// The index of the offending frame, or None for constraints on the whole stream
+    pub frame: Option<usize>,
+    pub value: u64,
+    pub limit: u64,
+}
+
+/// Checks the frame headers of a stream against the level of its first operating point. The
+/// display rate is only checked when the timing info signals a constant frame rate.
+pub fn validate_level(seq: &SequenceHeaderObu, frames: &[FrameHeaderObu]) -> Vec<LevelViolation> {
+    let mut violations = Vec::new();
+    let Some(limits) = seq.seq_level_idx.first().and_then(|&idx| level_limits(idx)) else {
+        return violations;
+    };
+
+    let mut check = |constraint, frame, value: u64, limit: u64| {
+        if value > limit {
+            violations.push(LevelViolation {
+                constraint,
+                frame,
+                value,
+                limit,
+            });
+        }
+    };
+
+    let mut max_shown_pic_size = 0;
+    for (i, fh) in frames.iter().enumerate() {
+        // Frames shown with show_existing_frame aren't decoded again
+        if fh.show_existing_frame {
+            continue;
+        }
+
+        let frame_size = &fh.frame_size;
+        let pic_size = frame_size.upscaled_width as u64 * frame_size.frame_height as u64;
+        let tile_info = &fh.tile_info;
+        check(
+            LevelConstraint::PicSize,
+            Some(i),
+            pic_size,
+            limits.max_pic_size as u64,
+        );
+        check(
+            LevelConstraint::HSize,
+            Some(i),
+            frame_size.upscaled_width as u64,
+            limits.max_h_size as u64,
+        );
+        check(
+            LevelConstraint::VSize,
+            Some(i),
+            frame_size.frame_height as u64,
+            limits.max_v_size as u64,
+        );
+        check(
+            LevelConstraint::Tiles,
+            Some(i),
+            tile_info.tile_cols as u64 * tile_info.tile_rows as u64,
+            limits.max_tiles as u64,
+        );
+        check(
+            LevelConstraint::TileCols,
+            Some(i),
+            tile_info.tile_cols as u64,
+            limits.max_tile_cols as u64,
+        );
+
+        if fh.show_frame || fh.showable_frame {
+            max_shown_pic_size = max_shown_pic_size.max(pic_size);
+        }
+    }
+
+    // With equal_picture_interval, every frame is shown for the same number of ticks
+    if let Some(timing_info) = seq
+        .timing_info
+        .as_ref()
+        .filter(|t| t.equal_picture_interval)
+    {
+        let ticks_per_picture = timing_info.num_units_in_display_tick as u64
+            * (timing_info.num_ticks_per_picture_minus_1 as u64 + 1);
+        if let Some(display_rate) =
+            (max_shown_pic_size * timing_info.time_scale as u64).checked_div(ticks_per_picture)
+        {
+            check(
+                LevelConstraint::DisplayRate,
+                None,
+                display_rate,
+                limits.max_display_rate,
+            );
+        }
+    }
+
+    violations
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::av1::frame_header::{FrameSize, TileInfo};
+    use crate::av1::obu::SequenceHeaderTimingInfo;
+    use alloc::vec;
+
+    fn seq_at_level(seq_level_idx: u8) -> SequenceHeaderObu {
+        SequenceHeaderObu {
+            seq_level_idx: vec![seq_level_idx],
+            seq_tier: vec![0],
+            ..Default::default()
+        }
+    }
+
+    fn shown_frame(width: u32, height: u32, tile_cols: u32, tile_rows: u32) -> FrameHeaderObu {
+        FrameHeaderObu {
+            show_frame: true,
+            frame_size: FrameSize {
+                frame_width: width,
+                frame_height: height,
+                upscaled_width: width,
+                ..Default::default()
+            },
+            tile_info: TileInfo {
+                tile_cols,
+                tile_rows,
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn level_4_0() {
+        assert_eq!(
+            level_limits(8),
+            Some(LevelLimits {
+                max_pic_size: 2359296,
+                max_h_size: 6144,
+                max_v_size: 3456,
+                max_display_rate: 70778880,
+                max_decode_rate: 77856768,
+                max_header_rate: 300,
+                max_tiles: 32,
+                max_tile_cols: 8,
+            })
+        );
+    }
+
+    #[test]
+    fn level_5_1() {
+        assert_eq!(
+            level_limits(13),
+            Some(LevelLimits {
+                max_pic_size: 8912896,
+                max_h_size: 8192,
+                max_v_size: 4352,
+                max_display_rate: 534773760,
+                max_decode_rate: 547430400,
+                max_header_rate: 300,
+                max_tiles: 64,
+                max_tile_cols: 8,
+            })
+        );
+    }
+
+    #[test]
+    fn reserved_levels() {
+        for seq_level_idx in [2, 3, 6, 7, 10, 11, 24, 30, 31] {
+            assert_eq!(level_limits(seq_level_idx), None);
+        }
+    }
+
+    #[test]
+    fn validate_within_level() {
+        let frames = [shown_frame(1920, 1080, 2, 2), shown_frame(1280, 720, 1, 1)];
+        assert_eq!(validate_level(&seq_at_level(8), &frames), []);
+        // Level 31 has no limits
+        assert_eq!(validate_level(&seq_at_level(31), &frames), []);
+    }
+
+    #[test]
+    fn validate_pic_size() {
+        // 1080p exceeds the 1065024 samples of level 3.1, though not its dimensions
+        let frames = [shown_frame(1280, 720, 1, 1), shown_frame(1920, 1080, 1, 1)];
+        assert_eq!(
+            validate_level(&seq_at_level(5), &frames),
+            [LevelViolation {
+                constraint: LevelConstraint::PicSize,
+                frame: Some(1),
+                value: 1920 * 1080,
+                limit: 1065024,
+            }]
+        );
+    }
+
+    #[test]
+    fn validate_tiles_and_display_rate() {
+        // 1080p60 at level 4.0, which allows 1080p30
+        let mut seq = seq_at_level(8);
+        seq.timing_info = Some(SequenceHeaderTimingInfo {
+            num_units_in_display_tick: 1,
+            time_scale: 60,
+            equal_picture_interval: true,
+            num_ticks_per_picture_minus_1: 0,
+        });
+        let frames = [shown_frame(1920, 1080, 16, 4)];
+        assert_eq!(
+            validate_level(&seq, &frames),
+            [
+                LevelViolation {
+                    constraint: LevelConstraint::Tiles,
+                    frame: Some(0),
+                    value: 64,
+                    limit: 32,
+                },
+                LevelViolation {
+                    constraint: LevelConstraint::TileCols,
+                    frame: Some(0),
+                    value: 16,
+                    limit: 8,
+                },
+                LevelViolation {
+                    constraint: LevelConstraint::DisplayRate,
+                    frame: None,
+                    value: 1920 * 1080 * 60,
+                    limit: 70778880,
+                },
+            ]
+        );
+
+        seq.timing_info
+            .as_mut()
+            .unwrap()
+            .num_ticks_per_picture_minus_1 = 1;
+        assert_eq!(validate_level(&seq, &[shown_frame(1920, 1080, 1, 1)]), []);
+    }
+}
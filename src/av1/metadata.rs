@@ -0,0 +1,575 @@
+use alloc::vec::Vec;
+use core::fmt;
+
+use crate::bits::{BitstreamReader, ByteRead};
+use crate::Error;
+
+pub const METADATA_TYPE_HDR_CLL: u64 = 1;
+pub const METADATA_TYPE_HDR_MDCV: u64 = 2;
+pub const METADATA_TYPE_SCALABILITY: u64 = 3;
+pub const METADATA_TYPE_ITUT_T35: u64 = 4;
+pub const METADATA_TYPE_TIMECODE: u64 = 5;
+
+pub const SCALABILITY_SS: u8 = 14;
+
+pub const ITU_T_T35_COUNTRY_CODE_US: u8 = 0xb5;
+
+/// metadata_hdr_cll() - 5.8.3
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct HdrCll {
+    pub max_cll: u16,
+    pub max_fall: u16,
+}
+
+/// metadata_hdr_mdcv() - 5.8.4
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct HdrMdcv {
+    /// 0.16 fixed point CIE 1931 x of each of the display primaries
+    pub primary_chromaticity_x: [u16; 3],
+    pub primary_chromaticity_y: [u16; 3],
+    pub white_point_chromaticity_x: u16,
+    pub white_point_chromaticity_y: u16,
+    /// 24.8 fixed point luminance in candelas per square metre
+    pub luminance_max: u32,
+    /// 18.14 fixed point luminance in candelas per square metre
+    pub luminance_min: u32,
+}
+
+impl HdrMdcv {
+    /// The CIE 1931 (x, y) of each of the display primaries
+    pub fn primaries(&self) -> [(f64, f64); 3] {
+        core::array::from_fn(|i| {
+            (
+                self.primary_chromaticity_x[i] as f64 / 65536.0,
+                self.primary_chromaticity_y[i] as f64 / 65536.0,
+            )
+        })
+    }
+
+    /// The CIE 1931 (x, y) of the white point
+    pub fn white_point(&self) -> (f64, f64) {
+        (
+            self.white_point_chromaticity_x as f64 / 65536.0,
+            self.white_point_chromaticity_y as f64 / 65536.0,
+        )
+    }
+
+    /// The maximum luminance in candelas per square metre
+    pub fn max_luminance(&self) -> f64 {
+        self.luminance_max as f64 / 256.0
+    }
+
+    /// The minimum luminance in candelas per square metre
+    pub fn min_luminance(&self) -> f64 {
+        self.luminance_min as f64 / 16384.0
+    }
+}
+
+/// metadata_itut_t35() - 5.8.2
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct ItuTT35<'a> {
+    pub itu_t_t35_country_code: u8,
+    pub itu_t_t35_country_code_extension_byte: Option<u8>,
+    /// The message, without the OBU's trailing bits
+    pub itu_t_t35_payload_bytes: &'a [u8],
+}
+
+impl ItuTT35<'_> {
+    /// Whether this is SMPTE ST 2094-40 (HDR10+) dynamic metadata, by the United States
+    /// country code, Samsung's terminal_provider_code, terminal_provider_oriented_code 1 and
+    /// application_identifier 4
+    pub fn is_hdr10plus(&self) -> bool {
+        self.itu_t_t35_country_code == ITU_T_T35_COUNTRY_CODE_US
+            && self
+                .itu_t_t35_payload_bytes
+                .starts_with(&[0x00, 0x3c, 0x00, 0x01, 0x04])
+    }
+}
+
+/// metadata_scalability() - 5.8.5
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct ScalabilityMetadata {
+    pub scalability_mode_idc: u8,
+    /// Only present when scalability_mode_idc is SCALABILITY_SS
+    pub scalability_structure: Option<ScalabilityStructure>,
+}
+
+/// scalability_structure() - 5.8.6
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct ScalabilityStructure {
+    pub spatial_layers_cnt_minus_1: u8,
+    pub spatial_layer_dimensions_present_flag: bool,
+    pub spatial_layer_description_present_flag: bool,
+    pub temporal_group_description_present_flag: bool,
+    pub scalability_structure_reserved_3bits: u8,
+    pub spatial_layer_max_width: Vec<u16>,
+    pub spatial_layer_max_height: Vec<u16>,
+    pub spatial_layer_ref_id: Vec<u8>,
+    pub temporal_group: Vec<TemporalGroupEntry>,
+}
+
+/// One picture of the temporal group in scalability_structure() - 5.8.6
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct TemporalGroupEntry {
+    pub temporal_group_temporal_id: u8,
+    pub temporal_group_temporal_switching_up_point_flag: bool,
+    pub temporal_group_spatial_switching_up_point_flag: bool,
+    pub temporal_group_ref_pic_diff: Vec<u8>,
+}
+
+/// metadata_timecode() - 5.8.7
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Timecode {
+    pub counting_type: u8,
+    pub full_timestamp_flag: bool,
+    pub discontinuity_flag: bool,
+    pub cnt_dropped_flag: bool,
+    pub n_frames: u16,
+    pub seconds_value: Option<u8>,
+    pub minutes_value: Option<u8>,
+    pub hours_value: Option<u8>,
+    pub time_offset_length: u8,
+    pub time_offset_value: u32,
+}
+
+/// HH:MM:SS:FF, with -- in place of the values a partial timestamp doesn't code
+impl fmt::Display for Timecode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for value in [self.hours_value, self.minutes_value, self.seconds_value] {
+            match value {
+                Some(value) => write!(f, "{:02}:", value)?,
+                None => write!(f, "--:")?,
+            }
+        }
+        write!(f, "{:02}", self.n_frames)
+    }
+}
+
+/// metadata_obu() - 5.8.1
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum Metadata<'a> {
+    HdrCll(HdrCll),
+    HdrMdcv(HdrMdcv),
+    Scalability(ScalabilityMetadata),
+    ItuTT35(ItuTT35<'a>),
+    Timecode(Timecode),
+    /// A reserved or unregistered metadata_type, with the rest of the payload as is
+    Unknown {
+        type_id: u64,
+        data: &'a [u8],
+    },
+}
+
+/// metadata_obu() - 5.8.1
+pub fn parse_metadata(buf: &[u8]) -> Result<Metadata<'_>, Error> {
+    parse_metadata_with_end(buf).map(|(metadata, _)| metadata)
+}
+
+/// metadata_obu() - 5.8.1, also returning the bit position of the trailing bits for the
+/// metadata types whose syntax ends before them
+pub(crate) fn parse_metadata_with_end(buf: &[u8]) -> Result<(Metadata<'_>, Option<u64>), Error> {
+    let mut reader = BitstreamReader::from_slice(buf);
+    let metadata = parse_metadata_payload(&mut reader, buf)?;
+    let end = match metadata {
+        // These run up to the trailing bits
+        Metadata::ItuTT35(_) | Metadata::Unknown { .. } => None,
+        _ => Some(reader.position()),
+    };
+    Ok((metadata, end))
+}
+
+fn parse_metadata_payload<'a, T: ByteRead>(
+    reader: &mut BitstreamReader<T>,
+    buf: &'a [u8],
+) -> Result<Metadata<'a>, Error> {
+    let metadata_type = reader.leb128()?;
+    match metadata_type {
+        METADATA_TYPE_HDR_CLL => Ok(Metadata::HdrCll(HdrCll {
+            max_cll: reader.f(16)? as u16,
+            max_fall: reader.f(16)? as u16,
+        })),
+        METADATA_TYPE_HDR_MDCV => {
+            let mut mdcv = HdrMdcv::default();
+            for i in 0..3 {
+                mdcv.primary_chromaticity_x[i] = reader.f(16)? as u16;
+                mdcv.primary_chromaticity_y[i] = reader.f(16)? as u16;
+            }
+            mdcv.white_point_chromaticity_x = reader.f(16)? as u16;
+            mdcv.white_point_chromaticity_y = reader.f(16)? as u16;
+            mdcv.luminance_max = reader.f(32)?;
+            mdcv.luminance_min = reader.f(32)?;
+            Ok(Metadata::HdrMdcv(mdcv))
+        }
+        METADATA_TYPE_SCALABILITY => {
+            let scalability_mode_idc = reader.f(8)? as u8;
+            let scalability_structure = if scalability_mode_idc == SCALABILITY_SS {
+                Some(parse_scalability_structure(reader)?)
+            } else {
+                None
+            };
+            Ok(Metadata::Scalability(ScalabilityMetadata {
+                scalability_mode_idc,
+                scalability_structure,
+            }))
+        }
+        METADATA_TYPE_ITUT_T35 => {
+            // metadata_itut_t35() - 5.8.2
+            let itu_t_t35_country_code = reader.f(8)? as u8;
+            let itu_t_t35_country_code_extension_byte = if itu_t_t35_country_code == 0xff {
+                Some(reader.f(8)? as u8)
+            } else {
+                None
+            };
+
+            let start = reader.byte_position() as usize;
+            Ok(Metadata::ItuTT35(ItuTT35 {
+                itu_t_t35_country_code,
+                itu_t_t35_country_code_extension_byte,
+                itu_t_t35_payload_bytes: strip_trailing_bits(&buf[start..]),
+            }))
+        }
+        METADATA_TYPE_TIMECODE => Ok(Metadata::Timecode(parse_timecode(reader)?)),
+        type_id => {
+            let start = reader.byte_position() as usize;
+            Ok(Metadata::Unknown {
+                type_id,
+                data: &buf[start..],
+            })
+        }
+    }
+}
+
+/// scalability_structure() - 5.8.6
+fn parse_scalability_structure<T: ByteRead>(
+    reader: &mut BitstreamReader<T>,
+) -> Result<ScalabilityStructure, Error> {
+    let mut structure = ScalabilityStructure {
+        spatial_layers_cnt_minus_1: reader.f(2)? as u8,
+        spatial_layer_dimensions_present_flag: reader.f1()?,
+        spatial_layer_description_present_flag: reader.f1()?,
+        temporal_group_description_present_flag: reader.f1()?,
+        scalability_structure_reserved_3bits: reader.f(3)? as u8,
+        ..Default::default()
+    };
+
+    let spatial_layers = structure.spatial_layers_cnt_minus_1 + 1;
+    if structure.spatial_layer_dimensions_present_flag {
+        for _ in 0..spatial_layers {
+            structure.spatial_layer_max_width.push(reader.f(16)? as u16);
+            structure
+                .spatial_layer_max_height
+                .push(reader.f(16)? as u16);
+        }
+    }
+    if structure.spatial_layer_description_present_flag {
+        for _ in 0..spatial_layers {
+            structure.spatial_layer_ref_id.push(reader.f(8)? as u8);
+        }
+    }
+    if structure.temporal_group_description_present_flag {
+        let temporal_group_size = reader.f(8)?;
+        for _ in 0..temporal_group_size {
+            let mut entry = TemporalGroupEntry {
+                temporal_group_temporal_id: reader.f(3)? as u8,
+                temporal_group_temporal_switching_up_point_flag: reader.f1()?,
+                temporal_group_spatial_switching_up_point_flag: reader.f1()?,
+                ..Default::default()
+            };
+            let temporal_group_ref_cnt = reader.f(3)?;
+            for _ in 0..temporal_group_ref_cnt {
+                entry.temporal_group_ref_pic_diff.push(reader.f(8)? as u8);
+            }
+            structure.temporal_group.push(entry);
+        }
+    }
+    Ok(structure)
+}
+
+/// metadata_timecode() - 5.8.7
+fn parse_timecode<T: ByteRead>(reader: &mut BitstreamReader<T>) -> Result<Timecode, Error> {
+    let mut timecode = Timecode {
+        counting_type: reader.f(5)? as u8,
+        full_timestamp_flag: reader.f1()?,
+        discontinuity_flag: reader.f1()?,
+        cnt_dropped_flag: reader.f1()?,
+        n_frames: reader.f(9)? as u16,
+        ..Default::default()
+    };
+
+    if timecode.full_timestamp_flag {
+        timecode.seconds_value = Some(reader.f(6)? as u8);
+        timecode.minutes_value = Some(reader.f(6)? as u8);
+        timecode.hours_value = Some(reader.f(5)? as u8);
+    } else {
+        let seconds_flag = reader.f1()?;
+        if seconds_flag {
+            timecode.seconds_value = Some(reader.f(6)? as u8);
+            let minutes_flag = reader.f1()?;
+            if minutes_flag {
+                timecode.minutes_value = Some(reader.f(6)? as u8);
+                let hours_flag = reader.f1()?;
+                if hours_flag {
+                    timecode.hours_value = Some(reader.f(5)? as u8);
+                }
+            }
+        }
+    }
+
+    timecode.time_offset_length = reader.f(5)? as u8;
+    if timecode.time_offset_length > 0 {
+        timecode.time_offset_value = reader.f(timecode.time_offset_length)?;
+    }
+    Ok(timecode)
+}
+
+/// Drops the byte aligned trailing_bits() - 5.3.4 from the end of a payload
+fn strip_trailing_bits(buf: &[u8]) -> &[u8] {
+    match buf.iter().rposition(|&byte| byte != 0) {
+        Some(last) if buf[last] == 0x80 => &buf[..last],
+        _ => buf,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::string::ToString;
+    use alloc::vec;
+
+    // Metadata payloads as written by rav1e
+    const HDR_CLL: [u8; 6] = [0x01, 0x03, 0xe8, 0x01, 0x90, 0x80];
+    const HDR_MDCV: [u8; 26] = [
+        0x02, 0x84, 0xd0, 0x3e, 0x80, 0x33, 0xc2, 0x86, 0xc4, 0x1d, 0x4c, 0x0b, 0xb8, 0x3d, 0x13,
+        0x40, 0x42, 0x00, 0x98, 0x96, 0x80, 0x00, 0x00, 0x00, 0x32, 0x80,
+    ];
+
+    #[test]
+    fn hdr_cll() {
+        let metadata = parse_metadata(&HDR_CLL).unwrap();
+        assert_eq!(
+            metadata,
+            Metadata::HdrCll(HdrCll {
+                max_cll: 1000,
+                max_fall: 400,
+            })
+        );
+    }
+
+    #[test]
+    fn hdr_mdcv() {
+        let Metadata::HdrMdcv(mdcv) = parse_metadata(&HDR_MDCV).unwrap() else {
+            panic!("expected HDR MDCV metadata");
+        };
+        assert_eq!(mdcv.primary_chromaticity_x, [34000, 13250, 7500]);
+        assert_eq!(mdcv.primary_chromaticity_y, [16000, 34500, 3000]);
+        assert_eq!(mdcv.white_point_chromaticity_x, 15635);
+        assert_eq!(mdcv.white_point_chromaticity_y, 16450);
+        // 39062.5 and 0.0030517578125 cd/m2
+        assert_eq!(mdcv.luminance_max, 10000000);
+        assert_eq!(mdcv.luminance_min, 50);
+        assert_eq!(mdcv.max_luminance(), 39062.5);
+        assert_eq!(mdcv.min_luminance(), 0.0030517578125);
+    }
+
+    #[test]
+    fn hdr_mdcv_p3() {
+        // A Display P3 mastering display, 1000 to 0.005 cd/m2
+        const TEST_BUF: [u8; 26] = [
+            0x02, 0xae, 0x14, 0x51, 0xec, 0x43, 0xd7, 0xb0, 0xa4, 0x26, 0x66, 0x0f, 0x5c, 0x50,
+            0x0d, 0x54, 0x39, 0x00, 0x03, 0xe8, 0x00, 0x00, 0x00, 0x00, 0x52, 0x80,
+        ];
+        let close = |a: f64, b: f64| (a - b).abs() < 1e-4;
+
+        let Metadata::HdrMdcv(mdcv) = parse_metadata(&TEST_BUF).unwrap() else {
+            panic!("expected HDR MDCV metadata");
+        };
+        assert_eq!(mdcv.primary_chromaticity_x, [44564, 17367, 9830]);
+        assert_eq!(mdcv.primary_chromaticity_y, [20972, 45220, 3932]);
+        assert_eq!(mdcv.luminance_max, 256000);
+        assert_eq!(mdcv.luminance_min, 82);
+
+        let expected = [(0.680, 0.320), (0.265, 0.690), (0.150, 0.060)];
+        for ((x, y), (expected_x, expected_y)) in mdcv.primaries().into_iter().zip(expected) {
+            assert!(close(x, expected_x) && close(y, expected_y));
+        }
+        let (x, y) = mdcv.white_point();
+        assert!(close(x, 0.3127) && close(y, 0.3290));
+        assert_eq!(mdcv.max_luminance(), 1000.0);
+        assert!(close(mdcv.min_luminance(), 0.005));
+    }
+
+    #[test]
+    fn timecode() {
+        // counting_type = 1, full_timestamp_flag = 1, n_frames = 24, 12:34:56, time_offset_length = 0
+        const TEST_BUF: [u8; 6] = [0x05, 0x0c, 0x0c, 0x71, 0x13, 0x01];
+
+        let Metadata::Timecode(timecode) = parse_metadata(&TEST_BUF).unwrap() else {
+            panic!("expected timecode metadata");
+        };
+        assert_eq!(timecode.counting_type, 1);
+        assert!(timecode.full_timestamp_flag);
+        assert!(!timecode.discontinuity_flag);
+        assert!(!timecode.cnt_dropped_flag);
+        assert_eq!(timecode.n_frames, 24);
+        assert_eq!(timecode.seconds_value, Some(56));
+        assert_eq!(timecode.minutes_value, Some(34));
+        assert_eq!(timecode.hours_value, Some(12));
+        assert_eq!(timecode.time_offset_length, 0);
+        assert_eq!(timecode.to_string(), "12:34:56:24");
+    }
+
+    #[test]
+    fn timecode_partial() {
+        // counting_type = 0, full_timestamp_flag = 0, discontinuity_flag = 1, n_frames = 5,
+        // seconds_flag = 1, seconds_value = 30, minutes_flag = 0, time_offset_length = 0
+        const TEST_BUF: [u8; 5] = [0x05, 0x02, 0x02, 0xde, 0x02];
+
+        let Metadata::Timecode(timecode) = parse_metadata(&TEST_BUF).unwrap() else {
+            panic!("expected timecode metadata");
+        };
+        assert!(!timecode.full_timestamp_flag);
+        assert!(timecode.discontinuity_flag);
+        assert_eq!(timecode.n_frames, 5);
+        assert_eq!(timecode.seconds_value, Some(30));
+        assert_eq!(timecode.minutes_value, None);
+        assert_eq!(timecode.hours_value, None);
+        assert_eq!(timecode.to_string(), "--:--:30:05");
+    }
+
+    #[test]
+    fn itu_t_t35_hdr10plus() {
+        // The start of an ST 2094-40 message: terminal_provider_code = 0x003c,
+        // terminal_provider_oriented_code = 1, application_identifier = 4, application_version = 1,
+        // num_windows = 1 and targeted_system_display_maximum_luminance = 400
+        const TEST_BUF: [u8; 13] = [
+            0x04, 0xb5, 0x00, 0x3c, 0x00, 0x01, 0x04, 0x01, 0x40, 0x00, 0x0c, 0x80, 0x80,
+        ];
+
+        let Metadata::ItuTT35(t35) = parse_metadata(&TEST_BUF).unwrap() else {
+            panic!("expected ITU-T T.35 metadata");
+        };
+        assert_eq!(t35.itu_t_t35_country_code, ITU_T_T35_COUNTRY_CODE_US);
+        assert_eq!(t35.itu_t_t35_country_code_extension_byte, None);
+        assert_eq!(
+            t35.itu_t_t35_payload_bytes,
+            &[0x00, 0x3c, 0x00, 0x01, 0x04, 0x01, 0x40, 0x00, 0x0c, 0x80]
+        );
+        assert!(t35.is_hdr10plus());
+    }
+
+    #[test]
+    fn itu_t_t35_other() {
+        // Dolby's terminal_provider_code = 0x003b
+        const DOLBY: [u8; 6] = [0x04, 0xb5, 0x00, 0x3b, 0x00, 0x80];
+        // A country code extension byte follows a country code of 0xff
+        const EXTENDED: [u8; 5] = [0x04, 0xff, 0x01, 0xaa, 0x80];
+
+        let Metadata::ItuTT35(t35) = parse_metadata(&DOLBY).unwrap() else {
+            panic!("expected ITU-T T.35 metadata");
+        };
+        assert_eq!(t35.itu_t_t35_payload_bytes, &[0x00, 0x3b, 0x00]);
+        assert!(!t35.is_hdr10plus());
+
+        let metadata = parse_metadata(&EXTENDED).unwrap();
+        assert_eq!(
+            metadata,
+            Metadata::ItuTT35(ItuTT35 {
+                itu_t_t35_country_code: 0xff,
+                itu_t_t35_country_code_extension_byte: Some(0x01),
+                itu_t_t35_payload_bytes: &[0xaa],
+            })
+        );
+    }
+
+    #[test]
+    fn unknown_metadata_type() {
+        // metadata_type = 0x1000, an unregistered value that takes two leb128 bytes
+        const TEST_BUF: [u8; 5] = [0x80, 0x20, 0xde, 0xad, 0x80];
+
+        let metadata = parse_metadata(&TEST_BUF).unwrap();
+        assert_eq!(
+            metadata,
+            Metadata::Unknown {
+                type_id: 0x1000,
+                data: &[0xde, 0xad, 0x80],
+            }
+        );
+    }
+
+    #[test]
+    fn scalability_two_spatial_layers() {
+        // scalability_mode_idc = SCALABILITY_SS, spatial_layers_cnt_minus_1 = 1 with dimensions
+        // and descriptions, 640x360 and 1280x720 both predicted from layer 0
+        const TEST_BUF: [u8; 14] = [
+            0x03, 0x0e, 0x70, 0x02, 0x80, 0x01, 0x68, 0x05, 0x00, 0x02, 0xd0, 0x00, 0x00, 0x80,
+        ];
+
+        let Metadata::Scalability(scalability) = parse_metadata(&TEST_BUF).unwrap() else {
+            panic!("expected scalability metadata");
+        };
+        assert_eq!(scalability.scalability_mode_idc, SCALABILITY_SS);
+
+        let structure = scalability.scalability_structure.unwrap();
+        assert_eq!(structure.spatial_layers_cnt_minus_1 + 1, 2);
+        assert!(structure.spatial_layer_dimensions_present_flag);
+        assert!(structure.spatial_layer_description_present_flag);
+        assert!(!structure.temporal_group_description_present_flag);
+        assert_eq!(structure.spatial_layer_max_width, vec![640, 1280]);
+        assert_eq!(structure.spatial_layer_max_height, vec![360, 720]);
+        assert_eq!(structure.spatial_layer_ref_id, vec![0, 0]);
+        assert!(structure.temporal_group.is_empty());
+    }
+
+    #[test]
+    fn scalability_temporal_group() {
+        // A single spatial layer with a two picture temporal group, each referencing one picture
+        const TEST_BUF: [u8; 9] = [0x03, 0x0e, 0x08, 0x02, 0x01, 0x02, 0x31, 0x01, 0x80];
+
+        let Metadata::Scalability(scalability) = parse_metadata(&TEST_BUF).unwrap() else {
+            panic!("expected scalability metadata");
+        };
+        let structure = scalability.scalability_structure.unwrap();
+        assert_eq!(structure.spatial_layers_cnt_minus_1, 0);
+        assert!(structure.spatial_layer_max_width.is_empty());
+        assert_eq!(
+            structure.temporal_group,
+            vec![
+                TemporalGroupEntry {
+                    temporal_group_temporal_id: 0,
+                    temporal_group_temporal_switching_up_point_flag: false,
+                    temporal_group_spatial_switching_up_point_flag: false,
+                    temporal_group_ref_pic_diff: vec![2],
+                },
+                TemporalGroupEntry {
+                    temporal_group_temporal_id: 1,
+                    temporal_group_temporal_switching_up_point_flag: true,
+                    temporal_group_spatial_switching_up_point_flag: false,
+                    temporal_group_ref_pic_diff: vec![1],
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn scalability_predefined_mode() {
+        // scalability_mode_idc = 5 (S2T1) has no scalability_structure()
+        const TEST_BUF: [u8; 3] = [0x03, 0x05, 0x80];
+
+        let metadata = parse_metadata(&TEST_BUF).unwrap();
+        assert_eq!(
+            metadata,
+            Metadata::Scalability(ScalabilityMetadata {
+                scalability_mode_idc: 5,
+                scalability_structure: None,
+            })
+        );
+    }
+}
@@ -0,0 +1,12 @@
+pub mod annexb;
+pub mod frame_header;
+pub mod level;
+pub mod metadata;
+pub mod obu;
+pub mod stats;
+pub mod streaming;
+pub mod temporal_unit;
+#[cfg(test)]
+pub(crate) mod test_fixtures;
+pub mod tile_group;
+pub mod tile_list;
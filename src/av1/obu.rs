@@ -0,0 +1,2624 @@
+use alloc::boxed::Box;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt;
+#[cfg(feature = "std")]
+use std::io::{self, Read, Seek, SeekFrom, Write};
+
+use crate::av1::frame_header::{
+    FrameHeaderObu, SequenceHeaderContext, MAX_TILE_COLS, MAX_TILE_ROWS,
+};
+use crate::av1::metadata::{parse_metadata_with_end, Metadata};
+use crate::av1::tile_group::{parse_tile_group, TileGroupObu};
+use crate::av1::tile_list::{parse_tile_list_with_end, TileListObu};
+use crate::bits::{BitstreamReader, ByteRead};
+use crate::Error;
+
+pub const OBU_SEQUENCE_HEADER: u8 = 1;
+pub const OBU_TEMPORAL_DELIMITER: u8 = 2;
+pub const OBU_FRAME_HEADER: u8 = 3;
+pub const OBU_TILE_GROUP: u8 = 4;
+pub const OBU_METADATA: u8 = 5;
+pub const OBU_FRAME: u8 = 6;
+pub const OBU_REDUNDANT_FRAME_HEADER: u8 = 7;
+pub const OBU_TILE_LIST: u8 = 8;
+pub const OBU_PADDING: u8 = 15;
+
+pub const CP_BT_709: u8 = 1;
+pub const CP_UNSPECIFIED: u8 = 2;
+pub const TC_UNSPECIFIED: u8 = 2;
+pub const TC_SRGB: u8 = 13;
+pub const MC_IDENTITY: u8 = 0;
+pub const MC_UNSPECIFIED: u8 = 2;
+pub const CSP_UNKNOWN: u8 = 0;
+
+pub const SELECT_SCREEN_CONTENT_TOOLS: u8 = 2;
+pub const SELECT_INTEGER_MV: u8 = 2;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum ObuType {
+    SequenceHeader,
+    TemporalDelimiter,
+    FrameHeader,
+    TileGroup,
+    Metadata,
+    Frame,
+    RedundantFrameHeader,
+    TileList,
+    Padding,
+}
+
+impl TryFrom<u8> for ObuType {
+    type Error = Error;
+
+    fn try_from(obu_type: u8) -> Result<Self, Self::Error> {
+        match obu_type {
+            OBU_SEQUENCE_HEADER => Ok(Self::SequenceHeader),
+            OBU_TEMPORAL_DELIMITER => Ok(Self::TemporalDelimiter),
+            OBU_FRAME_HEADER => Ok(Self::FrameHeader),
+            OBU_TILE_GROUP => Ok(Self::TileGroup),
+            OBU_METADATA => Ok(Self::Metadata),
+            OBU_FRAME => Ok(Self::Frame),
+            OBU_REDUNDANT_FRAME_HEADER => Ok(Self::RedundantFrameHeader),
+            OBU_TILE_LIST => Ok(Self::TileList),
+            OBU_PADDING => Ok(Self::Padding),
+            _ => Err(Error::Invalid("reserved obu_type")),
+        }
+    }
+}
+
+impl From<ObuType> for u8 {
+    fn from(obu_type: ObuType) -> Self {
+        match obu_type {
+            ObuType::SequenceHeader => OBU_SEQUENCE_HEADER,
+            ObuType::TemporalDelimiter => OBU_TEMPORAL_DELIMITER,
+            ObuType::FrameHeader => OBU_FRAME_HEADER,
+            ObuType::TileGroup => OBU_TILE_GROUP,
+            ObuType::Metadata => OBU_METADATA,
+            ObuType::Frame => OBU_FRAME,
+            ObuType::RedundantFrameHeader => OBU_REDUNDANT_FRAME_HEADER,
+            ObuType::TileList => OBU_TILE_LIST,
+            ObuType::Padding => OBU_PADDING,
+        }
+    }
+}
+
+impl fmt::Display for ObuType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::SequenceHeader => "OBU_SEQUENCE_HEADER",
+            Self::TemporalDelimiter => "OBU_TEMPORAL_DELIMITER",
+            Self::FrameHeader => "OBU_FRAME_HEADER",
+            Self::TileGroup => "OBU_TILE_GROUP",
+            Self::Metadata => "OBU_METADATA",
+            Self::Frame => "OBU_FRAME",
+            Self::RedundantFrameHeader => "OBU_REDUNDANT_FRAME_HEADER",
+            Self::TileList => "OBU_TILE_LIST",
+            Self::Padding => "OBU_PADDING",
+        })
+    }
+}
+
+/// obu_extension_header() - 5.3.3
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct ObuExtensionHeader {
+    pub temporal_id: u8,
+    pub spatial_id: u8,
+    pub extension_header_reserved_3bits: u8,
+}
+
+/// obu_header() - 5.3.2
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct ObuHeader {
+    pub obu_type: ObuType,
+    pub obu_extension_flag: bool,
+    pub obu_has_size_field: bool,
+    pub obu_reserved_1bit: bool,
+    pub extension: Option<ObuExtensionHeader>,
+}
+
+impl fmt::Display for ObuHeader {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ext=", self.obu_type)?;
+        match self.extension {
+            Some(extension) => write!(
+                f,
+                "tid{}/sid{}",
+                extension.temporal_id, extension.spatial_id
+            ),
+            None => f.write_str("none"),
+        }
+    }
+}
+
+/// seq_profile - 6.4.1
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum Av1Profile {
+    #[default]
+    Main,
+    High,
+    Professional,
+}
+
+impl Av1Profile {
+    pub fn as_u8(&self) -> u8 {
+        match self {
+            Self::Main => 0,
+            Self::High => 1,
+            Self::Professional => 2,
+        }
+    }
+}
+
+impl TryFrom<u8> for Av1Profile {
+    type Error = Error;
+
+    fn try_from(seq_profile: u8) -> Result<Self, Self::Error> {
+        match seq_profile {
+            0 => Ok(Self::Main),
+            1 => Ok(Self::High),
+            2 => Ok(Self::Professional),
+            _ => Err(Error::Invalid("reserved seq_profile")),
+        }
+    }
+}
+
+/// seq_tier - 6.4.1
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum Tier {
+    #[default]
+    Main,
+    High,
+}
+
+impl From<u8> for Tier {
+    fn from(seq_tier: u8) -> Self {
+        match seq_tier {
+            0 => Self::Main,
+            _ => Self::High,
+        }
+    }
+}
+
+/// The level a seq_level_idx stands for, from the table in A.3
+pub fn level_name(seq_level_idx: u8) -> &'static str {
+    const LEVELS: [&str; 24] = [
+        "2.0", "2.1", "reserved", "reserved", "3.0", "3.1", "reserved", "reserved", "4.0", "4.1",
+        "reserved", "reserved", "5.0", "5.1", "5.2", "5.3", "6.0", "6.1", "6.2", "6.3", "7.0",
+        "7.1", "7.2", "7.3",
+    ];
+    match seq_level_idx {
+        31 => "max",
+        _ => LEVELS
+            .get(seq_level_idx as usize)
+            .copied()
+            .unwrap_or("reserved"),
+    }
+}
+
+/// timing_info() - 5.5.3
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct SequenceHeaderTimingInfo {
+    pub num_units_in_display_tick: u32,
+    pub time_scale: u32,
+    pub equal_picture_interval: bool,
+    pub num_ticks_per_picture_minus_1: u32,
+}
+
+/// decoder_model_info() - 5.5.4
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct SequenceHeaderDecoderModelInfo {
+    pub buffer_delay_length_minus_1: u8,
+    pub num_units_in_decoding_tick: u32,
+    pub buffer_removal_time_length_minus_1: u8,
+    pub frame_presentation_time_length_minus_1: u8,
+}
+
+/// operating_parameters_info() - 5.5.5
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct OperatingParametersInfo {
+    pub decoder_buffer_delay: u32,
+    pub encoder_buffer_delay: u32,
+    pub low_delay_mode_flag: bool,
+}
+
+/// color_config() - 5.5.2
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct ColorConfig {
+    pub high_bitdepth: bool,
+    pub twelve_bit: bool,
+    pub bit_depth: u8,
+    pub mono_chrome: bool,
+    pub color_description_present_flag: bool,
+    pub color_primaries: u8,
+    pub transfer_characteristics: u8,
+    pub matrix_coefficients: u8,
+    pub color_range: bool,
+    pub subsampling_x: bool,
+    pub subsampling_y: bool,
+    pub chroma_sample_position: u8,
+    pub separate_uv_delta_q: bool,
+}
+
+/// sequence_header_obu() - 5.5.1
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct SequenceHeaderObu {
+    pub seq_profile: Av1Profile,
+    pub still_picture: bool,
+    pub reduced_still_picture_header: bool,
+
+    pub timing_info: Option<SequenceHeaderTimingInfo>,
+    pub decoder_model_info: Option<SequenceHeaderDecoderModelInfo>,
+    pub initial_display_delay_present_flag: bool,
+
+    pub operating_points_cnt_minus_1: u8,
+    pub operating_point_idc: Vec<u16>,
+    pub seq_level_idx: Vec<u8>,
+    pub seq_tier: Vec<u8>,
+    pub decoder_model_present_for_this_op: Vec<bool>,
+    pub operating_parameters_info: Vec<Option<OperatingParametersInfo>>,
+    pub initial_display_delay_present_for_this_op: Vec<bool>,
+    pub initial_display_delay_minus_1: Vec<u8>,
+
+    pub frame_width_bits_minus_1: u8,
+    pub frame_height_bits_minus_1: u8,
+    pub max_frame_width_minus_1: u32,
+    pub max_frame_height_minus_1: u32,
+
+    pub frame_id_numbers_present_flag: bool,
+    pub delta_frame_id_length_minus_2: u8,
+    pub additional_frame_id_length_minus_1: u8,
+
+    pub use_128x128_superblock: bool,
+    pub enable_filter_intra: bool,
+    pub enable_intra_edge_filter: bool,
+    pub enable_interintra_compound: bool,
+    pub enable_masked_compound: bool,
+    pub enable_warped_motion: bool,
+    pub enable_dual_filter: bool,
+    pub enable_order_hint: bool,
+    pub enable_jnt_comp: bool,
+    pub enable_ref_frame_mvs: bool,
+    pub seq_choose_screen_content_tools: bool,
+    pub seq_force_screen_content_tools: u8,
+    pub seq_choose_integer_mv: bool,
+    pub seq_force_integer_mv: u8,
+    pub order_hint_bits_minus_1: u8,
+
+    pub enable_superres: bool,
+    pub enable_cdef: bool,
+    pub enable_restoration: bool,
+    pub color_config: ColorConfig,
+    pub film_grain_params_present: bool,
+}
+
+impl SequenceHeaderObu {
+    /// The level of an operating point, as a name like "5.1", or None past the last operating
+    /// point
+    pub fn level_name(&self, op: usize) -> Option<&'static str> {
+        self.seq_level_idx.get(op).copied().map(level_name)
+    }
+
+    /// The tier of an operating point, or None past the last operating point
+    pub fn tier(&self, op: usize) -> Option<Tier> {
+        self.seq_tier.get(op).copied().map(Tier::from)
+    }
+
+    /// BitDepth - 8, 10 or 12
+    pub fn bit_depth(&self) -> u8 {
+        self.color_config.bit_depth
+    }
+
+    pub fn is_monochrome(&self) -> bool {
+        self.color_config.mono_chrome
+    }
+
+    /// OrderHintBits - 5.5.1, 0 without enable_order_hint
+    pub fn order_hint_bits(&self) -> u8 {
+        if self.enable_order_hint {
+            self.order_hint_bits_minus_1 + 1
+        } else {
+            0
+        }
+    }
+
+    /// (subsampling_x, subsampling_y), which are both 1 for monochrome streams
+    pub fn chroma_subsampling(&self) -> (u8, u8) {
+        (
+            self.color_config.subsampling_x as u8,
+            self.color_config.subsampling_y as u8,
+        )
+    }
+}
+
+impl fmt::Display for SequenceHeaderObu {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let subsampling = match (self.is_monochrome(), self.chroma_subsampling()) {
+            (true, _) => "4:0:0",
+            (false, (1, 1)) => "4:2:0",
+            (false, (1, 0)) => "4:2:2",
+            (false, _) => "4:4:4",
+        };
+        write!(
+            f,
+            "profile={:?} size={}x{} bit_depth={} subsampling={} tools=",
+            self.seq_profile,
+            self.max_frame_width_minus_1 + 1,
+            self.max_frame_height_minus_1 + 1,
+            self.bit_depth(),
+            subsampling,
+        )?;
+
+        let tools = [
+            (self.use_128x128_superblock, "128x128_superblock"),
+            (self.enable_filter_intra, "filter_intra"),
+            (self.enable_intra_edge_filter, "intra_edge_filter"),
+            (self.enable_interintra_compound, "interintra_compound"),
+            (self.enable_masked_compound, "masked_compound"),
+            (self.enable_warped_motion, "warped_motion"),
+            (self.enable_dual_filter, "dual_filter"),
+            (self.enable_order_hint, "order_hint"),
+            (self.enable_jnt_comp, "jnt_comp"),
+            (self.enable_ref_frame_mvs, "ref_frame_mvs"),
+            (self.enable_superres, "superres"),
+            (self.enable_cdef, "cdef"),
+            (self.enable_restoration, "restoration"),
+            (self.film_grain_params_present, "film_grain"),
+        ];
+        let mut enabled = tools.iter().filter(|(enabled, _)| *enabled);
+        match enabled.next() {
+            Some((_, name)) => f.write_str(name)?,
+            None => return f.write_str("none"),
+        }
+        for (_, name) in enabled {
+            write!(f, ",{}", name)?;
+        }
+        Ok(())
+    }
+}
+
+/// Options controlling how strictly the bitstream's constraints are checked, and limits that
+/// guard against untrusted input
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ParseOptions {
+    /// Reject bitstreams with obu_forbidden_bit or the reserved bits of the OBU header set
+    pub strict_reserved_bits: bool,
+    /// Reject sequence header and frame header OBUs whose payload doesn't end with
+    /// trailing_bits() right after the parsed syntax, as happens with a wrong obu_size
+    pub strict_trailing_bits: bool,
+    /// The largest OBU payload accepted, in bytes
+    pub max_obu_size: usize,
+    /// The largest number of tiles accepted in a frame
+    pub max_tiles: usize,
+}
+
+impl Default for ParseOptions {
+    fn default() -> Self {
+        Self {
+            strict_reserved_bits: true,
+            strict_trailing_bits: true,
+            max_obu_size: 64 * 1024 * 1024,
+            max_tiles: (MAX_TILE_ROWS * MAX_TILE_COLS) as usize,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Obu<'a> {
+    pub header: ObuHeader,
+    buf: &'a [u8],
+    payload: &'a [u8],
+}
+
+impl<'a> Obu<'a> {
+    /// Parses the OBU at the start of `buf`
+    pub fn from_buf(buf: &'a [u8]) -> Result<Self, Error> {
+        Self::from_buf_with_options(buf, &ParseOptions::default())
+    }
+
+    pub fn from_buf_with_options(buf: &'a [u8], options: &ParseOptions) -> Result<Self, Error> {
+        let mut reader = BitstreamReader::from_slice(buf);
+        let header = parse_header(&mut reader, options)?;
+
+        let obu_size = if header.obu_has_size_field {
+            Some(reader.leb128()?)
+        } else {
+            None
+        };
+
+        let payload_start = reader.byte_position() as usize;
+        let payload_end = match obu_size {
+            // Without a size field the OBU extends to the end of the buffer
+            None => buf.len(),
+            Some(obu_size) => usize::try_from(obu_size)
+                .ok()
+                .and_then(|obu_size| payload_start.checked_add(obu_size))
+                .filter(|end| *end <= buf.len())
+                .ok_or(Error::Invalid("obu_size exceeds the buffer"))?,
+        };
+        if payload_end - payload_start > options.max_obu_size {
+            return Err(Error::Invalid("obu_size exceeds max_obu_size"));
+        }
+
+        Ok(Self {
+            header,
+            buf: &buf[..payload_end],
+            payload: &buf[payload_start..payload_end],
+        })
+    }
+
+    /// The complete OBU, including the header and size field
+    pub fn buf(&self) -> &'a [u8] {
+        self.buf
+    }
+
+    /// The complete OBU exactly as it was in the bitstream, for passing OBUs on to a muxer
+    /// without writing them again - the same bytes as `buf()`
+    pub fn raw_bytes(&self) -> &'a [u8] {
+        self.buf
+    }
+
+    /// The OBU data following the header and size field
+    pub fn payload(&self) -> &'a [u8] {
+        self.payload
+    }
+
+    pub fn obu_type(&self) -> ObuType {
+        self.header.obu_type
+    }
+
+    pub fn extension_header(&self) -> Option<ObuExtensionHeader> {
+        self.header.extension
+    }
+
+    /// temporal_id, 0 without an extension header
+    pub fn temporal_id(&self) -> u8 {
+        self.header
+            .extension
+            .map_or(0, |extension| extension.temporal_id)
+    }
+
+    /// spatial_id, 0 without an extension header
+    pub fn spatial_id(&self) -> u8 {
+        self.header
+            .extension
+            .map_or(0, |extension| extension.spatial_id)
+    }
+
+    /// A 64-bit FNV-1a hash of the payload, for finding identical OBUs across streams. The hash
+    /// is the same on every platform.
+    pub fn payload_hash(&self) -> u64 {
+        const FNV_OFFSET_BASIS: u64 = 0xcbf29ce484222325;
+        const FNV_PRIME: u64 = 0x100000001b3;
+
+        self.payload.iter().fold(FNV_OFFSET_BASIS, |hash, &byte| {
+            (hash ^ byte as u64).wrapping_mul(FNV_PRIME)
+        })
+    }
+
+    /// Writes the OBU in the low overhead bitstream format, with obu_has_size_field set and
+    /// obu_size encoded in as few bytes as possible
+    #[cfg(feature = "std")]
+    pub fn write_to<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        out.write_all(&self.header_bytes(true))?;
+        out.write_all(self.payload)
+    }
+
+    /// The OBU's header, followed by obu_size in as few bytes as possible when `size_field` is
+    /// set. obu_has_size_field is set to match.
+    pub(crate) fn header_bytes(&self, size_field: bool) -> Vec<u8> {
+        let header = &self.header;
+        let mut header_bytes = vec![
+            u8::from(header.obu_type) << 3
+                | (header.extension.is_some() as u8) << 2
+                | (size_field as u8) << 1
+                | header.obu_reserved_1bit as u8,
+        ];
+        if let Some(extension) = header.extension {
+            header_bytes.push(
+                extension.temporal_id << 5
+                    | extension.spatial_id << 3
+                    | extension.extension_header_reserved_3bits,
+            );
+        }
+        if size_field {
+            push_leb128(&mut header_bytes, self.payload.len() as u64);
+        }
+        header_bytes
+    }
+}
+
+impl fmt::Display for Obu<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} size={}", self.header, self.payload.len())
+    }
+}
+
+/// An `Obu` that owns its bytes, for keeping it after the buffer it was parsed from is gone
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OwnedObu {
+    pub header: ObuHeader,
+    buf: Vec<u8>,
+    /// Offset of the payload in `buf`
+    payload_start: usize,
+}
+
+impl OwnedObu {
+    /// The complete OBU, including the header and size field
+    pub fn buf(&self) -> &[u8] {
+        &self.buf
+    }
+
+    /// The OBU data following the header and size field
+    pub fn payload(&self) -> &[u8] {
+        &self.buf[self.payload_start..]
+    }
+
+    pub fn obu_type(&self) -> ObuType {
+        self.header.obu_type
+    }
+
+    /// Borrows the OBU, to pass to the functions that take an `Obu`
+    pub fn as_obu(&self) -> Obu<'_> {
+        Obu {
+            header: self.header,
+            buf: &self.buf,
+            payload: self.payload(),
+        }
+    }
+}
+
+impl From<Obu<'_>> for OwnedObu {
+    fn from(obu: Obu<'_>) -> Self {
+        Self {
+            header: obu.header,
+            buf: obu.buf.to_vec(),
+            payload_start: obu.buf.len() - obu.payload.len(),
+        }
+    }
+}
+
+pub struct ObuIterator<'a> {
+    buf: &'a [u8],
+    options: ParseOptions,
+}
+
+impl<'a> Iterator for ObuIterator<'a> {
+    type Item = Result<Obu<'a>, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.buf.is_empty() {
+            return None;
+        }
+
+        match Obu::from_buf_with_options(self.buf, &self.options).and_then(check_trailing_obu) {
+            Ok(obu) => {
+                self.buf = &self.buf[obu.buf.len()..];
+                Some(Ok(obu))
+            }
+            Err(err) => {
+                // There's no way to resynchronize after a malformed OBU
+                self.buf = &[];
+                Some(Err(err))
+            }
+        }
+    }
+}
+
+/// An OBU without a size field takes up the rest of the buffer, so it can only be the last OBU.
+/// Data following one is detected here for a temporal delimiter, whose payload is always empty.
+/// Sequence headers, frame headers, metadata and tile lists need their syntax parsed to find where
+/// they end, so `ObuParser` rejects data following those.
+fn check_trailing_obu(obu: Obu) -> Result<Obu, Error> {
+    if !obu.header.obu_has_size_field
+        && obu.header.obu_type == ObuType::TemporalDelimiter
+        && !obu.payload.is_empty()
+    {
+        return Err(Error::Invalid(
+            "obu without a size field followed by more data",
+        ));
+    }
+    Ok(obu)
+}
+
+/// Iterates over the OBUs in a buffer of low overhead bitstream format OBUs
+///
+/// An OBU without obu_size extends to the end of the buffer, which is only valid for the last
+/// OBU. More data after one is an error for a temporal delimiter, and for the types whose syntax
+/// says where they end, an error from `ObuParser`.
+pub fn iter_obus(buf: &[u8]) -> ObuIterator<'_> {
+    iter_obus_with_options(buf, ParseOptions::default())
+}
+
+pub fn iter_obus_with_options(buf: &[u8], options: ParseOptions) -> ObuIterator<'_> {
+    ObuIterator { buf, options }
+}
+
+/// Parses the first sequence header of a low overhead bitstream, without looking at anything
+/// after it
+pub fn probe_sequence_header(buf: &[u8]) -> Result<SequenceHeaderObu, Error> {
+    for obu in iter_obus(buf) {
+        let obu = obu?;
+        if obu.header.obu_type == ObuType::SequenceHeader {
+            return parse_sequence_header(&mut BitstreamReader::from_slice(obu.payload()));
+        }
+    }
+    Err(Error::Invalid("no sequence header"))
+}
+
+/// Where an OBU is in a file, as found by `scan_offsets`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct ObuIndexEntry {
+    /// Byte offset of the OBU header
+    pub offset: u64,
+    /// Byte offset of the payload, following the header and size field
+    pub payload_offset: u64,
+    pub obu_type: ObuType,
+    /// obu_size, or the rest of the file for an OBU without a size field
+    pub obu_size: u64,
+    pub temporal_id: u8,
+    pub spatial_id: u8,
+}
+
+/// Finds every OBU of a low overhead bitstream, reading only the OBU headers and size fields
+/// and seeking past the payloads
+///
+/// Offsets are relative to the start of `r`'s stream, and scanning begins at its current
+/// position. An OBU without a size field takes up the rest of the stream.
+#[cfg(feature = "std")]
+pub fn scan_offsets<R: Read + Seek>(r: &mut R) -> Result<Vec<ObuIndexEntry>, Error> {
+    let options = ParseOptions::default();
+    let mut offset = r.stream_position()?;
+    let len = r.seek(SeekFrom::End(0))?;
+    r.seek(SeekFrom::Start(offset))?;
+
+    let mut entries = Vec::new();
+    // The header, extension header and obu_size are at most 10 bytes
+    let mut header_bytes = [0u8; 10];
+    while offset < len {
+        r.read_exact(&mut header_bytes[..1])?;
+        let mut header_len = 1;
+        if header_bytes[0] & 0x04 != 0 {
+            r.read_exact(&mut header_bytes[1..2])?;
+            header_len = 2;
+        }
+        let mut reader = BitstreamReader::from_slice(&header_bytes[..header_len]);
+        let header = parse_header(&mut reader, &options)?;
+
+        let obu_size = if header.obu_has_size_field {
+            let size_start = header_len;
+            loop {
+                if header_len == size_start + 8 {
+                    return Err(Error::Invalid("unterminated leb128"));
+                }
+                r.read_exact(&mut header_bytes[header_len..header_len + 1])?;
+                header_len += 1;
+                if header_bytes[header_len - 1] & 0x80 == 0 {
+                    break;
+                }
+            }
+            BitstreamReader::from_slice(&header_bytes[size_start..header_len]).leb128()?
+        } else {
+            len - offset - header_len as u64
+        };
+
+        let payload_offset = offset + header_len as u64;
+        let end = payload_offset
+            .checked_add(obu_size)
+            .filter(|end| *end <= len)
+            .ok_or(Error::Invalid("obu_size exceeds the buffer"))?;
+        entries.push(ObuIndexEntry {
+            offset,
+            payload_offset,
+            obu_type: header.obu_type,
+            obu_size,
+            temporal_id: header
+                .extension
+                .map_or(0, |extension| extension.temporal_id),
+            spatial_id: header.extension.map_or(0, |extension| extension.spatial_id),
+        });
+
+        r.seek(SeekFrom::Start(end))?;
+        offset = end;
+    }
+    Ok(entries)
+}
+
+/// Appends leb128() - 4.10.5 encoded in as few bytes as possible
+pub(crate) fn push_leb128(out: &mut Vec<u8>, mut value: u64) {
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+        if value == 0 {
+            out.push(byte);
+            return;
+        }
+        out.push(byte | 0x80);
+    }
+}
+
+/// Whether an OBU with the given temporal_id and spatial_id is in the operating point selected
+/// by `op_idc` - 6.2.1
+pub fn operating_point_filter(op_idc: u16, temporal_id: u32, spatial_id: u32) -> bool {
+    if op_idc == 0 {
+        return true;
+    }
+
+    let in_temporal_layer = temporal_id < 8 && (op_idc >> temporal_id) & 1 != 0;
+    let in_spatial_layer = spatial_id < 4 && (op_idc >> (spatial_id + 8)) & 1 != 0;
+    in_temporal_layer && in_spatial_layer
+}
+
+/// Iterates over the OBUs in one operating point, dropping those outside of it
+pub struct OperatingPointIterator<'a> {
+    obus: ObuIterator<'a>,
+    op_idx: usize,
+    /// OperatingPointIdc from the most recent sequence header
+    op_idc: u16,
+}
+
+impl<'a> OperatingPointIterator<'a> {
+    fn in_operating_point(&mut self, obu: &Obu) -> Result<bool, Error> {
+        match (obu.header.obu_type, obu.header.extension) {
+            (ObuType::SequenceHeader, _) => {
+                // OperatingPointIdc is chosen from the operating points of each sequence header
+                let mut reader = BitstreamReader::from_slice(obu.payload());
+                let seq = parse_sequence_header(&mut reader)?;
+                self.op_idc = *seq
+                    .operating_point_idc
+                    .get(self.op_idx)
+                    .ok_or(Error::Invalid("operating point not in the sequence header"))?;
+                Ok(true)
+            }
+            (ObuType::TemporalDelimiter, _) | (_, None) => Ok(true),
+            (_, Some(extension)) => Ok(operating_point_filter(
+                self.op_idc,
+                extension.temporal_id as u32,
+                extension.spatial_id as u32,
+            )),
+        }
+    }
+}
+
+impl<'a> Iterator for OperatingPointIterator<'a> {
+    type Item = Result<Obu<'a>, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let obu = match self.obus.next()? {
+                Ok(obu) => obu,
+                Err(err) => return Some(Err(err)),
+            };
+
+            match self.in_operating_point(&obu) {
+                Ok(true) => return Some(Ok(obu)),
+                Ok(false) => {}
+                Err(err) => {
+                    self.obus = iter_obus(&[]);
+                    return Some(Err(err));
+                }
+            }
+        }
+    }
+}
+
+/// Iterates over the OBUs of a low overhead bitstream format buffer that are in the operating
+/// point `op_idx` of the sequence header
+pub fn iter_obus_for_operating_point(buf: &[u8], op_idx: usize) -> OperatingPointIterator<'_> {
+    OperatingPointIterator {
+        obus: iter_obus(buf),
+        op_idx,
+        op_idc: 0,
+    }
+}
+
+/// The decoded contents of an OBU
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum ParsedObu<'a> {
+    TemporalDelimiter,
+    SequenceHeader(Box<SequenceHeaderObu>),
+    FrameHeader(Box<FrameHeaderObu>),
+    TileGroup(TileGroupObu),
+    Metadata(Metadata<'a>),
+    Frame(Box<FrameHeaderObu>, TileGroupObu),
+    /// A frame header repeated for error resilience, parsed on its own if the frame header it
+    /// repeats was lost
+    RedundantFrameHeader(Box<FrameHeaderObu>),
+    TileList(TileListObu<'a>),
+    /// padding_obu() - 5.7, filler whose bytes are skipped
+    Padding {
+        len: usize,
+    },
+}
+
+/// Dispatches OBUs to the parser for their type, keeping the sequence header and reference
+/// frame state that later OBUs are parsed against
+#[derive(Debug, Default)]
+pub struct ObuParser {
+    options: ParseOptions,
+    /// The most recent sequence header, and the reference frames since the first one
+    context: Option<SequenceHeaderContext>,
+    /// The header of the frame whose tile groups are being parsed, SeenFrameHeader in the spec
+    frame_header: Option<FrameHeaderObu>,
+    /// The bytes frame_header was parsed from and its length in bits, for checking copies of it
+    frame_header_bits: (Vec<u8>, u64),
+}
+
+impl ObuParser {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_options(options: ParseOptions) -> Self {
+        Self {
+            options,
+            ..Default::default()
+        }
+    }
+
+    pub fn parse<'a>(&mut self, obu: &Obu<'a>) -> Result<ParsedObu<'a>, Error> {
+        match obu.header.obu_type {
+            ObuType::TemporalDelimiter => {
+                // temporal_delimiter_obu() - 5.6
+                if !obu.payload().is_empty() {
+                    return Err(Error::Invalid(
+                        "temporal delimiter with a non-empty payload",
+                    ));
+                }
+                Ok(ParsedObu::TemporalDelimiter)
+            }
+            ObuType::SequenceHeader => {
+                let mut reader = BitstreamReader::from_slice(obu.payload());
+                let seq = parse_sequence_header(&mut reader)?;
+                self.check_trailing_bits(obu, reader.position())?;
+                match &mut self.context {
+                    Some(context) => context.sequence_header = seq.clone(),
+                    None => self.context = Some(SequenceHeaderContext::new(seq.clone())),
+                }
+                Ok(ParsedObu::SequenceHeader(Box::new(seq)))
+            }
+            ObuType::FrameHeader | ObuType::RedundantFrameHeader => {
+                // Repeated and redundant frame headers are a copy of the first - 5.9.1
+                let frame_header = match &self.frame_header {
+                    Some(frame_header) => {
+                        self.check_frame_header_copy(obu)?;
+                        frame_header.clone()
+                    }
+                    None => {
+                        let mut reader = BitstreamReader::from_slice(obu.payload());
+                        let frame_header = self.parse_frame_header(&mut reader, obu)?;
+                        self.check_trailing_bits(obu, reader.position())?;
+                        frame_header
+                    }
+                };
+                if obu.header.obu_type == ObuType::RedundantFrameHeader {
+                    Ok(ParsedObu::RedundantFrameHeader(Box::new(frame_header)))
+                } else {
+                    Ok(ParsedObu::FrameHeader(Box::new(frame_header)))
+                }
+            }
+            ObuType::TileGroup => {
+                let frame_header = self
+                    .frame_header
+                    .as_ref()
+                    .ok_or(Error::Invalid("tile group without a frame header"))?;
+                let tile_group = parse_tile_group(obu.payload(), &frame_header.tile_info)?;
+                if tile_group.is_last(&frame_header.tile_info) {
+                    self.frame_header = None;
+                }
+                Ok(ParsedObu::TileGroup(tile_group))
+            }
+            ObuType::Frame => {
+                // frame_obu() - 5.10
+                self.frame_header = None;
+                let mut reader = BitstreamReader::from_slice(obu.payload());
+                let frame_header = self.parse_frame_header(&mut reader, obu)?;
+                if frame_header.show_existing_frame {
+                    return Err(Error::Invalid("frame obu with show_existing_frame set"));
+                }
+                reader.byte_alignment()?;
+
+                let header_len = reader.byte_position() as usize;
+                let tile_group =
+                    parse_tile_group(&obu.payload()[header_len..], &frame_header.tile_info)?;
+                if tile_group.is_last(&frame_header.tile_info) {
+                    self.frame_header = None;
+                }
+                Ok(ParsedObu::Frame(Box::new(frame_header), tile_group))
+            }
+            ObuType::Metadata => {
+                let (metadata, end) = parse_metadata_with_end(obu.payload())?;
+                if let Some(end) = end {
+                    check_unsized_end(obu, ends_with_trailing_bits(obu.payload(), end))?;
+                }
+                Ok(ParsedObu::Metadata(metadata))
+            }
+            ObuType::TileList => {
+                let (tile_list, end) = parse_tile_list_with_end(obu.payload(), &self.options)?;
+                // tile_list_obu() doesn't end with trailing bits
+                check_unsized_end(obu, obu.payload()[end..].iter().all(|&byte| byte == 0))?;
+                Ok(ParsedObu::TileList(tile_list))
+            }
+            ObuType::Padding => Ok(ParsedObu::Padding {
+                len: obu.payload().len(),
+            }),
+        }
+    }
+
+    /// The most recently parsed sequence header
+    pub fn sequence_header(&self) -> Option<&SequenceHeaderObu> {
+        self.context
+            .as_ref()
+            .map(|context| &context.sequence_header)
+    }
+
+    /// Checks that the payload ends with trailing_bits() - 5.3.4 at `position`, the number of
+    /// bits parsed. Zero bytes after the trailing bits are allowed.
+    ///
+    /// Without a size field this is checked even without `strict_trailing_bits`, as anything
+    /// else is a following OBU taken in with this one.
+    fn check_trailing_bits(&self, obu: &Obu, position: u64) -> Result<(), Error> {
+        let ends = ends_with_trailing_bits(obu.payload(), position);
+        if !obu.header.obu_has_size_field {
+            return check_unsized_end(obu, ends);
+        }
+        if self.options.strict_trailing_bits && !ends {
+            return Err(Error::Invalid("obu_size doesn't match the parsed payload"));
+        }
+        Ok(())
+    }
+
+    /// frame_header_copy() - 5.9.1, the same bits as the frame header followed by
+    /// trailing_bits()
+    fn check_frame_header_copy(&self, obu: &Obu) -> Result<(), Error> {
+        let (bits, len) = &self.frame_header_bits;
+        let whole_bytes = (len / 8) as usize;
+        let mask = !(0xff >> (len % 8)) as u8;
+
+        let payload = obu.payload();
+        let same_bytes = payload.get(..whole_bytes) == Some(&bits[..whole_bytes]);
+        let same_bits = mask == 0
+            || payload.get(whole_bytes).map(|byte| byte & mask) == Some(bits[whole_bytes] & mask);
+        if !same_bytes || !same_bits {
+            return Err(Error::Invalid(
+                "frame header copy doesn't match the frame header",
+            ));
+        }
+        self.check_trailing_bits(obu, *len)
+    }
+
+    fn parse_frame_header<T: ByteRead>(
+        &mut self,
+        reader: &mut BitstreamReader<T>,
+        obu: &Obu,
+    ) -> Result<FrameHeaderObu, Error> {
+        let context = self
+            .context
+            .as_mut()
+            .ok_or(Error::Invalid("frame header before a sequence header"))?;
+
+        let frame_header = context.parse_frame_header(reader, obu.header.extension)?;
+        let tile_info = &frame_header.tile_info;
+        if (tile_info.tile_cols * tile_info.tile_rows) as usize > self.options.max_tiles {
+            return Err(Error::Invalid("tile count exceeds max_tiles"));
+        }
+        if !frame_header.show_existing_frame {
+            self.frame_header = Some(frame_header.clone());
+            let len = reader.position();
+            let bits = obu.payload()[..len.div_ceil(8) as usize].to_vec();
+            self.frame_header_bits = (bits, len);
+        }
+        Ok(frame_header)
+    }
+}
+
+/// Whether `payload` holds trailing_bits() - 5.3.4 at bit `position`, followed by nothing but
+/// zero bytes
+fn ends_with_trailing_bits(payload: &[u8], position: u64) -> bool {
+    let trailing = usize::try_from(position / 8)
+        .ok()
+        .and_then(|start| payload.get(start..))
+        .and_then(|trailing| trailing.split_first());
+    matches!(
+        trailing,
+        Some((&first, rest))
+            if first & (0xff >> (position % 8)) == 0x80 >> (position % 8)
+                && rest.iter().all(|&byte| byte == 0)
+    )
+}
+
+/// An OBU without a size field takes up the rest of the buffer, so when its syntax ends before
+/// the payload does (`ends` is false) it was followed by more data
+fn check_unsized_end(obu: &Obu, ends: bool) -> Result<(), Error> {
+    if !obu.header.obu_has_size_field && !ends {
+        return Err(Error::Invalid(
+            "obu without a size field followed by more data",
+        ));
+    }
+    Ok(())
+}
+
+/// obu_header() - 5.3.2
+pub fn parse_header<T: ByteRead>(
+    reader: &mut BitstreamReader<T>,
+    options: &ParseOptions,
+) -> Result<ObuHeader, Error> {
+    if reader.f1()? && options.strict_reserved_bits {
+        return Err(Error::Invalid("obu_forbidden_bit is set"));
+    }
+
+    let obu_type = ObuType::try_from(reader.f(4)? as u8)?;
+    let obu_extension_flag = reader.f1()?;
+    let obu_has_size_field = reader.f1()?;
+    let obu_reserved_1bit = reader.f1()?;
+    if obu_reserved_1bit && options.strict_reserved_bits {
+        return Err(Error::Invalid("obu_reserved_1bit is set"));
+    }
+
+    let extension = if obu_extension_flag {
+        Some(parse_extension_header(reader, options)?)
+    } else {
+        None
+    };
+
+    Ok(ObuHeader {
+        obu_type,
+        obu_extension_flag,
+        obu_has_size_field,
+        obu_reserved_1bit,
+        extension,
+    })
+}
+
+/// obu_extension_header() - 5.3.3
+fn parse_extension_header<T: ByteRead>(
+    reader: &mut BitstreamReader<T>,
+    options: &ParseOptions,
+) -> Result<ObuExtensionHeader, Error> {
+    let extension = ObuExtensionHeader {
+        temporal_id: reader.f(3)? as u8,
+        spatial_id: reader.f(2)? as u8,
+        extension_header_reserved_3bits: reader.f(3)? as u8,
+    };
+    if extension.extension_header_reserved_3bits != 0 && options.strict_reserved_bits {
+        return Err(Error::Invalid("extension_header_reserved_3bits is set"));
+    }
+    Ok(extension)
+}
+
+/// sequence_header_obu() - 5.5.1
+pub fn parse_sequence_header<T: ByteRead>(
+    reader: &mut BitstreamReader<T>,
+) -> Result<SequenceHeaderObu, Error> {
+    let mut seq = SequenceHeaderObu {
+        seq_profile: Av1Profile::try_from(reader.f(3)? as u8)?,
+        still_picture: reader.f1()?,
+        reduced_still_picture_header: reader.f1()?,
+        ..Default::default()
+    };
+
+    if seq.reduced_still_picture_header {
+        if !seq.still_picture {
+            return Err(Error::Invalid(
+                "reduced_still_picture_header without still_picture",
+            ));
+        }
+
+        seq.operating_point_idc.push(0);
+        seq.seq_level_idx.push(reader.f(5)? as u8);
+        seq.seq_tier.push(0);
+        seq.decoder_model_present_for_this_op.push(false);
+        seq.operating_parameters_info.push(None);
+        seq.initial_display_delay_present_for_this_op.push(false);
+        seq.initial_display_delay_minus_1.push(0);
+    } else {
+        let timing_info_present_flag = reader.f1()?;
+        if timing_info_present_flag {
+            seq.timing_info = Some(parse_timing_info(reader)?);
+
+            let decoder_model_info_present_flag = reader.f1()?;
+            if decoder_model_info_present_flag {
+                seq.decoder_model_info = Some(parse_decoder_model_info(reader)?);
+            }
+        }
+
+        seq.initial_display_delay_present_flag = reader.f1()?;
+        seq.operating_points_cnt_minus_1 = reader.f(5)? as u8;
+        for _ in 0..=seq.operating_points_cnt_minus_1 {
+            seq.operating_point_idc.push(reader.f(12)? as u16);
+
+            let seq_level_idx = reader.f(5)? as u8;
+            seq.seq_level_idx.push(seq_level_idx);
+            seq.seq_tier.push(if seq_level_idx > 7 {
+                reader.f(1)? as u8
+            } else {
+                0
+            });
+
+            let decoder_model_present_for_this_op = match &seq.decoder_model_info {
+                Some(decoder_model_info) if reader.f1()? => {
+                    Some(parse_operating_parameters_info(reader, decoder_model_info)?)
+                }
+                _ => None,
+            };
+            seq.decoder_model_present_for_this_op
+                .push(decoder_model_present_for_this_op.is_some());
+            seq.operating_parameters_info
+                .push(decoder_model_present_for_this_op);
+
+            let initial_display_delay_present_for_this_op =
+                seq.initial_display_delay_present_flag && reader.f1()?;
+            seq.initial_display_delay_present_for_this_op
+                .push(initial_display_delay_present_for_this_op);
+            seq.initial_display_delay_minus_1
+                .push(if initial_display_delay_present_for_this_op {
+                    reader.f(4)? as u8
+                } else {
+                    0
+                });
+        }
+    }
+
+    seq.frame_width_bits_minus_1 = reader.f(4)? as u8;
+    seq.frame_height_bits_minus_1 = reader.f(4)? as u8;
+    seq.max_frame_width_minus_1 = reader.f(seq.frame_width_bits_minus_1 + 1)?;
+    seq.max_frame_height_minus_1 = reader.f(seq.frame_height_bits_minus_1 + 1)?;
+
+    seq.frame_id_numbers_present_flag = !seq.reduced_still_picture_header && reader.f1()?;
+    if seq.frame_id_numbers_present_flag {
+        seq.delta_frame_id_length_minus_2 = reader.f(4)? as u8;
+        seq.additional_frame_id_length_minus_1 = reader.f(3)? as u8;
+    }
+
+    seq.use_128x128_superblock = reader.f1()?;
+    seq.enable_filter_intra = reader.f1()?;
+    seq.enable_intra_edge_filter = reader.f1()?;
+
+    if seq.reduced_still_picture_header {
+        seq.seq_choose_screen_content_tools = true;
+        seq.seq_force_screen_content_tools = SELECT_SCREEN_CONTENT_TOOLS;
+        seq.seq_choose_integer_mv = true;
+        seq.seq_force_integer_mv = SELECT_INTEGER_MV;
+    } else {
+        seq.enable_interintra_compound = reader.f1()?;
+        seq.enable_masked_compound = reader.f1()?;
+        seq.enable_warped_motion = reader.f1()?;
+        seq.enable_dual_filter = reader.f1()?;
+        seq.enable_order_hint = reader.f1()?;
+        if seq.enable_order_hint {
+            seq.enable_jnt_comp = reader.f1()?;
+            seq.enable_ref_frame_mvs = reader.f1()?;
+        }
+
+        seq.seq_choose_screen_content_tools = reader.f1()?;
+        seq.seq_force_screen_content_tools = if seq.seq_choose_screen_content_tools {
+            SELECT_SCREEN_CONTENT_TOOLS
+        } else {
+            reader.f(1)? as u8
+        };
+
+        if seq.seq_force_screen_content_tools > 0 {
+            seq.seq_choose_integer_mv = reader.f1()?;
+            seq.seq_force_integer_mv = if seq.seq_choose_integer_mv {
+                SELECT_INTEGER_MV
+            } else {
+                reader.f(1)? as u8
+            };
+        } else {
+            seq.seq_force_integer_mv = SELECT_INTEGER_MV;
+        }
+
+        if seq.enable_order_hint {
+            seq.order_hint_bits_minus_1 = reader.f(3)? as u8;
+        }
+    }
+
+    seq.enable_superres = reader.f1()?;
+    seq.enable_cdef = reader.f1()?;
+    seq.enable_restoration = reader.f1()?;
+    seq.color_config = parse_color_config(reader, seq.seq_profile)?;
+    seq.film_grain_params_present = reader.f1()?;
+
+    Ok(seq)
+}
+
+/// timing_info() - 5.5.3
+pub fn parse_timing_info<T: ByteRead>(
+    reader: &mut BitstreamReader<T>,
+) -> Result<SequenceHeaderTimingInfo, Error> {
+    let mut timing_info = SequenceHeaderTimingInfo {
+        num_units_in_display_tick: reader.f(32)?,
+        time_scale: reader.f(32)?,
+        equal_picture_interval: reader.f1()?,
+        ..Default::default()
+    };
+
+    if timing_info.equal_picture_interval {
+        timing_info.num_ticks_per_picture_minus_1 = reader.uvlc()?;
+    }
+    Ok(timing_info)
+}
+
+/// decoder_model_info() - 5.5.4
+pub fn parse_decoder_model_info<T: ByteRead>(
+    reader: &mut BitstreamReader<T>,
+) -> Result<SequenceHeaderDecoderModelInfo, Error> {
+    Ok(SequenceHeaderDecoderModelInfo {
+        buffer_delay_length_minus_1: reader.f(5)? as u8,
+        num_units_in_decoding_tick: reader.f(32)?,
+        buffer_removal_time_length_minus_1: reader.f(5)? as u8,
+        frame_presentation_time_length_minus_1: reader.f(5)? as u8,
+    })
+}
+
+/// operating_parameters_info() - 5.5.5
+pub fn parse_operating_parameters_info<T: ByteRead>(
+    reader: &mut BitstreamReader<T>,
+    decoder_model_info: &SequenceHeaderDecoderModelInfo,
+) -> Result<OperatingParametersInfo, Error> {
+    let n = decoder_model_info.buffer_delay_length_minus_1 + 1;
+    Ok(OperatingParametersInfo {
+        decoder_buffer_delay: reader.f(n)?,
+        encoder_buffer_delay: reader.f(n)?,
+        low_delay_mode_flag: reader.f1()?,
+    })
+}
+
+/// color_config() - 5.5.2
+pub fn parse_color_config<T: ByteRead>(
+    reader: &mut BitstreamReader<T>,
+    seq_profile: Av1Profile,
+) -> Result<ColorConfig, Error> {
+    let mut config = ColorConfig {
+        high_bitdepth: reader.f1()?,
+        ..Default::default()
+    };
+
+    if seq_profile == Av1Profile::Professional && config.high_bitdepth {
+        config.twelve_bit = reader.f1()?;
+        config.bit_depth = if config.twelve_bit { 12 } else { 10 };
+    } else {
+        config.bit_depth = if config.high_bitdepth { 10 } else { 8 };
+    }
+
+    config.mono_chrome = seq_profile != Av1Profile::High && reader.f1()?;
+
+    config.color_description_present_flag = reader.f1()?;
+    if config.color_description_present_flag {
+        config.color_primaries = reader.f(8)? as u8;
+        config.transfer_characteristics = reader.f(8)? as u8;
+        config.matrix_coefficients = reader.f(8)? as u8;
+    } else {
+        config.color_primaries = CP_UNSPECIFIED;
+        config.transfer_characteristics = TC_UNSPECIFIED;
+        config.matrix_coefficients = MC_UNSPECIFIED;
+    }
+
+    if config.mono_chrome {
+        config.color_range = reader.f1()?;
+        config.subsampling_x = true;
+        config.subsampling_y = true;
+        config.chroma_sample_position = CSP_UNKNOWN;
+        config.separate_uv_delta_q = false;
+        return Ok(config);
+    } else if config.color_primaries == CP_BT_709
+        && config.transfer_characteristics == TC_SRGB
+        && config.matrix_coefficients == MC_IDENTITY
+    {
+        config.color_range = true;
+        config.subsampling_x = false;
+        config.subsampling_y = false;
+    } else {
+        config.color_range = reader.f1()?;
+        match seq_profile {
+            Av1Profile::Main => {
+                config.subsampling_x = true;
+                config.subsampling_y = true;
+            }
+            Av1Profile::High => {
+                config.subsampling_x = false;
+                config.subsampling_y = false;
+            }
+            Av1Profile::Professional => {
+                if config.bit_depth == 12 {
+                    config.subsampling_x = reader.f1()?;
+                    config.subsampling_y = config.subsampling_x && reader.f1()?;
+                } else {
+                    config.subsampling_x = true;
+                    config.subsampling_y = false;
+                }
+            }
+        }
+
+        if config.subsampling_x && config.subsampling_y {
+            config.chroma_sample_position = reader.f(2)? as u8;
+        }
+    }
+
+    config.separate_uv_delta_q = reader.f1()?;
+    Ok(config)
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+    use crate::av1::frame_header::KEY_FRAME;
+    use crate::av1::test_fixtures::{KEY_FRAME_64X64, SEQ_64X64, SEQ_STILL};
+    use std::io::Cursor;
+
+    fn header(buf: &[u8]) -> Result<ObuHeader, Error> {
+        let mut bytes = Cursor::new(buf);
+        let mut bs = BitstreamReader::new(&mut bytes).unwrap();
+        parse_header(&mut bs, &ParseOptions::default())
+    }
+
+    fn lenient_header(buf: &[u8]) -> Result<ObuHeader, Error> {
+        let mut bytes = Cursor::new(buf);
+        let mut bs = BitstreamReader::new(&mut bytes).unwrap();
+        parse_header(
+            &mut bs,
+            &ParseOptions {
+                strict_reserved_bits: false,
+                ..Default::default()
+            },
+        )
+    }
+
+    #[test]
+    fn obu_type_values() {
+        const TYPES: [(u8, ObuType); 9] = [
+            (OBU_SEQUENCE_HEADER, ObuType::SequenceHeader),
+            (OBU_TEMPORAL_DELIMITER, ObuType::TemporalDelimiter),
+            (OBU_FRAME_HEADER, ObuType::FrameHeader),
+            (OBU_TILE_GROUP, ObuType::TileGroup),
+            (OBU_METADATA, ObuType::Metadata),
+            (OBU_FRAME, ObuType::Frame),
+            (OBU_REDUNDANT_FRAME_HEADER, ObuType::RedundantFrameHeader),
+            (OBU_TILE_LIST, ObuType::TileList),
+            (OBU_PADDING, ObuType::Padding),
+        ];
+
+        for (value, obu_type) in TYPES {
+            assert_eq!(ObuType::try_from(value).unwrap(), obu_type);
+        }
+    }
+
+    #[test]
+    fn obu_type_reserved() {
+        for value in [0, 9, 10, 11, 12, 13, 14, 16, 255] {
+            let err = ObuType::try_from(value).unwrap_err();
+            assert!(matches!(err, Error::Invalid(_)));
+        }
+    }
+
+    #[test]
+    fn temporal_delimiter() {
+        let header = header(&[0x12, 0x00]).unwrap();
+        assert_eq!(header.obu_type, ObuType::TemporalDelimiter);
+        assert!(!header.obu_extension_flag);
+        assert!(header.obu_has_size_field);
+        assert!(!header.obu_reserved_1bit);
+        assert_eq!(header.extension, None);
+    }
+
+    #[test]
+    fn sequence_header() {
+        let header = header(&[0x0a, 0x0a]).unwrap();
+        assert_eq!(header.obu_type, ObuType::SequenceHeader);
+        assert!(header.obu_has_size_field);
+        assert_eq!(header.extension, None);
+    }
+
+    #[test]
+    fn frame_without_size() {
+        let header = header(&[0x30]).unwrap();
+        assert_eq!(header.obu_type, ObuType::Frame);
+        assert!(!header.obu_has_size_field);
+    }
+
+    #[test]
+    fn frame_with_extension() {
+        let header = header(&[0x36, 0x28]).unwrap();
+        assert_eq!(header.obu_type, ObuType::Frame);
+        assert!(header.obu_extension_flag);
+        assert!(header.obu_has_size_field);
+        assert_eq!(
+            header.extension,
+            Some(ObuExtensionHeader {
+                temporal_id: 1,
+                spatial_id: 1,
+                extension_header_reserved_3bits: 0,
+            })
+        );
+    }
+
+    #[test]
+    fn obu_payload() {
+        // OBU_PADDING with an extension header and obu_size = 3, followed by another OBU
+        const TEST_BUF: [u8; 8] = [0x7e, 0x48, 0x03, 0xaa, 0xbb, 0xcc, 0x12, 0x00];
+
+        let obu = Obu::from_buf(&TEST_BUF).unwrap();
+        assert_eq!(obu.obu_type(), ObuType::Padding);
+        assert_eq!(obu.buf().len(), 6);
+
+        let mut bytes = Cursor::new(&TEST_BUF[2..]);
+        let mut bs = BitstreamReader::new(&mut bytes).unwrap();
+        assert_eq!(obu.payload().len() as u64, bs.leb128().unwrap());
+        assert_eq!(obu.payload(), &[0xaa, 0xbb, 0xcc]);
+
+        assert_eq!(
+            obu.extension_header(),
+            Some(ObuExtensionHeader {
+                temporal_id: 2,
+                spatial_id: 1,
+                extension_header_reserved_3bits: 0,
+            })
+        );
+        assert_eq!((obu.temporal_id(), obu.spatial_id()), (2, 1));
+
+        let obu = Obu::from_buf(&TEST_BUF[6..]).unwrap();
+        assert_eq!(obu.extension_header(), None);
+        assert_eq!((obu.temporal_id(), obu.spatial_id()), (0, 0));
+    }
+
+    #[test]
+    fn payload_hash() {
+        const TEST_BUF: [u8; 12] = [
+            0x7a, 0x03, 0xaa, 0xbb, 0xcc, 0x7e, 0x00, 0x03, 0xaa, 0xbb, 0xcc, 0x12,
+        ];
+
+        // The header isn't part of the hash, only the payload
+        let obus: Vec<Obu> = iter_obus(&TEST_BUF[..11]).map(|obu| obu.unwrap()).collect();
+        assert_eq!(obus[0].payload_hash(), obus[1].payload_hash());
+
+        let mut changed = TEST_BUF;
+        changed[4] ^= 0x01;
+        let obu = Obu::from_buf(&changed).unwrap();
+        assert_ne!(obu.payload_hash(), obus[0].payload_hash());
+
+        // Known FNV-1a values
+        let empty = Obu::from_buf(&[0x12, 0x00]).unwrap();
+        assert_eq!(empty.payload_hash(), 0xcbf29ce484222325);
+        let a = Obu::from_buf(&[0x7a, 0x01, b'a']).unwrap();
+        assert_eq!(a.payload_hash(), 0xaf63dc4c8601ec8c);
+    }
+
+    #[test]
+    fn write_to_round_trip() {
+        // A padding OBU with a two byte obu_size, then a tile group with an extension header
+        let mut buf = vec![0x7a, 0x80, 0x01];
+        buf.extend_from_slice(&[0xcc; 128]);
+        buf.extend_from_slice(&[0x26, 0x48, 0x02, 0xa0, 0xa1]);
+
+        let mut out = Vec::new();
+        for obu in iter_obus(&buf) {
+            obu.unwrap().write_to(&mut out).unwrap();
+        }
+        assert_eq!(out, buf);
+    }
+
+    #[test]
+    fn write_to_adds_size_field() {
+        // Without obu_has_size_field the OBU extends to the end of the buffer
+        const TEST_BUF: [u8; 3] = [0x78, 0xaa, 0xbb];
+
+        let mut out = Vec::new();
+        Obu::from_buf(&TEST_BUF)
+            .unwrap()
+            .write_to(&mut out)
+            .unwrap();
+        assert_eq!(out, [0x7a, 0x02, 0xaa, 0xbb]);
+
+        let obu = Obu::from_buf(&out).unwrap();
+        assert!(obu.header.obu_has_size_field);
+        assert_eq!(obu.payload(), &TEST_BUF[1..]);
+    }
+
+    #[test]
+    fn obu_display() {
+        let obu = Obu::from_buf(&[0x0a, 0x02, 0xaa, 0xbb]).unwrap();
+        assert_eq!(obu.to_string(), "OBU_SEQUENCE_HEADER ext=none size=2");
+
+        let obu = Obu::from_buf(&[0x36, 0x28, 0x01, 0xcc]).unwrap();
+        assert_eq!(obu.header.to_string(), "OBU_FRAME ext=tid1/sid1");
+        assert_eq!(obu.to_string(), "OBU_FRAME ext=tid1/sid1 size=1");
+    }
+
+    #[test]
+    fn padding() {
+        let header = header(&[0x7a, 0x00]).unwrap();
+        assert_eq!(header.obu_type, ObuType::Padding);
+    }
+
+    #[test]
+    fn forbidden_bit() {
+        let err = header(&[0x92, 0x00]).unwrap_err();
+        assert!(matches!(err, Error::Invalid(_)));
+
+        let header = lenient_header(&[0x92, 0x00]).unwrap();
+        assert_eq!(header.obu_type, ObuType::TemporalDelimiter);
+    }
+
+    #[test]
+    fn reserved_bit() {
+        let err = header(&[0x13, 0x00]).unwrap_err();
+        assert!(matches!(err, Error::Invalid(_)));
+
+        let header = lenient_header(&[0x13, 0x00]).unwrap();
+        assert_eq!(header.obu_type, ObuType::TemporalDelimiter);
+        assert!(header.obu_reserved_1bit);
+    }
+
+    #[test]
+    fn extension_reserved_bits() {
+        // temporal_id = 1, spatial_id = 1, extension_header_reserved_3bits = 5
+        let err = header(&[0x36, 0x2d]).unwrap_err();
+        assert!(matches!(err, Error::Invalid(_)));
+
+        let header = lenient_header(&[0x36, 0x2d]).unwrap();
+        let extension = header.extension.unwrap();
+        assert_eq!((extension.temporal_id, extension.spatial_id), (1, 1));
+        assert_eq!(extension.extension_header_reserved_3bits, 5);
+    }
+
+    #[test]
+    fn max_obu_size() {
+        // A padding OBU with obu_size = 0x10000, and one with a leb128 size far beyond the buffer
+        let mut buf = vec![0x7a, 0x80, 0x80, 0x04];
+        buf.resize(4 + 0x10000, 0);
+        const HUGE_SIZE: [u8; 9] = [0x7a, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x7f];
+
+        let options = ParseOptions {
+            max_obu_size: 0x10000,
+            ..Default::default()
+        };
+        let obu = Obu::from_buf_with_options(&buf, &options).unwrap();
+        assert_eq!(obu.payload().len(), 0x10000);
+        assert!(Obu::from_buf(&buf).is_ok());
+
+        let options = ParseOptions {
+            max_obu_size: 0xffff,
+            ..Default::default()
+        };
+        let err = Obu::from_buf_with_options(&buf, &options).unwrap_err();
+        assert!(matches!(err, Error::Invalid(_)));
+        let err = Obu::from_buf(&HUGE_SIZE).unwrap_err();
+        assert!(matches!(err, Error::Invalid(_)));
+    }
+
+    #[test]
+    fn lenient_iterator() {
+        const TEST_BUF: [u8; 4] = [0x92, 0x00, 0x13, 0x00];
+
+        assert!(iter_obus(&TEST_BUF).next().unwrap().is_err());
+        let obus: Vec<Obu> = iter_obus_with_options(
+            &TEST_BUF,
+            ParseOptions {
+                strict_reserved_bits: false,
+                ..Default::default()
+            },
+        )
+        .collect::<Result<_, _>>()
+        .unwrap();
+        assert_eq!(obus.len(), 2);
+    }
+
+    #[test]
+    fn reserved_type() {
+        let err = header(&[0x02, 0x00]).unwrap_err();
+        assert!(matches!(err, Error::Invalid(_)));
+    }
+
+    #[test]
+    fn truncated_extension() {
+        let err = header(&[0x36]).unwrap_err();
+        assert!(matches!(err, Error::Eof));
+    }
+
+    #[test]
+    fn sequence_header_construct() {
+        let seq = SequenceHeaderObu {
+            operating_points_cnt_minus_1: 1,
+            operating_point_idc: vec![0x103, 0x101],
+            seq_level_idx: vec![8, 5],
+            seq_tier: vec![0, 0],
+            decoder_model_info: Some(SequenceHeaderDecoderModelInfo {
+                buffer_delay_length_minus_1: 23,
+                ..Default::default()
+            }),
+            order_hint_bits_minus_1: 6,
+            ..Default::default()
+        };
+
+        assert_eq!(
+            seq.seq_level_idx.len(),
+            seq.operating_points_cnt_minus_1 as usize + 1
+        );
+        assert_eq!(seq.order_hint_bits_minus_1, 6);
+        assert_eq!(seq.timing_info, None);
+    }
+
+    #[test]
+    fn profile_round_trip() {
+        for seq_profile in 0..=2 {
+            let profile = Av1Profile::try_from(seq_profile).unwrap();
+            assert_eq!(profile.as_u8(), seq_profile);
+        }
+
+        assert_eq!(Av1Profile::try_from(0).unwrap(), Av1Profile::Main);
+        assert_eq!(Av1Profile::try_from(1).unwrap(), Av1Profile::High);
+        assert_eq!(Av1Profile::try_from(2).unwrap(), Av1Profile::Professional);
+    }
+
+    #[test]
+    fn profile_reserved() {
+        for seq_profile in 3..=7 {
+            let err = Av1Profile::try_from(seq_profile).unwrap_err();
+            assert!(matches!(err, Error::Invalid(_)));
+        }
+    }
+
+    #[test]
+    fn sequence_header_reserved_profile() {
+        const TEST_BUF: [u8; 2] = [0b11100000, 0x00];
+
+        let mut bytes = Cursor::new(&TEST_BUF);
+        let mut bs = BitstreamReader::new(&mut bytes).unwrap();
+        let err = parse_sequence_header(&mut bs).unwrap_err();
+        assert!(matches!(err, Error::Invalid(_)));
+    }
+
+    // Sequence header payloads as written by rav1e
+    const SEQ_1080P_10BIT: [u8; 11] = [
+        0x00, 0x00, 0x00, 0xfa, 0xab, 0xbf, 0xc3, 0x71, 0x08, 0x55, 0x05,
+    ];
+    // A 1920x1080 AVIF image's sequence header as libaom writes it: level 5.1, 128x128
+    // superblocks, sRGB transfer with BT.709 primaries and BT.601 matrix coefficients
+    const SEQ_AVIF: [u8; 10] = [0x1b, 0x6a, 0xbb, 0xfc, 0x37, 0xec, 0x80, 0x86, 0x83, 0x42];
+    const SEQ_MONO: [u8; 10] = [0x00, 0x00, 0x00, 0xf9, 0x57, 0xff, 0xc4, 0x21, 0x52, 0x20];
+    const SEQ_HDR: [u8; 13] = [
+        0x00, 0x00, 0x00, 0xf9, 0x57, 0xff, 0xc4, 0x21, 0x51, 0x09, 0x10, 0x09, 0x14,
+    ];
+
+    fn seq_header(buf: &[u8]) -> SequenceHeaderObu {
+        let mut bytes = Cursor::new(buf);
+        let mut bs = BitstreamReader::new(&mut bytes).unwrap();
+        parse_sequence_header(&mut bs).unwrap()
+    }
+
+    #[test]
+    fn sequence_header_64x64() {
+        let seq = seq_header(&SEQ_64X64);
+        assert_eq!(seq.seq_profile, Av1Profile::Main);
+        assert!(!seq.still_picture);
+        assert!(!seq.reduced_still_picture_header);
+        assert_eq!(seq.timing_info, None);
+        assert_eq!(seq.operating_points_cnt_minus_1, 0);
+        assert_eq!(seq.operating_point_idc, vec![0]);
+        assert_eq!(seq.seq_level_idx, vec![31]);
+        assert_eq!(seq.seq_tier, vec![0]);
+        assert_eq!(seq.frame_width_bits_minus_1, 5);
+        assert_eq!(seq.frame_height_bits_minus_1, 5);
+        assert_eq!(seq.max_frame_width_minus_1, 63);
+        assert_eq!(seq.max_frame_height_minus_1, 63);
+        assert!(!seq.frame_id_numbers_present_flag);
+        assert!(!seq.use_128x128_superblock);
+        assert!(!seq.enable_filter_intra);
+        assert!(seq.enable_intra_edge_filter);
+        assert!(!seq.enable_interintra_compound);
+        assert!(!seq.enable_masked_compound);
+        assert!(!seq.enable_warped_motion);
+        assert!(!seq.enable_dual_filter);
+        assert!(seq.enable_order_hint);
+        assert!(!seq.enable_jnt_comp);
+        assert!(!seq.enable_ref_frame_mvs);
+        assert!(!seq.seq_choose_screen_content_tools);
+        assert_eq!(seq.seq_force_screen_content_tools, 0);
+        assert_eq!(seq.seq_force_integer_mv, SELECT_INTEGER_MV);
+        assert_eq!(seq.order_hint_bits_minus_1, 5);
+        assert!(!seq.enable_superres);
+        assert!(seq.enable_cdef);
+        assert!(!seq.enable_restoration);
+    }
+
+    #[test]
+    fn sequence_header_1080p() {
+        let seq = seq_header(&SEQ_1080P_10BIT);
+        assert_eq!(seq.frame_width_bits_minus_1, 10);
+        assert_eq!(seq.frame_height_bits_minus_1, 10);
+        assert_eq!(seq.max_frame_width_minus_1, 1919);
+        assert_eq!(seq.max_frame_height_minus_1, 1079);
+        assert!(seq.enable_cdef);
+    }
+
+    #[test]
+    fn sequence_header_display() {
+        assert_eq!(
+            seq_header(&SEQ_64X64).to_string(),
+            "profile=Main size=64x64 bit_depth=8 subsampling=4:2:0 \
+             tools=intra_edge_filter,order_hint,cdef"
+        );
+        assert_eq!(
+            seq_header(&SEQ_MONO).to_string(),
+            "profile=Main size=64x64 bit_depth=8 subsampling=4:0:0 \
+             tools=intra_edge_filter,order_hint,cdef"
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn sequence_header_serialize() {
+        let seq = seq_header(&SEQ_1080P_10BIT);
+        let json = serde_json::to_value(&seq).unwrap();
+        assert_eq!(json["seq_profile"], "Main");
+        assert_eq!(json["max_frame_width_minus_1"], 1919);
+        assert_eq!(json["color_config"]["bit_depth"], 10);
+
+        let obu = Obu::from_buf(&[0x12, 0x00]).unwrap();
+        let json = serde_json::to_value(obu.header).unwrap();
+        assert_eq!(json["obu_type"], "TemporalDelimiter");
+        assert_eq!(json["extension"], serde_json::Value::Null);
+    }
+
+    #[test]
+    fn sequence_header_reduced_still_picture() {
+        let seq = seq_header(&SEQ_STILL);
+        assert!(seq.still_picture);
+        assert!(seq.reduced_still_picture_header);
+        assert_eq!(seq.operating_points_cnt_minus_1, 0);
+        assert_eq!(seq.operating_point_idc, vec![0]);
+        assert_eq!(seq.seq_level_idx, vec![31]);
+        assert_eq!(seq.max_frame_width_minus_1, 63);
+        assert_eq!(seq.max_frame_height_minus_1, 63);
+        assert!(!seq.enable_order_hint);
+        assert_eq!(
+            seq.seq_force_screen_content_tools,
+            SELECT_SCREEN_CONTENT_TOOLS
+        );
+        assert_eq!(seq.seq_force_integer_mv, SELECT_INTEGER_MV);
+        assert!(seq.enable_cdef);
+    }
+
+    #[test]
+    fn sequence_header_avif() {
+        let mut bytes = Cursor::new(&SEQ_AVIF);
+        let mut bs = BitstreamReader::new(&mut bytes).unwrap();
+        let seq = parse_sequence_header(&mut bs).unwrap();
+        bs.trailing_bits().unwrap();
+
+        assert!(seq.still_picture);
+        assert!(seq.reduced_still_picture_header);
+        assert_eq!(seq.timing_info, None);
+        assert_eq!(seq.decoder_model_info, None);
+        assert!(!seq.initial_display_delay_present_flag);
+        assert_eq!(seq.operating_points_cnt_minus_1 + 1, 1);
+        assert_eq!(seq.operating_point_idc, vec![0]);
+        assert_eq!(seq.seq_level_idx, vec![13]);
+        assert_eq!(seq.seq_tier, vec![0]);
+        assert_eq!(seq.decoder_model_present_for_this_op, vec![false]);
+        assert_eq!(
+            (seq.max_frame_width_minus_1, seq.max_frame_height_minus_1),
+            (1919, 1079)
+        );
+        assert!(!seq.frame_id_numbers_present_flag);
+        assert!(seq.use_128x128_superblock);
+        assert!(seq.enable_filter_intra && seq.enable_intra_edge_filter);
+
+        // The inter coding tools are all forced off
+        assert!(!seq.enable_interintra_compound);
+        assert!(!seq.enable_masked_compound);
+        assert!(!seq.enable_warped_motion);
+        assert!(!seq.enable_dual_filter);
+        assert!(!seq.enable_order_hint);
+        assert!(!seq.enable_jnt_comp);
+        assert!(!seq.enable_ref_frame_mvs);
+        assert_eq!(
+            seq.seq_force_screen_content_tools,
+            SELECT_SCREEN_CONTENT_TOOLS
+        );
+        assert_eq!(seq.seq_force_integer_mv, SELECT_INTEGER_MV);
+
+        assert!(!seq.enable_superres);
+        assert!(seq.enable_cdef && seq.enable_restoration);
+        assert_eq!(seq.bit_depth(), 8);
+        assert_eq!(seq.chroma_subsampling(), (1, 1));
+        assert_eq!(seq.color_config.transfer_characteristics, TC_SRGB);
+        assert!(seq.color_config.color_range);
+        assert!(!seq.film_grain_params_present);
+
+        // reduced_still_picture_header with still_picture = 0
+        let mut buf = SEQ_AVIF;
+        buf[0] = 0x0b;
+        let mut bs = BitstreamReader::from_slice(&buf);
+        assert!(matches!(
+            parse_sequence_header(&mut bs),
+            Err(Error::Invalid(_))
+        ));
+    }
+
+    #[test]
+    fn sequence_header_operating_points() {
+        // Two operating points, the first with an initial display delay
+        const TEST_BUF: [u8; 12] = [
+            0x02, 0x11, 0x03, 0x47, 0x20, 0x02, 0x91, 0x57, 0xff, 0xc4, 0x02, 0x00,
+        ];
+
+        let seq = seq_header(&TEST_BUF);
+        assert!(seq.initial_display_delay_present_flag);
+        assert_eq!(seq.operating_points_cnt_minus_1, 1);
+        assert_eq!(seq.operating_point_idc, vec![0x103, 0x001]);
+        assert_eq!(seq.seq_level_idx, vec![8, 9]);
+        assert_eq!(seq.seq_tier, vec![1, 0]);
+        assert_eq!(
+            seq.initial_display_delay_present_for_this_op,
+            vec![true, false]
+        );
+        assert_eq!(seq.initial_display_delay_minus_1, vec![9, 0]);
+    }
+
+    #[test]
+    fn level_names() {
+        assert_eq!(level_name(0), "2.0");
+        assert_eq!(level_name(8), "4.0");
+        assert_eq!(level_name(13), "5.1");
+        assert_eq!(level_name(19), "6.3");
+        assert_eq!(level_name(2), "reserved");
+        assert_eq!(level_name(11), "reserved");
+        assert_eq!(level_name(24), "reserved");
+        assert_eq!(level_name(30), "reserved");
+        assert_eq!(level_name(31), "max");
+
+        let seq = seq_header(&[
+            0x02, 0x11, 0x03, 0x47, 0x20, 0x02, 0x91, 0x57, 0xff, 0xc4, 0x02, 0x00,
+        ]);
+        assert_eq!(seq.level_name(0), Some("4.0"));
+        assert_eq!(seq.tier(0), Some(Tier::High));
+        assert_eq!(seq.level_name(1), Some("4.1"));
+        assert_eq!(seq.tier(1), Some(Tier::Main));
+        // There are only two operating points
+        assert_eq!(seq.level_name(2), None);
+        assert_eq!(seq.tier(2), None);
+        assert_eq!(seq.level_name(usize::MAX), None);
+    }
+
+    #[test]
+    fn sequence_header_decoder_model() {
+        const TEST_BUF: [u8; 31] = [
+            0x04, 0x00, 0x00, 0x0f, 0xa4, 0x00, 0x03, 0xa9, 0x83, 0xa4, 0x00, 0x00, 0x0f, 0xa6,
+            0x75, 0x04, 0x40, 0xd0, 0xa5, 0x95, 0xe4, 0x00, 0x48, 0xaa, 0xef, 0xf0, 0xdc, 0x42,
+            0x19, 0x00, 0x00,
+        ];
+
+        let seq = seq_header(&TEST_BUF);
+        assert_eq!(
+            seq.timing_info,
+            Some(SequenceHeaderTimingInfo {
+                num_units_in_display_tick: 1001,
+                time_scale: 60000,
+                equal_picture_interval: true,
+                num_ticks_per_picture_minus_1: 0,
+            })
+        );
+        assert_eq!(
+            seq.decoder_model_info,
+            Some(SequenceHeaderDecoderModelInfo {
+                buffer_delay_length_minus_1: 9,
+                num_units_in_decoding_tick: 1001,
+                buffer_removal_time_length_minus_1: 19,
+                frame_presentation_time_length_minus_1: 21,
+            })
+        );
+
+        assert_eq!(seq.operating_points_cnt_minus_1, 1);
+        assert_eq!(seq.seq_level_idx, vec![8, 4]);
+        assert_eq!(seq.decoder_model_present_for_this_op, vec![true, false]);
+        assert_eq!(
+            seq.operating_parameters_info,
+            vec![
+                Some(OperatingParametersInfo {
+                    decoder_buffer_delay: 300,
+                    encoder_buffer_delay: 700,
+                    low_delay_mode_flag: true,
+                }),
+                None
+            ]
+        );
+
+        assert_eq!(seq.max_frame_width_minus_1, 1919);
+        assert_eq!(seq.max_frame_height_minus_1, 1079);
+        assert_eq!(seq.order_hint_bits_minus_1, 6);
+        assert!(seq.enable_cdef);
+        assert_eq!(seq.color_config.bit_depth, 8);
+    }
+
+    #[test]
+    fn timing_info_unequal_interval() {
+        const TEST_BUF: [u8; 9] = [0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x19, 0x00];
+
+        let mut bytes = Cursor::new(&TEST_BUF);
+        let mut bs = BitstreamReader::new(&mut bytes).unwrap();
+        let timing_info = parse_timing_info(&mut bs).unwrap();
+        assert_eq!(timing_info.num_units_in_display_tick, 1);
+        assert_eq!(timing_info.time_scale, 25);
+        assert!(!timing_info.equal_picture_interval);
+        assert_eq!(bs.position(), 65);
+    }
+
+    fn color_config(buf: &[u8], seq_profile: Av1Profile) -> ColorConfig {
+        let mut bytes = Cursor::new(buf);
+        let mut bs = BitstreamReader::new(&mut bytes).unwrap();
+        parse_color_config(&mut bs, seq_profile).unwrap()
+    }
+
+    #[test]
+    fn color_config_monochrome() {
+        let seq = seq_header(&SEQ_MONO);
+        let config = &seq.color_config;
+        assert_eq!(config.bit_depth, 8);
+        assert!(config.mono_chrome);
+        assert!(!config.color_description_present_flag);
+        assert_eq!(config.color_primaries, CP_UNSPECIFIED);
+        assert_eq!(config.transfer_characteristics, TC_UNSPECIFIED);
+        assert_eq!(config.matrix_coefficients, MC_UNSPECIFIED);
+        assert!(!config.color_range);
+        assert!(config.subsampling_x);
+        assert!(config.subsampling_y);
+        assert_eq!(config.chroma_sample_position, CSP_UNKNOWN);
+        assert!(!config.separate_uv_delta_q);
+        assert!(!seq.film_grain_params_present);
+    }
+
+    #[test]
+    fn color_config_10bit_420() {
+        let seq = seq_header(&SEQ_1080P_10BIT);
+        let config = &seq.color_config;
+        assert!(config.high_bitdepth);
+        assert!(!config.twelve_bit);
+        assert_eq!(config.bit_depth, 10);
+        assert!(!config.mono_chrome);
+        assert!(!config.color_description_present_flag);
+        assert!(!config.color_range);
+        assert!(config.subsampling_x);
+        assert!(config.subsampling_y);
+        assert_eq!(config.chroma_sample_position, CSP_UNKNOWN);
+        assert!(config.separate_uv_delta_q);
+        assert!(!seq.film_grain_params_present);
+    }
+
+    #[test]
+    fn color_config_description() {
+        let config = seq_header(&SEQ_HDR).color_config;
+        assert_eq!(config.bit_depth, 8);
+        assert!(config.color_description_present_flag);
+        assert_eq!(config.color_primaries, 9);
+        assert_eq!(config.transfer_characteristics, 16);
+        assert_eq!(config.matrix_coefficients, 9);
+        assert!(config.subsampling_x);
+        assert!(config.subsampling_y);
+    }
+
+    #[test]
+    fn color_config_srgb() {
+        // high_bitdepth, color_description_present_flag, BT.709/sRGB/identity,
+        // separate_uv_delta_q
+        const TEST_BUF: [u8; 4] = [0x40, 0x43, 0x40, 0x00];
+
+        let config = color_config(&TEST_BUF, Av1Profile::High);
+        assert!(config.color_range);
+        assert!(!config.subsampling_x);
+        assert!(!config.subsampling_y);
+        assert!(!config.separate_uv_delta_q);
+    }
+
+    #[test]
+    fn color_config_12bit() {
+        // high_bitdepth, twelve_bit, mono_chrome, color_description_present_flag, color_range,
+        // subsampling_x, subsampling_y, chroma_sample_position, separate_uv_delta_q
+        const TEST_BUF: [u8; 2] = [0b11001100, 0b00000000];
+
+        let config = color_config(&TEST_BUF, Av1Profile::Professional);
+        assert!(config.twelve_bit);
+        assert_eq!(config.bit_depth, 12);
+        assert!(config.color_range);
+        assert!(config.subsampling_x);
+        assert!(!config.subsampling_y);
+        assert!(!config.separate_uv_delta_q);
+
+        // Professional profile 10-bit is 4:2:2
+        const TEST_BUF_10BIT: [u8; 1] = [0b10000100];
+
+        let config = color_config(&TEST_BUF_10BIT, Av1Profile::Professional);
+        assert_eq!(config.bit_depth, 10);
+        assert!(config.subsampling_x);
+        assert!(!config.subsampling_y);
+        assert!(config.separate_uv_delta_q);
+    }
+
+    #[test]
+    fn sequence_header_format() {
+        let seq = seq_header(&SEQ_HDR);
+        assert_eq!(seq.bit_depth(), 8);
+        assert!(!seq.is_monochrome());
+        assert_eq!(seq.chroma_subsampling(), (1, 1));
+
+        let seq = seq_header(&SEQ_MONO);
+        assert!(seq.is_monochrome());
+        assert_eq!(seq.chroma_subsampling(), (1, 1));
+
+        // high_bitdepth, then BT.709/sRGB/identity which is 4:4:4, in the High profile
+        let seq = SequenceHeaderObu {
+            seq_profile: Av1Profile::High,
+            color_config: color_config(&[0xc0, 0x43, 0x40, 0x00], Av1Profile::High),
+            ..Default::default()
+        };
+        assert_eq!(seq.bit_depth(), 10);
+        assert!(!seq.is_monochrome());
+        assert_eq!(seq.chroma_subsampling(), (0, 0));
+
+        // high_bitdepth, twelve_bit, 4:2:2 in the Professional profile
+        let seq = SequenceHeaderObu {
+            seq_profile: Av1Profile::Professional,
+            color_config: color_config(&[0b11001100, 0b00000000], Av1Profile::Professional),
+            ..Default::default()
+        };
+        assert_eq!(seq.bit_depth(), 12);
+        assert!(!seq.is_monochrome());
+        assert_eq!(seq.chroma_subsampling(), (1, 0));
+    }
+
+    #[test]
+    fn order_hint_bits() {
+        let seq = SequenceHeaderObu {
+            enable_order_hint: true,
+            order_hint_bits_minus_1: 7,
+            ..Default::default()
+        };
+        assert_eq!(seq.order_hint_bits(), 8);
+
+        let seq = SequenceHeaderObu {
+            enable_order_hint: false,
+            ..seq
+        };
+        assert_eq!(seq.order_hint_bits(), 0);
+
+        let seq = seq_header(&SEQ_64X64);
+        assert_eq!(seq.order_hint_bits(), 6);
+    }
+
+    #[test]
+    fn probe_first_sequence_header() {
+        // A temporal delimiter, metadata and the sequence header, then garbage
+        let mut buf = vec![
+            0x12,
+            0x00,
+            0x2a,
+            0x02,
+            0x01,
+            0x00,
+            0x0a,
+            SEQ_64X64.len() as u8,
+        ];
+        buf.extend_from_slice(&SEQ_64X64);
+        buf.extend_from_slice(&[0xff; 8]);
+        assert!(iter_obus(&buf).any(|obu| obu.is_err()));
+
+        let seq = probe_sequence_header(&buf).unwrap();
+        assert_eq!(seq, seq_header(&SEQ_64X64));
+
+        assert!(matches!(
+            probe_sequence_header(&[0x12, 0x00]),
+            Err(Error::Invalid(_))
+        ));
+        assert!(matches!(
+            probe_sequence_header(&[0xff; 8]),
+            Err(Error::Invalid(_))
+        ));
+    }
+
+    #[test]
+    fn obu_from_buf() {
+        const TEST_BUF: [u8; 4] = [0x2a, 0x02, 0x01, 0x00];
+
+        let obu = Obu::from_buf(&TEST_BUF).unwrap();
+        assert_eq!(obu.header.obu_type, ObuType::Metadata);
+        assert_eq!(obu.buf(), &TEST_BUF);
+    }
+
+    #[test]
+    fn obu_from_buf_payload() {
+        const TEST_BUF: [u8; 7] = [0x2a, 0x02, 0x01, 0x00, 0x12, 0x00, 0xff];
+
+        let obu = Obu::from_buf(&TEST_BUF).unwrap();
+        assert_eq!(obu.buf(), &TEST_BUF[..4]);
+        assert_eq!(obu.payload(), &[0x01, 0x00]);
+    }
+
+    #[test]
+    fn iter_temporal_unit() {
+        let mut buf = vec![0x12, 0x00, 0x0a, SEQ_64X64.len() as u8];
+        buf.extend_from_slice(&SEQ_64X64);
+        buf.extend_from_slice(&[0x2a, 0x02, 0x01, 0xaa]);
+        buf.extend_from_slice(&[0x36, 0x28, 0x03, 0x10, 0x20, 0x30]);
+        buf.extend_from_slice(&[0x7a, 0x80, 0x01]);
+        buf.extend_from_slice(&[0xff; 128]);
+
+        let obus: Vec<Obu> = iter_obus(&buf).map(|obu| obu.unwrap()).collect();
+        let types: Vec<ObuType> = obus.iter().map(|obu| obu.header.obu_type).collect();
+        assert_eq!(
+            types,
+            vec![
+                ObuType::TemporalDelimiter,
+                ObuType::SequenceHeader,
+                ObuType::Metadata,
+                ObuType::Frame,
+                ObuType::Padding,
+            ]
+        );
+
+        assert!(obus[0].payload().is_empty());
+        assert_eq!(obus[1].payload(), &SEQ_64X64);
+        assert_eq!(obus[2].payload(), &[0x01, 0xaa]);
+        assert_eq!(obus[3].payload(), &[0x10, 0x20, 0x30]);
+        assert_eq!(obus[3].header.extension.unwrap().temporal_id, 1);
+        assert_eq!(obus[4].payload().len(), 128);
+
+        let total: usize = obus.iter().map(|obu| obu.buf().len()).sum();
+        assert_eq!(total, buf.len());
+    }
+
+    #[test]
+    fn raw_bytes_concat() {
+        let mut buf = vec![0x12, 0x00, 0x0a, SEQ_64X64.len() as u8];
+        buf.extend_from_slice(&SEQ_64X64);
+        buf.extend_from_slice(&[0x36, 0x28, 0x03, 0x10, 0x20, 0x30]);
+        // obu_size in two bytes, where one would do
+        buf.extend_from_slice(&[0x7a, 0x82, 0x00, 0xaa, 0xbb]);
+        buf.extend_from_slice(&[0x12, 0x00, 0x78, 0xcc]);
+
+        let obus: Vec<Obu> = iter_obus(&buf).map(|obu| obu.unwrap()).collect();
+        let concat = |obus: &[Obu]| -> Vec<u8> {
+            obus.iter()
+                .flat_map(|obu| obu.raw_bytes())
+                .copied()
+                .collect()
+        };
+        assert_eq!(concat(&obus), buf);
+        assert_eq!(obus[3].raw_bytes(), &[0x7a, 0x82, 0x00, 0xaa, 0xbb]);
+
+        // Dropping OBUs keeps the bytes of the rest intact
+        let filtered: Vec<Obu> = obus
+            .iter()
+            .filter(|obu| obu.header.obu_type != ObuType::Padding)
+            .copied()
+            .collect();
+        let mut expected = buf[..20].to_vec();
+        expected.extend_from_slice(&[0x12, 0x00]);
+        assert_eq!(concat(&filtered), expected);
+    }
+
+    #[test]
+    fn iter_without_size_field() {
+        const TEST_BUF: [u8; 6] = [0x12, 0x00, 0x30, 0x01, 0x02, 0x03];
+
+        let obus: Vec<Obu> = iter_obus(&TEST_BUF).map(|obu| obu.unwrap()).collect();
+        assert_eq!(obus.len(), 2);
+        assert_eq!(obus[1].header.obu_type, ObuType::Frame);
+        assert_eq!(obus[1].payload(), &[0x01, 0x02, 0x03]);
+    }
+
+    #[test]
+    fn iter_empty() {
+        assert!(iter_obus(&[]).next().is_none());
+    }
+
+    #[test]
+    fn iter_oversized() {
+        const TEST_BUF: [u8; 6] = [0x12, 0x00, 0x32, 0x05, 0x01, 0x02];
+
+        let mut obus = iter_obus(&TEST_BUF);
+        assert!(obus.next().unwrap().is_ok());
+
+        let err = obus.next().unwrap().err().unwrap();
+        assert!(matches!(err, Error::Invalid(_)));
+        assert!(obus.next().is_none());
+    }
+
+    #[test]
+    fn iter_huge_size() {
+        const TEST_BUF: [u8; 10] = [0x32, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x7f, 0x00];
+
+        let err = iter_obus(&TEST_BUF).next().unwrap().err().unwrap();
+        assert!(matches!(err, Error::Invalid(_)));
+    }
+
+    #[test]
+    fn iter_truncated_size() {
+        const TEST_BUF: [u8; 2] = [0x32, 0x80];
+
+        let err = iter_obus(&TEST_BUF).next().unwrap().err().unwrap();
+        assert!(matches!(err, Error::Eof));
+    }
+
+    #[test]
+    fn operating_point_filter_idc() {
+        // Temporal layers 0 and 1 of spatial layer 0
+        assert!(operating_point_filter(0x103, 0, 0));
+        assert!(operating_point_filter(0x103, 1, 0));
+        assert!(!operating_point_filter(0x103, 2, 0));
+        assert!(!operating_point_filter(0x103, 0, 1));
+        // Every OBU is in an operating point with an idc of 0
+        assert!(operating_point_filter(0, 7, 3));
+    }
+
+    #[test]
+    fn operating_point_obus() {
+        // Operating point 0 has temporal layers 0 and 1, operating point 1 only temporal layer 0
+        const SEQ_TWO_LAYERS: [u8; 12] = [
+            0x00, 0x11, 0x03, 0x40, 0x40, 0x40, 0xab, 0xff, 0xe2, 0x10, 0xa8, 0x02,
+        ];
+
+        let mut buf = vec![0x12, 0x00, 0x0a, SEQ_TWO_LAYERS.len() as u8];
+        buf.extend_from_slice(&SEQ_TWO_LAYERS);
+        // Padding in temporal layer 0, temporal layer 1 and without an extension
+        buf.extend_from_slice(&[0x7e, 0x00, 0x01, 0xa0]);
+        buf.extend_from_slice(&[0x7e, 0x20, 0x01, 0xa1]);
+        buf.extend_from_slice(&[0x7a, 0x01, 0xa2]);
+
+        let payloads = |op_idx| -> Vec<u8> {
+            iter_obus_for_operating_point(&buf, op_idx)
+                .map(|obu| obu.unwrap())
+                .filter(|obu| obu.header.obu_type == ObuType::Padding)
+                .map(|obu| obu.payload()[0])
+                .collect()
+        };
+        assert_eq!(payloads(0), vec![0xa0, 0xa1, 0xa2]);
+        assert_eq!(payloads(1), vec![0xa0, 0xa2]);
+
+        let err = iter_obus_for_operating_point(&buf, 2)
+            .find_map(|obu| obu.err())
+            .unwrap();
+        assert!(matches!(err, Error::Invalid(_)));
+    }
+
+    #[test]
+    fn trailing_obu_without_size() {
+        const TEST_BUF: [u8; 6] = [0x12, 0x00, 0x78, 0xaa, 0xbb, 0xcc];
+
+        let obus: Vec<Obu> = iter_obus(&TEST_BUF).map(|obu| obu.unwrap()).collect();
+        assert_eq!(obus.len(), 2);
+        assert_eq!(obus[1].header.obu_type, ObuType::Padding);
+        assert!(!obus[1].header.obu_has_size_field);
+        assert_eq!(obus[1].payload(), &[0xaa, 0xbb, 0xcc]);
+    }
+
+    #[test]
+    fn obu_without_size_followed_by_data() {
+        // A temporal delimiter without a size field, then a sized padding OBU
+        const TEST_BUF: [u8; 5] = [0x10, 0x7a, 0x02, 0xaa, 0xbb];
+
+        let mut obus = iter_obus(&TEST_BUF);
+        let err = obus.next().unwrap().unwrap_err();
+        assert!(matches!(err, Error::Invalid(_)));
+        assert!(obus.next().is_none());
+    }
+
+    #[test]
+    fn non_td_obu_without_size_followed_by_data() {
+        // Checked even without strict_trailing_bits
+        let parse_all = |buf: &[u8]| -> Result<(), Error> {
+            let mut parser = ObuParser::with_options(ParseOptions {
+                strict_trailing_bits: false,
+                ..Default::default()
+            });
+            for obu in iter_obus(buf) {
+                parser.parse(&obu?)?;
+            }
+            Ok(())
+        };
+        let check = |prefix: &[u8], obu: &[u8]| {
+            let mut buf = prefix.to_vec();
+            buf.extend_from_slice(obu);
+            assert!(parse_all(&buf).is_ok());
+            // Zero bytes after the trailing bits are allowed
+            buf.extend_from_slice(&[0x00, 0x00]);
+            assert!(parse_all(&buf).is_ok());
+            // A temporal delimiter can't be told apart from more of the OBU
+            buf.extend_from_slice(&[0x12, 0x00]);
+            assert!(matches!(
+                parse_all(&buf),
+                Err(Error::Invalid(
+                    "obu without a size field followed by more data"
+                ))
+            ));
+        };
+
+        let mut seq = vec![0x08];
+        seq.extend_from_slice(&SEQ_64X64);
+        check(&[], &seq);
+
+        let mut frame_header = vec![0x18];
+        frame_header.extend_from_slice(&KEY_FRAME_64X64);
+        *frame_header.last_mut().unwrap() |= 0x08;
+        let mut prefix = vec![0x0a, SEQ_64X64.len() as u8];
+        prefix.extend_from_slice(&SEQ_64X64);
+        check(&prefix, &frame_header);
+
+        // HDR CLL metadata, and a tile list with one entry of one byte
+        check(&[], &[0x28, 0x01, 0x03, 0xe8, 0x01, 0x90, 0x80]);
+        check(
+            &[],
+            &[
+                0x40, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xb0,
+            ],
+        );
+
+        // T.35 metadata runs up to its trailing bits, so the data is taken as part of it
+        let buf = [0x28, 0x04, 0xb5, 0xaa, 0x80, 0x12, 0x00];
+        assert!(parse_all(&buf).is_ok());
+    }
+
+    #[test]
+    fn count_temporal_units() {
+        let mut frame = vec![0x32, KEY_FRAME_64X64.len() as u8];
+        frame.extend_from_slice(&KEY_FRAME_64X64);
+
+        let mut buf = vec![0x12, 0x00, 0x0a, SEQ_64X64.len() as u8];
+        buf.extend_from_slice(&SEQ_64X64);
+        buf.extend_from_slice(&frame);
+        buf.extend_from_slice(&[0x12, 0x00]);
+        buf.extend_from_slice(&frame);
+        buf.extend_from_slice(&[0x12, 0x00, 0x2a, 0x01, 0x00]);
+        buf.extend_from_slice(&frame);
+        // Without the size field a temporal delimiter has to be the last OBU
+        buf.extend_from_slice(&[0x10]);
+
+        let mut parser = ObuParser::new();
+        let parsed: Vec<ParsedObu> = iter_obus(&buf)
+            .map(|obu| parser.parse(&obu.unwrap()).unwrap())
+            .collect();
+
+        let temporal_units = parsed
+            .iter()
+            .filter(|obu| **obu == ParsedObu::TemporalDelimiter)
+            .count();
+        assert_eq!(temporal_units, 4);
+        assert!(matches!(parsed[1], ParsedObu::SequenceHeader(_)));
+        assert!(matches!(&parsed[2], ParsedObu::Frame(fh, _) if fh.show_frame));
+        assert!(matches!(parsed[6], ParsedObu::Metadata(_)));
+    }
+
+    fn parse_obu<'a>(parser: &mut ObuParser, buf: &'a [u8]) -> Result<ParsedObu<'a>, Error> {
+        parser.parse(&Obu::from_buf(buf).unwrap())
+    }
+
+    #[test]
+    fn parse_padding() {
+        // 300 bytes of padding with a two byte obu_size, then a temporal delimiter, then
+        // padding without a size field that runs to the end of the buffer
+        let mut buf = vec![0x7a, 0xac, 0x02];
+        buf.extend_from_slice(&[0xff; 300]);
+        buf.extend_from_slice(&[0x12, 0x00, 0x78, 0xaa, 0xbb]);
+
+        let mut parser = ObuParser::new();
+        let mut obus = iter_obus(&buf);
+        let obu = obus.next().unwrap().unwrap();
+        assert_eq!(parser.parse(&obu).unwrap(), ParsedObu::Padding { len: 300 });
+        let obu = obus.next().unwrap().unwrap();
+        assert_eq!(parser.parse(&obu).unwrap(), ParsedObu::TemporalDelimiter);
+        let obu = obus.next().unwrap().unwrap();
+        assert_eq!(parser.parse(&obu).unwrap(), ParsedObu::Padding { len: 2 });
+        assert!(obus.next().is_none());
+    }
+
+    #[test]
+    fn obu_size_mismatch() {
+        // obu_size is one byte too large, taking in the temporal delimiter that follows
+        let mut buf = vec![0x0a, SEQ_64X64.len() as u8 + 1];
+        buf.extend_from_slice(&SEQ_64X64);
+        buf.extend_from_slice(&[0x12, 0x00]);
+        let obu = Obu::from_buf(&buf).unwrap();
+
+        assert!(matches!(
+            ObuParser::new().parse(&obu),
+            Err(Error::Invalid(_))
+        ));
+        let mut parser = ObuParser::with_options(ParseOptions {
+            strict_trailing_bits: false,
+            ..Default::default()
+        });
+        assert!(matches!(
+            parser.parse(&obu),
+            Ok(ParsedObu::SequenceHeader(_))
+        ));
+
+        // Zero bytes after the trailing bits are fine
+        buf[2 + SEQ_64X64.len()] = 0x00;
+        let obu = Obu::from_buf(&buf).unwrap();
+        assert!(ObuParser::new().parse(&obu).is_ok());
+
+        // The payload ending before the trailing bits
+        let mut buf = vec![0x0a, SEQ_64X64.len() as u8 - 1];
+        buf.extend_from_slice(&SEQ_64X64[..SEQ_64X64.len() - 1]);
+        let obu = Obu::from_buf(&buf).unwrap();
+        assert!(ObuParser::new().parse(&obu).is_err());
+    }
+
+    #[test]
+    fn frame_header_and_tile_group() {
+        let mut seq = vec![0x0a, SEQ_64X64.len() as u8];
+        seq.extend_from_slice(&SEQ_64X64);
+        let mut frame_header = vec![0x1a, KEY_FRAME_64X64.len() as u8];
+        frame_header.extend_from_slice(&KEY_FRAME_64X64);
+        // trailing_bits() in place of the frame OBU's byte_alignment()
+        *frame_header.last_mut().unwrap() |= 0x08;
+        const TILE_GROUP: [u8; 5] = [0x22, 0x03, 0xa0, 0xa1, 0xa2];
+
+        let mut parser = ObuParser::new();
+        parse_obu(&mut parser, &seq).unwrap();
+
+        let ParsedObu::FrameHeader(first) = parse_obu(&mut parser, &frame_header).unwrap() else {
+            panic!("expected a frame header");
+        };
+        assert_eq!(first.frame_type, KEY_FRAME);
+
+        // A repeated frame header is a copy of the first one
+        let ParsedObu::FrameHeader(copy) = parse_obu(&mut parser, &frame_header).unwrap() else {
+            panic!("expected a frame header");
+        };
+        assert_eq!(copy, first);
+
+        let ParsedObu::TileGroup(tile_group) = parse_obu(&mut parser, &TILE_GROUP).unwrap() else {
+            panic!("expected a tile group");
+        };
+        assert_eq!(tile_group.tiles.len(), 1);
+        assert_eq!(tile_group.tiles[0].len, 3);
+
+        // The frame is complete after its last tile
+        let err = parse_obu(&mut parser, &TILE_GROUP).unwrap_err();
+        assert!(matches!(err, Error::Invalid(_)));
+    }
+
+    #[test]
+    fn redundant_frame_header() {
+        let mut seq = vec![0x0a, SEQ_64X64.len() as u8];
+        seq.extend_from_slice(&SEQ_64X64);
+        let mut frame_header = vec![0x1a, KEY_FRAME_64X64.len() as u8];
+        frame_header.extend_from_slice(&KEY_FRAME_64X64);
+        *frame_header.last_mut().unwrap() |= 0x08;
+        let mut redundant = frame_header.clone();
+        redundant[0] = 0x3a;
+
+        let mut parser = ObuParser::new();
+        parse_obu(&mut parser, &seq).unwrap();
+        let ParsedObu::FrameHeader(first) = parse_obu(&mut parser, &frame_header).unwrap() else {
+            panic!("expected a frame header");
+        };
+        assert_eq!(
+            parse_obu(&mut parser, &redundant).unwrap(),
+            ParsedObu::RedundantFrameHeader(first.clone())
+        );
+
+        // A redundant frame header that differs from the frame header
+        let mut mismatch = redundant.clone();
+        mismatch[10] ^= 0x01;
+        assert!(matches!(
+            parse_obu(&mut parser, &mismatch),
+            Err(Error::Invalid(_))
+        ));
+
+        // With the frame header lost, the redundant one is parsed in its place
+        let mut parser = ObuParser::new();
+        parse_obu(&mut parser, &seq).unwrap();
+        assert_eq!(
+            parse_obu(&mut parser, &redundant).unwrap(),
+            ParsedObu::RedundantFrameHeader(first)
+        );
+        assert!(matches!(
+            parse_obu(&mut parser, &mismatch),
+            Err(Error::Invalid(_))
+        ));
+    }
+
+    #[test]
+    fn max_tiles() {
+        let mut seq = vec![0x0a, SEQ_64X64.len() as u8];
+        seq.extend_from_slice(&SEQ_64X64);
+        let mut frame = vec![0x32, KEY_FRAME_64X64.len() as u8];
+        frame.extend_from_slice(&KEY_FRAME_64X64);
+
+        let mut parser = ObuParser::with_options(ParseOptions {
+            max_tiles: 1,
+            ..Default::default()
+        });
+        parse_obu(&mut parser, &seq).unwrap();
+        assert!(parse_obu(&mut parser, &frame).is_ok());
+
+        let mut parser = ObuParser::with_options(ParseOptions {
+            max_tiles: 0,
+            ..Default::default()
+        });
+        parse_obu(&mut parser, &seq).unwrap();
+        let err = parse_obu(&mut parser, &frame).unwrap_err();
+        assert!(matches!(err, Error::Invalid(_)));
+    }
+
+    #[test]
+    fn frame_header_before_sequence_header() {
+        let mut buf = vec![0x32, KEY_FRAME_64X64.len() as u8];
+        buf.extend_from_slice(&KEY_FRAME_64X64);
+
+        let obu = Obu::from_buf(&buf).unwrap();
+        let err = ObuParser::new().parse(&obu).unwrap_err();
+        assert!(matches!(err, Error::Invalid(_)));
+    }
+
+    #[test]
+    fn temporal_delimiter_with_payload() {
+        const TEST_BUF: [u8; 3] = [0x12, 0x01, 0x00];
+
+        let obu = Obu::from_buf(&TEST_BUF).unwrap();
+        let err = ObuParser::new().parse(&obu).unwrap_err();
+        assert!(matches!(err, Error::Invalid(_)));
+    }
+
+    #[test]
+    fn scan_offsets_layout() {
+        let mut buf = vec![0x12, 0x00, 0x0a, SEQ_64X64.len() as u8];
+        buf.extend_from_slice(&SEQ_64X64);
+        // Metadata with an extension header, padding with a two byte obu_size, then padding
+        // without a size field
+        buf.extend_from_slice(&[0x2e, 0x48, 0x03, 0x01, 0x00, 0x10]);
+        buf.extend_from_slice(&[0x7a, 0xac, 0x02]);
+        buf.extend_from_slice(&[0xff; 300]);
+        buf.extend_from_slice(&[0x78, 0xaa, 0xbb]);
+
+        let entry = |offset, payload_offset, obu_type, obu_size| ObuIndexEntry {
+            offset,
+            payload_offset,
+            obu_type,
+            obu_size,
+            temporal_id: 0,
+            spatial_id: 0,
+        };
+        let entries = scan_offsets(&mut Cursor::new(&buf)).unwrap();
+        assert_eq!(
+            entries,
+            vec![
+                entry(0, 2, ObuType::TemporalDelimiter, 0),
+                entry(2, 4, ObuType::SequenceHeader, 10),
+                ObuIndexEntry {
+                    temporal_id: 2,
+                    spatial_id: 1,
+                    ..entry(14, 17, ObuType::Metadata, 3)
+                },
+                entry(20, 23, ObuType::Padding, 300),
+                entry(323, 324, ObuType::Padding, 2),
+            ]
+        );
+        assert_eq!(buf.len(), 326);
+
+        // Scanning from the middle of the stream
+        let mut cursor = Cursor::new(&buf);
+        cursor.set_position(20);
+        assert_eq!(scan_offsets(&mut cursor).unwrap(), entries[3..]);
+
+        // The padding obu_size runs past the end
+        let err = scan_offsets(&mut Cursor::new(&buf[..322])).unwrap_err();
+        assert!(matches!(err, Error::Invalid(_)));
+        // The stream ends in the middle of obu_size
+        let err = scan_offsets(&mut Cursor::new(&buf[..22])).unwrap_err();
+        assert!(matches!(err, Error::Eof));
+    }
+}
@@ -0,0 +1,415 @@
+use alloc::collections::BTreeMap;
+use alloc::format;
+use alloc::string::ToString;
+use alloc::vec::Vec;
+use core::fmt;
+
+use crate::av1::obu::{iter_obus, Obu, ObuParser, ObuType, ParsedObu};
+use crate::av1::temporal_unit::TemporalUnit;
+use crate::av1::tile_group::TileGroupObu;
+use crate::Error;
+
+const FRAME_TYPE_NAMES: [&str; 4] = [
+    "KEY_FRAME",
+    "INTER_FRAME",
+    "INTRA_ONLY_FRAME",
+    "SWITCH_FRAME",
+];
+
+/// Aggregate statistics of a stream, accumulated one OBU at a time
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct StreamStats {
+    pub temporal_units: u64,
+    /// Decoded frames, indexed by frame_type
+    pub frames: [u64; 4],
+    pub show_existing_frames: u64,
+    pub tiles: u64,
+    /// The maximum frame size from the most recent sequence header
+    pub resolution: Option<(u32, u32)>,
+    /// Total bytes of each OBU type, including the OBU headers, in the order they were first seen
+    pub obu_bytes: Vec<(ObuType, u64)>,
+    /// Tiles of the current frame that haven't been seen yet, used to skip repeated frame headers
+    tiles_remaining: u32,
+}
+
+impl StreamStats {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Accumulates an OBU and the result of parsing it with an `ObuParser`
+    pub fn add(&mut self, obu: &Obu, parsed: &ParsedObu) {
+        let obu_type = obu.header.obu_type;
+        let len = obu.buf().len() as u64;
+        match self.obu_bytes.iter_mut().find(|(t, _)| *t == obu_type) {
+            Some((_, bytes)) => *bytes += len,
+            None => self.obu_bytes.push((obu_type, len)),
+        }
+
+        match parsed {
+            ParsedObu::TemporalDelimiter => self.temporal_units += 1,
+            ParsedObu::SequenceHeader(seq) => {
+                self.resolution = Some((
+                    seq.max_frame_width_minus_1 + 1,
+                    seq.max_frame_height_minus_1 + 1,
+                ));
+            }
+            ParsedObu::FrameHeader(frame_header)
+            | ParsedObu::RedundantFrameHeader(frame_header) => {
+                if frame_header.show_existing_frame {
+                    self.show_existing_frames += 1;
+                } else if self.tiles_remaining == 0 {
+                    self.frames[frame_header.frame_type as usize] += 1;
+                    self.tiles_remaining =
+                        frame_header.tile_info.tile_cols * frame_header.tile_info.tile_rows;
+                }
+            }
+            ParsedObu::TileGroup(tile_group) => self.add_tile_group(tile_group),
+            ParsedObu::Frame(frame_header, tile_group) => {
+                self.frames[frame_header.frame_type as usize] += 1;
+                self.tiles_remaining =
+                    frame_header.tile_info.tile_cols * frame_header.tile_info.tile_rows;
+                self.add_tile_group(tile_group);
+            }
+            _ => {}
+        }
+    }
+
+    fn add_tile_group(&mut self, tile_group: &TileGroupObu) {
+        let tiles = tile_group.tiles.len() as u32;
+        self.tiles += tiles as u64;
+        self.tiles_remaining = self.tiles_remaining.saturating_sub(tiles);
+    }
+
+    /// The number of decoded frames, excluding shown existing frames
+    pub fn total_frames(&self) -> u64 {
+        self.frames.iter().sum()
+    }
+
+    pub fn average_tiles(&self) -> f64 {
+        match self.total_frames() {
+            0 => 0.0,
+            frames => self.tiles as f64 / frames as f64,
+        }
+    }
+}
+
+impl fmt::Display for StreamStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{:<28} {:>10}", "temporal units", self.temporal_units)?;
+        match self.resolution {
+            Some((width, height)) => writeln!(
+                f,
+                "{:<28} {:>10}",
+                "resolution",
+                format!("{}x{}", width, height)
+            )?,
+            None => writeln!(f, "{:<28} {:>10}", "resolution", "unknown")?,
+        }
+        writeln!(f, "{:<28} {:>10}", "frames", self.total_frames())?;
+        for (name, count) in FRAME_TYPE_NAMES.iter().zip(self.frames) {
+            writeln!(f, "  {:<26} {:>10}", name, count)?;
+        }
+        writeln!(
+            f,
+            "  {:<26} {:>10}",
+            "show_existing_frame", self.show_existing_frames
+        )?;
+        writeln!(
+            f,
+            "{:<28} {:>10.2}",
+            "average tiles per frame",
+            self.average_tiles()
+        )?;
+        writeln!(
+            f,
+            "{:<28} {:>10}",
+            "bytes",
+            self.obu_bytes.iter().map(|(_, bytes)| bytes).sum::<u64>()
+        )?;
+        for (obu_type, bytes) in &self.obu_bytes {
+            writeln!(f, "  {:<26} {:>10}", obu_type.to_string(), bytes)?;
+        }
+        Ok(())
+    }
+}
+
+/// Decoded frames of each layer of a stream, accumulated one OBU at a time
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct LayerStats {
+    /// Decoded frames, excluding shown existing frames, keyed by (temporal_id, spatial_id)
+    pub frames: BTreeMap<(u32, u32), usize>,
+    /// Tiles of the current frame that haven't been seen yet, used to skip repeated frame headers
+    tiles_remaining: u32,
+}
+
+impl LayerStats {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Accumulates an OBU and the result of parsing it with an `ObuParser`
+    pub fn add(&mut self, obu: &Obu, parsed: &ParsedObu) {
+        let layer = (obu.temporal_id() as u32, obu.spatial_id() as u32);
+        match parsed {
+            ParsedObu::FrameHeader(frame_header)
+            | ParsedObu::RedundantFrameHeader(frame_header)
+                if !frame_header.show_existing_frame && self.tiles_remaining == 0 =>
+            {
+                *self.frames.entry(layer).or_default() += 1;
+                self.tiles_remaining =
+                    frame_header.tile_info.tile_cols * frame_header.tile_info.tile_rows;
+            }
+            ParsedObu::TileGroup(tile_group) => self.add_tile_group(tile_group),
+            ParsedObu::Frame(frame_header, tile_group) => {
+                *self.frames.entry(layer).or_default() += 1;
+                self.tiles_remaining =
+                    frame_header.tile_info.tile_cols * frame_header.tile_info.tile_rows;
+                self.add_tile_group(tile_group);
+            }
+            _ => {}
+        }
+    }
+
+    fn add_tile_group(&mut self, tile_group: &TileGroupObu) {
+        let tiles = tile_group.tiles.len() as u32;
+        self.tiles_remaining = self.tiles_remaining.saturating_sub(tiles);
+    }
+}
+
+/// Counts the decoded frames of each (temporal_id, spatial_id) layer of a low overhead
+/// bitstream
+pub fn layer_breakdown(buf: &[u8]) -> Result<BTreeMap<(u32, u32), usize>, Error> {
+    let mut parser = ObuParser::new();
+    let mut stats = LayerStats::new();
+    for obu in iter_obus(buf) {
+        let obu = obu?;
+        stats.add(&obu, &parser.parse(&obu)?);
+    }
+    Ok(stats.frames)
+}
+
+/// The statistics of a whole low overhead bitstream, from `parse_stream`
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct StreamSummary {
+    pub stats: StreamStats,
+    /// Decoded frames, excluding shown existing frames, keyed by (temporal_id, spatial_id)
+    pub layers: BTreeMap<(u32, u32), usize>,
+}
+
+/// Parses every OBU of a low overhead bitstream, stopping at the first error. This never
+/// panics, whatever the input, so it can be used as a fuzzing entry point.
+pub fn parse_stream(buf: &[u8]) -> Result<StreamSummary, Error> {
+    let mut parser = ObuParser::new();
+    let mut stats = StreamStats::new();
+    let mut layers = LayerStats::new();
+    for obu in iter_obus(buf) {
+        let obu = obu?;
+        let parsed = parser.parse(&obu)?;
+        stats.add(&obu, &parsed);
+        layers.add(&obu, &parsed);
+    }
+    Ok(StreamSummary {
+        stats,
+        layers: layers.frames,
+    })
+}
+
+/// The instantaneous bitrate in bits per second of each displayed frame, at `fps` frames per
+/// second
+///
+/// A temporal unit displays one frame (7.5), so there's a value for each unit. Only the
+/// payloads of frame and tile group OBUs count, as they hold the coded frame data, so a unit
+/// that only shows an existing frame has a bitrate of 0. `fps` has to be finite and positive.
+pub fn bitrate_profile(units: &[TemporalUnit], fps: f64) -> Result<Vec<f64>, Error> {
+    if !fps.is_finite() || fps <= 0.0 {
+        return Err(Error::Invalid("fps must be finite and positive"));
+    }
+
+    Ok(units
+        .iter()
+        .map(|unit| {
+            let bytes: usize = unit
+                .obus()
+                .iter()
+                .filter(|obu| matches!(obu.obu_type(), ObuType::Frame | ObuType::TileGroup))
+                .map(|obu| obu.payload().len())
+                .sum();
+            (bytes * 8) as f64 * fps
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::av1::obu::{iter_obus, ObuParser};
+    use crate::av1::temporal_unit::group_temporal_units;
+    use crate::av1::test_fixtures::{HIDDEN_INTER_FRAME_64X64, KEY_FRAME_64X64, SEQ_64X64};
+    use alloc::vec;
+
+    fn stream_stats(buf: &[u8]) -> StreamStats {
+        let mut parser = ObuParser::new();
+        let mut stats = StreamStats::new();
+        for obu in iter_obus(buf) {
+            let obu = obu.unwrap();
+            stats.add(&obu, &parser.parse(&obu).unwrap());
+        }
+        stats
+    }
+
+    #[test]
+    fn synthetic_stream() {
+        let mut buf = vec![0x12, 0x00, 0x0a, SEQ_64X64.len() as u8];
+        buf.extend_from_slice(&SEQ_64X64);
+        buf.extend_from_slice(&[0x32, KEY_FRAME_64X64.len() as u8]);
+        buf.extend_from_slice(&KEY_FRAME_64X64);
+
+        // A hidden inter frame with a repeated frame header, then shown with show_existing_frame
+        let mut frame_header = vec![0x1a, HIDDEN_INTER_FRAME_64X64.len() as u8];
+        frame_header.extend_from_slice(&HIDDEN_INTER_FRAME_64X64);
+        *frame_header.last_mut().unwrap() |= 0x08;
+        buf.extend_from_slice(&[0x12, 0x00]);
+        buf.extend_from_slice(&frame_header);
+        buf.extend_from_slice(&frame_header);
+        buf.extend_from_slice(&[0x22, 0x02, 0xa0, 0xa1]);
+        buf.extend_from_slice(&[0x1a, 0x01, 0b10011000]);
+
+        let stats = stream_stats(&buf);
+        assert_eq!(stats.temporal_units, 2);
+        assert_eq!(stats.resolution, Some((64, 64)));
+        assert_eq!(stats.frames, [1, 1, 0, 0]);
+        assert_eq!(stats.show_existing_frames, 1);
+        assert_eq!(stats.tiles, 2);
+        assert_eq!(stats.average_tiles(), 1.0);
+        assert_eq!(
+            stats.obu_bytes,
+            vec![
+                (ObuType::TemporalDelimiter, 4),
+                (ObuType::SequenceHeader, 12),
+                (ObuType::Frame, 28),
+                (ObuType::FrameHeader, 49),
+                (ObuType::TileGroup, 4),
+            ]
+        );
+    }
+
+    #[test]
+    fn layers() {
+        let mut buf = vec![0x12, 0x00, 0x0a, SEQ_64X64.len() as u8];
+        buf.extend_from_slice(&SEQ_64X64);
+        // The same key frame coded in different layers, with and without extension headers
+        for extension in [None, Some(0x00), Some(0x20), Some(0x08), Some(0x00)] {
+            match extension {
+                Some(extension) => buf.extend_from_slice(&[0x36, extension]),
+                None => buf.push(0x32),
+            }
+            buf.push(KEY_FRAME_64X64.len() as u8);
+            buf.extend_from_slice(&KEY_FRAME_64X64);
+        }
+        // A shown existing frame isn't counted
+        buf.extend_from_slice(&[0x1e, 0x20, 0x01, 0b10001000]);
+
+        let layers = layer_breakdown(&buf).unwrap();
+        assert_eq!(
+            layers.into_iter().collect::<Vec<_>>(),
+            [((0, 0), 3), ((0, 1), 1), ((1, 0), 1)]
+        );
+    }
+
+    #[test]
+    fn parse_synthetic_stream() {
+        let mut buf = vec![0x12, 0x00, 0x0a, SEQ_64X64.len() as u8];
+        buf.extend_from_slice(&SEQ_64X64);
+        buf.extend_from_slice(&[0x32, KEY_FRAME_64X64.len() as u8]);
+        buf.extend_from_slice(&KEY_FRAME_64X64);
+
+        let summary = parse_stream(&buf).unwrap();
+        assert_eq!(summary.stats, stream_stats(&buf));
+        assert_eq!(
+            summary.layers.into_iter().collect::<Vec<_>>(),
+            [((0, 0), 1)]
+        );
+    }
+
+    #[test]
+    fn parse_stream_errors() {
+        let mut buf = vec![0x12, 0x00, 0x0a, SEQ_64X64.len() as u8];
+        buf.extend_from_slice(&SEQ_64X64);
+        buf.extend_from_slice(&[0x32, KEY_FRAME_64X64.len() as u8]);
+        buf.extend_from_slice(&KEY_FRAME_64X64);
+        let obu_ends = [2, 2 + 2 + SEQ_64X64.len(), buf.len()];
+
+        // Truncated anywhere but an OBU boundary
+        for len in (1..buf.len()).filter(|len| !obu_ends.contains(len)) {
+            assert!(parse_stream(&buf[..len]).is_err(), "truncated to {}", len);
+        }
+
+        let mut corpus: Vec<Vec<u8>> = vec![
+            // obu_forbidden_bit, and reserved OBU types
+            vec![0x80],
+            vec![0x4a, 0x00],
+            // obu_size that doesn't terminate, or runs past the buffer
+            vec![0x12, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff],
+            vec![0x12, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x7f],
+            // A frame before any sequence header, and a tile group before any frame header
+            vec![0x32, 0x01, 0x10],
+            vec![0x22, 0x01, 0x00],
+            // A sequence header of all ones, and one that ends early
+            vec![
+                0x0a, 0x09, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+            ],
+            vec![0x0a, 0x03, 0x00, 0x00, 0x00],
+        ];
+        // A frame OBU showing an existing frame, which has no tiles
+        let mut show_existing = buf[..obu_ends[1]].to_vec();
+        show_existing.extend_from_slice(&[0x32, 0x01, 0b10000000]);
+        corpus.push(show_existing);
+        // An inter frame taking its size from a reference that was never decoded
+        let mut invalid_ref = buf[..obu_ends[1]].to_vec();
+        invalid_ref.extend_from_slice(&[0x32, 0x06, 0x37, 0x26, 0x9c, 0x22, 0x8e, 0x8e]);
+        corpus.push(invalid_ref);
+
+        for input in &corpus {
+            assert!(parse_stream(input).is_err(), "{:02x?}", input);
+        }
+    }
+
+    #[test]
+    fn empty_stream() {
+        let stats = stream_stats(&[]);
+        assert_eq!(stats.total_frames(), 0);
+        assert_eq!(stats.average_tiles(), 0.0);
+        assert_eq!(stats.resolution, None);
+    }
+
+    #[test]
+    fn bitrate_per_temporal_unit() {
+        let mut buf = vec![0x12, 0x00, 0x0a, SEQ_64X64.len() as u8];
+        buf.extend_from_slice(&SEQ_64X64);
+        buf.extend_from_slice(&[0x32, KEY_FRAME_64X64.len() as u8]);
+        buf.extend_from_slice(&KEY_FRAME_64X64);
+        // A unit only showing an existing frame, then a frame header with a separate tile group
+        // and padding
+        buf.extend_from_slice(&[0x12, 0x00, 0x1a, 0x01, 0b10001000]);
+        buf.extend_from_slice(&[0x12, 0x00, 0x1a, HIDDEN_INTER_FRAME_64X64.len() as u8]);
+        buf.extend_from_slice(&HIDDEN_INTER_FRAME_64X64);
+        buf.extend_from_slice(&[0x22, 0x02, 0xa0, 0xa1, 0x7a, 0x02, 0xff, 0xff]);
+
+        let units = group_temporal_units(&buf).unwrap();
+        assert_eq!(units.len(), 3);
+        assert_eq!(
+            bitrate_profile(&units, 30.0).unwrap(),
+            [26.0 * 8.0 * 30.0, 0.0, 2.0 * 8.0 * 30.0]
+        );
+        assert_eq!(bitrate_profile(&units[2..], 24.0).unwrap(), [384.0]);
+        assert!(bitrate_profile(&[], 30.0).unwrap().is_empty());
+
+        for fps in [0.0, -30.0, f64::NAN, f64::INFINITY] {
+            assert!(matches!(
+                bitrate_profile(&units, fps),
+                Err(Error::Invalid(_))
+            ));
+        }
+    }
+}
@@ -0,0 +1,201 @@
+use alloc::vec::Vec;
+
+use crate::av1::obu::{parse_header, Obu, OwnedObu, ParseOptions};
+use crate::bits::BitstreamReader;
+use crate::Error;
+
+/// Splits a low overhead bitstream that arrives in chunks of any size into OBUs, buffering
+/// partial OBUs until the rest of them is pushed
+///
+/// Every OBU needs obu_size, as without it there's no telling where an OBU ends.
+#[derive(Debug, Default)]
+pub struct StreamingObuParser {
+    options: ParseOptions,
+    buf: Vec<u8>,
+    /// Offset in `buf` of the first byte that isn't part of a returned OBU
+    start: usize,
+    /// Set after an error, as there's no way to resynchronize after a malformed OBU
+    failed: bool,
+}
+
+impl StreamingObuParser {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_options(options: ParseOptions) -> Self {
+        Self {
+            options,
+            ..Default::default()
+        }
+    }
+
+    /// Appends the next chunk of the stream
+    pub fn push(&mut self, bytes: &[u8]) {
+        if !self.failed {
+            self.buf.extend_from_slice(bytes);
+        }
+    }
+
+    /// Returns the next OBU once all of it has been pushed
+    pub fn poll_obu(&mut self) -> Option<Result<OwnedObu, Error>> {
+        if self.failed {
+            return None;
+        }
+
+        let len = match self.next_obu_len() {
+            Ok(Some(len)) if len <= self.buffered() => len,
+            Ok(_) => return None,
+            Err(err) => return Some(Err(self.fail(err))),
+        };
+
+        let end = self.start + len;
+        let obu = match Obu::from_buf_with_options(&self.buf[self.start..end], &self.options) {
+            Ok(obu) => OwnedObu::from(obu),
+            Err(err) => return Some(Err(self.fail(err))),
+        };
+        self.start = end;
+        // Returned OBUs are only dropped from the buffer once they're half of it, so that many
+        // small OBUs don't each move everything after them
+        if self.start > self.buf.len() / 2 {
+            self.buf.drain(..self.start);
+            self.start = 0;
+        }
+        Some(Ok(obu))
+    }
+
+    /// The number of bytes buffered that aren't part of a returned OBU
+    pub fn buffered(&self) -> usize {
+        self.buf.len() - self.start
+    }
+
+    /// The length of the OBU at the start of the buffer, or None if its header and size field
+    /// haven't all been pushed yet
+    fn next_obu_len(&self) -> Result<Option<usize>, Error> {
+        if self.buffered() == 0 {
+            return Ok(None);
+        }
+
+        let mut reader = BitstreamReader::from_slice(&self.buf[self.start..]);
+        let header = match parse_header(&mut reader, &self.options) {
+            Ok(header) => header,
+            Err(Error::Eof) => return Ok(None),
+            Err(err) => return Err(err),
+        };
+        if !header.obu_has_size_field {
+            return Err(Error::Unsupported("streamed obu without a size field"));
+        }
+        let obu_size = match reader.leb128() {
+            Ok(obu_size) => obu_size,
+            Err(Error::Eof) => return Ok(None),
+            Err(err) => return Err(err),
+        };
+
+        // Checked before the payload arrives, so an oversized OBU isn't buffered
+        match usize::try_from(obu_size) {
+            Ok(obu_size) if obu_size <= self.options.max_obu_size => {
+                Ok(Some(reader.byte_position() as usize + obu_size))
+            }
+            _ => Err(Error::Invalid("obu_size exceeds max_obu_size")),
+        }
+    }
+
+    fn fail(&mut self, err: Error) -> Error {
+        self.failed = true;
+        self.buf = Vec::new();
+        self.start = 0;
+        err
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::av1::obu::{iter_obus, ObuType};
+    use crate::av1::test_fixtures::SEQ_64X64;
+    use alloc::vec;
+
+    fn test_stream() -> Vec<u8> {
+        let mut buf = vec![0x12, 0x00, 0x0a, SEQ_64X64.len() as u8];
+        buf.extend_from_slice(&SEQ_64X64);
+        // Metadata with an extension header, and padding with a two byte obu_size
+        buf.extend_from_slice(&[0x2e, 0x28, 0x03, 0x01, 0x00, 0x10]);
+        buf.extend_from_slice(&[0x7a, 0xc8, 0x01]);
+        buf.extend((0..200).map(|i| i as u8));
+        buf.extend_from_slice(&[0x12, 0x00]);
+        buf
+    }
+
+    fn parse_in_chunks(buf: &[u8], chunk: usize) -> Vec<OwnedObu> {
+        let mut parser = StreamingObuParser::new();
+        let mut obus = Vec::new();
+        for bytes in buf.chunks(chunk) {
+            parser.push(bytes);
+            while let Some(obu) = parser.poll_obu() {
+                obus.push(obu.unwrap());
+            }
+        }
+        assert_eq!(parser.buffered(), 0);
+        obus
+    }
+
+    #[test]
+    fn chunk_sizes() {
+        let buf = test_stream();
+        let expected: Vec<OwnedObu> = iter_obus(&buf)
+            .map(|obu| OwnedObu::from(obu.unwrap()))
+            .collect();
+        assert_eq!(expected.len(), 5);
+
+        let one_byte = parse_in_chunks(&buf, 1);
+        assert_eq!(one_byte, expected);
+        assert_eq!(parse_in_chunks(&buf, 7), expected);
+        assert_eq!(parse_in_chunks(&buf, 4096), expected);
+
+        assert_eq!(one_byte[2].header.extension.unwrap().temporal_id, 1);
+        assert_eq!(one_byte[3].obu_type(), ObuType::Padding);
+        assert_eq!(one_byte[3].payload().len(), 200);
+        assert_eq!(one_byte[3].as_obu().buf(), &buf[20..223]);
+    }
+
+    #[test]
+    fn partial_obu() {
+        let mut parser = StreamingObuParser::new();
+        // The header, then the first byte of a two byte obu_size
+        parser.push(&[0x7a]);
+        assert!(parser.poll_obu().is_none());
+        parser.push(&[0x80]);
+        assert!(parser.poll_obu().is_none());
+        parser.push(&[0x01]);
+        parser.push(&[0xff; 127]);
+        assert!(parser.poll_obu().is_none());
+        parser.push(&[0xff, 0x12]);
+
+        let obu = parser.poll_obu().unwrap().unwrap();
+        assert_eq!(obu.payload(), &[0xff; 128]);
+        assert!(parser.poll_obu().is_none());
+        assert_eq!(parser.buffered(), 1);
+    }
+
+    #[test]
+    fn errors() {
+        let mut parser = StreamingObuParser::new();
+        parser.push(&[0x12, 0x00, 0x10]);
+        assert!(parser.poll_obu().unwrap().is_ok());
+        assert!(matches!(
+            parser.poll_obu(),
+            Some(Err(Error::Unsupported(_)))
+        ));
+        // Nothing more is parsed after an error
+        parser.push(&[0x12, 0x00]);
+        assert!(parser.poll_obu().is_none());
+
+        let mut parser = StreamingObuParser::with_options(ParseOptions {
+            max_obu_size: 16,
+            ..Default::default()
+        });
+        parser.push(&[0x7a, 0x11]);
+        assert!(matches!(parser.poll_obu(), Some(Err(Error::Invalid(_)))));
+        assert_eq!(parser.buffered(), 0);
+    }
+}
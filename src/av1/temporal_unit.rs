@@ -0,0 +1,150 @@
+use alloc::vec;
+use alloc::vec::Vec;
+use core::ops::Range;
+
+use crate::av1::obu::{iter_obus, Obu, ObuType};
+use crate::Error;
+
+/// A temporal delimiter and the OBUs that follow it, up to the next temporal delimiter - 7.5
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TemporalUnit<'a> {
+    pub(crate) obus: Vec<Obu<'a>>,
+}
+
+impl<'a> TemporalUnit<'a> {
+    /// The OBUs of the temporal unit, starting with its temporal delimiter
+    pub fn obus(&self) -> &[Obu<'a>] {
+        &self.obus
+    }
+}
+
+/// Splits a buffer of low overhead bitstream format OBUs into temporal units
+pub fn group_temporal_units(buf: &[u8]) -> Result<Vec<TemporalUnit<'_>>, Error> {
+    let mut temporal_units: Vec<TemporalUnit> = Vec::new();
+    for obu in iter_obus(buf) {
+        let obu = obu?;
+        if obu.header.obu_type == ObuType::TemporalDelimiter {
+            temporal_units.push(TemporalUnit { obus: vec![obu] });
+            continue;
+        }
+
+        match temporal_units.last_mut() {
+            Some(temporal_unit) => temporal_unit.obus.push(obu),
+            None => {
+                return Err(Error::Invalid(
+                    "bitstream doesn't start with a temporal delimiter",
+                ))
+            }
+        }
+    }
+
+    Ok(temporal_units)
+}
+
+/// The byte range of each temporal unit in a buffer of low overhead bitstream format OBUs, from
+/// its temporal delimiter up to the next one
+///
+/// These are the units to hand to a decoder one at a time. The ranges cover the whole buffer.
+pub fn frame_ranges(buf: &[u8]) -> Result<Vec<Range<usize>>, Error> {
+    let mut ranges: Vec<Range<usize>> = Vec::new();
+    let mut offset = 0;
+    for obu in iter_obus(buf) {
+        let obu = obu?;
+        let end = offset + obu.buf().len();
+        if obu.header.obu_type == ObuType::TemporalDelimiter {
+            ranges.push(offset..end);
+        } else {
+            match ranges.last_mut() {
+                Some(range) => range.end = end,
+                None => {
+                    return Err(Error::Invalid(
+                        "bitstream doesn't start with a temporal delimiter",
+                    ))
+                }
+            }
+        }
+        offset = end;
+    }
+
+    Ok(ranges)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn three_temporal_units() {
+        const TEST_BUF: [u8; 17] = [
+            0x12, 0x00, 0x0a, 0x02, 0x00, 0x00, 0x7a, 0x01, 0xff, 0x12, 0x00, 0x7a, 0x02, 0xff,
+            0xff, 0x12, 0x00,
+        ];
+
+        let temporal_units = group_temporal_units(&TEST_BUF).unwrap();
+        assert_eq!(temporal_units.len(), 3);
+
+        let obu_types = |temporal_unit: &TemporalUnit| -> Vec<ObuType> {
+            temporal_unit
+                .obus()
+                .iter()
+                .map(|obu| obu.header.obu_type)
+                .collect()
+        };
+        assert_eq!(
+            obu_types(&temporal_units[0]),
+            vec![
+                ObuType::TemporalDelimiter,
+                ObuType::SequenceHeader,
+                ObuType::Padding,
+            ]
+        );
+        assert_eq!(
+            obu_types(&temporal_units[1]),
+            vec![ObuType::TemporalDelimiter, ObuType::Padding]
+        );
+        assert_eq!(temporal_units[1].obus()[1].payload(), &[0xff, 0xff]);
+        assert_eq!(
+            obu_types(&temporal_units[2]),
+            vec![ObuType::TemporalDelimiter]
+        );
+    }
+
+    #[test]
+    fn missing_temporal_delimiter() {
+        const TEST_BUF: [u8; 5] = [0x7a, 0x01, 0xff, 0x12, 0x00];
+
+        let err = group_temporal_units(&TEST_BUF).unwrap_err();
+        assert!(matches!(err, Error::Invalid(_)));
+    }
+
+    #[test]
+    fn empty_buffer() {
+        assert!(group_temporal_units(&[]).unwrap().is_empty());
+    }
+
+    #[test]
+    fn frame_ranges_tile_buffer() {
+        const TEST_BUF: [u8; 17] = [
+            0x12, 0x00, 0x0a, 0x02, 0x00, 0x00, 0x7a, 0x01, 0xff, 0x12, 0x00, 0x7a, 0x02, 0xff,
+            0xff, 0x12, 0x00,
+        ];
+
+        let ranges = frame_ranges(&TEST_BUF).unwrap();
+        assert_eq!(ranges, vec![0..9, 9..15, 15..17]);
+        assert_eq!(ranges[0].start, 0);
+        assert_eq!(ranges[2].end, TEST_BUF.len());
+        for pair in ranges.windows(2) {
+            assert_eq!(pair[0].end, pair[1].start);
+        }
+
+        let temporal_units = group_temporal_units(&TEST_BUF).unwrap();
+        for (range, temporal_unit) in ranges.iter().zip(&temporal_units) {
+            let len: usize = temporal_unit.obus().iter().map(|obu| obu.buf().len()).sum();
+            assert_eq!(range.len(), len);
+        }
+
+        let err = frame_ranges(&TEST_BUF[6..]).unwrap_err();
+        assert!(matches!(err, Error::Invalid(_)));
+        assert!(frame_ranges(&[]).unwrap().is_empty());
+    }
+}
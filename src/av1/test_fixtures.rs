@@ -0,0 +1,17 @@
+// Payloads shared by the tests of several modules
+
+// The sequence header and frame headers of a 64x64 rav1e encode with frame reordering
+pub(crate) const SEQ_64X64: [u8; 10] = [0x00, 0x00, 0x00, 0xf9, 0x57, 0xff, 0xc4, 0x21, 0x50, 0x14];
+// The first frame header, without its tile group
+pub(crate) const KEY_FRAME_64X64: [u8; 26] = [
+    0x10, 0x02, 0xaf, 0x1d, 0x89, 0xf8, 0xe9, 0xdf, 0x7f, 0xc0, 0x1f, 0xe0, 0x08, 0x38, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x51, 0x45, 0x14, 0x10, 0x41, 0x10,
+];
+pub(crate) const HIDDEN_INTER_FRAME_64X64: [u8; 21] = [
+    0x28, 0x11, 0x01, 0x03, 0x00, 0x00, 0x02, 0xdb, 0x1d, 0x81, 0xf5, 0xe7, 0xd9, 0x60, 0x51, 0x45,
+    0x14, 0x10, 0x40, 0x28, 0x00,
+];
+
+// A reduced still picture sequence header
+#[cfg(feature = "std")]
+pub(crate) const SEQ_STILL: [u8; 6] = [0x1f, 0xd5, 0x7f, 0xfc, 0xa0, 0x28];
@@ -0,0 +1,298 @@
+use alloc::vec::Vec;
+
+use crate::av1::frame_header::TileInfo;
+use crate::bits::BitstreamReader;
+use crate::Error;
+
+/// The location of one tile's data within a tile group
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Tile {
+    /// TileNum, the index of the tile in raster order within the frame
+    pub tile_num: u32,
+    /// Offset of the tile data from the start of the tile group
+    pub offset: usize,
+    pub len: usize,
+}
+
+/// tile_group_obu() - 5.11.1, without the decoded tile data
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct TileGroupObu {
+    pub tile_start_and_end_present_flag: bool,
+    pub tg_start: u32,
+    pub tg_end: u32,
+    pub tiles: Vec<Tile>,
+}
+
+impl TileGroupObu {
+    /// Whether this tile group contains the last tile of the frame, which is never the case for
+    /// a `TileInfo` without any tiles
+    pub fn is_last(&self, tile_info: &TileInfo) -> bool {
+        self.tg_end as u64 + 1 == tile_info.tile_cols as u64 * tile_info.tile_rows as u64
+    }
+}
+
+/// tile_group_obu() - 5.11.1
+///
+/// `buf` is the tile group, which for OBU_FRAME starts after the byte aligned frame header.
+pub fn parse_tile_group(buf: &[u8], tile_info: &TileInfo) -> Result<TileGroupObu, Error> {
+    let num_tiles = tile_info.tile_cols * tile_info.tile_rows;
+    if num_tiles == 0 {
+        return Err(Error::Invalid("tile group without any tiles"));
+    }
+
+    let mut reader = BitstreamReader::from_slice(buf);
+
+    let mut tile_group = TileGroupObu {
+        tg_end: num_tiles - 1,
+        ..Default::default()
+    };
+    if num_tiles > 1 {
+        tile_group.tile_start_and_end_present_flag = reader.f1()?;
+    }
+    if tile_group.tile_start_and_end_present_flag {
+        let tile_bits = (tile_info.tile_cols_log2 + tile_info.tile_rows_log2) as u8;
+        tile_group.tg_start = reader.f(tile_bits)?;
+        tile_group.tg_end = reader.f(tile_bits)?;
+        if tile_group.tg_start > tile_group.tg_end || tile_group.tg_end >= num_tiles {
+            return Err(Error::Invalid("tile group range outside of the frame"));
+        }
+    }
+    reader.byte_alignment()?;
+
+    for tile_num in tile_group.tg_start..=tile_group.tg_end {
+        let last_tile = tile_num == tile_group.tg_end;
+        let len = if last_tile {
+            buf.len() - reader.byte_position() as usize
+        } else {
+            let tile_size_minus_1 = reader.le(tile_info.tile_size_bytes)?;
+            tile_size_minus_1 as usize + 1
+        };
+
+        let offset = reader.byte_position() as usize;
+        if len > buf.len() - offset {
+            return Err(Error::Invalid("tile size exceeds the tile group"));
+        }
+        tile_group.tiles.push(Tile {
+            tile_num,
+            offset,
+            len,
+        });
+        if !last_tile {
+            reader.skip_bits(len as u64 * 8)?;
+        }
+    }
+
+    Ok(tile_group)
+}
+
+/// A tile's position in the frame, and where its data is in a tile group
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct TileEntry {
+    pub row: u32,
+    pub col: u32,
+    /// Offset of the tile data from the start of the tile group
+    pub offset: usize,
+    pub size: usize,
+}
+
+/// Locates the data of each tile of a tile group, for jumping straight to any one of them
+pub fn index_tiles(payload: &[u8], info: &TileInfo) -> Result<Vec<TileEntry>, Error> {
+    let tile_group = parse_tile_group(payload, info)?;
+    Ok(tile_group
+        .tiles
+        .iter()
+        .map(|tile| TileEntry {
+            row: tile.tile_num / info.tile_cols,
+            col: tile.tile_num % info.tile_cols,
+            offset: tile.offset,
+            size: tile.len,
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec;
+
+    fn two_tiles() -> TileInfo {
+        TileInfo {
+            uniform_tile_spacing_flag: true,
+            tile_cols: 2,
+            tile_rows: 1,
+            tile_cols_log2: 1,
+            tile_rows_log2: 0,
+            mi_col_starts: vec![0, 16, 32],
+            mi_row_starts: vec![0, 16],
+            context_update_tile_id: 0,
+            tile_size_bytes: 2,
+        }
+    }
+
+    #[test]
+    fn two_tile_group() {
+        // tile_start_and_end_present_flag = 0, tile_size_minus_1 = 3
+        const TEST_BUF: [u8; 12] = [
+            0x00, 0x03, 0x00, 0xa0, 0xa1, 0xa2, 0xa3, 0xb0, 0xb1, 0xb2, 0xb3, 0xb4,
+        ];
+
+        let tile_group = parse_tile_group(&TEST_BUF, &two_tiles()).unwrap();
+        assert!(!tile_group.tile_start_and_end_present_flag);
+        assert_eq!((tile_group.tg_start, tile_group.tg_end), (0, 1));
+        assert!(tile_group.is_last(&two_tiles()));
+        assert_eq!(
+            tile_group.tiles,
+            vec![
+                Tile {
+                    tile_num: 0,
+                    offset: 3,
+                    len: 4,
+                },
+                Tile {
+                    tile_num: 1,
+                    offset: 7,
+                    len: 5,
+                },
+            ]
+        );
+
+        // The header, tile size and tile data cover the whole payload
+        let tile_bytes: usize = tile_group.tiles.iter().map(|tile| tile.len).sum();
+        assert_eq!(1 + 2 + tile_bytes, TEST_BUF.len());
+        assert_eq!(&TEST_BUF[7..], &[0xb0, 0xb1, 0xb2, 0xb3, 0xb4]);
+    }
+
+    #[test]
+    fn tile_group_start_and_end() {
+        // tile_start_and_end_present_flag = 1, tg_start = 1, tg_end = 1
+        const TEST_BUF: [u8; 3] = [0b11100000, 0xb0, 0xb1];
+
+        let tile_group = parse_tile_group(&TEST_BUF, &two_tiles()).unwrap();
+        assert!(tile_group.tile_start_and_end_present_flag);
+        assert_eq!((tile_group.tg_start, tile_group.tg_end), (1, 1));
+        assert_eq!(
+            tile_group.tiles,
+            vec![Tile {
+                tile_num: 1,
+                offset: 1,
+                len: 2,
+            }]
+        );
+    }
+
+    #[test]
+    fn single_tile_group() {
+        const TEST_BUF: [u8; 3] = [0xa0, 0xa1, 0xa2];
+
+        let tile_info = TileInfo {
+            tile_cols: 1,
+            tile_rows: 1,
+            ..Default::default()
+        };
+        let tile_group = parse_tile_group(&TEST_BUF, &tile_info).unwrap();
+        assert_eq!(
+            tile_group.tiles,
+            vec![Tile {
+                tile_num: 0,
+                offset: 0,
+                len: 3,
+            }]
+        );
+    }
+
+    #[test]
+    fn index_2x2_tiles() {
+        // tile_start_and_end_present_flag = 0, then tiles of 2, 3, 1 and 4 bytes with a one byte
+        // tile_size_minus_1 before all but the last
+        const TEST_BUF: [u8; 14] = [
+            0x00, 0x01, 0xa0, 0xa1, 0x02, 0xb0, 0xb1, 0xb2, 0x00, 0xc0, 0xd0, 0xd1, 0xd2, 0xd3,
+        ];
+
+        let tile_info = TileInfo {
+            uniform_tile_spacing_flag: true,
+            tile_cols: 2,
+            tile_rows: 2,
+            tile_cols_log2: 1,
+            tile_rows_log2: 1,
+            mi_col_starts: vec![0, 16, 32],
+            mi_row_starts: vec![0, 16, 32],
+            context_update_tile_id: 0,
+            tile_size_bytes: 1,
+        };
+        let tiles = index_tiles(&TEST_BUF, &tile_info).unwrap();
+        assert_eq!(
+            tiles
+                .iter()
+                .map(|tile| (tile.row, tile.col, tile.offset, tile.size))
+                .collect::<Vec<_>>(),
+            [(0, 0, 2, 2), (0, 1, 5, 3), (1, 0, 9, 1), (1, 1, 10, 4)]
+        );
+
+        // Each tile ends at the next tile's size field, or the last tile which has none, and the
+        // last tile ends at the end of the payload
+        let last = tiles.last().unwrap();
+        for pair in tiles.windows(2) {
+            let size_field = if pair[1] == *last { 0 } else { 1 };
+            assert_eq!(pair[0].offset + pair[0].size + size_field, pair[1].offset);
+        }
+        assert_eq!(last.offset + last.size, TEST_BUF.len());
+        assert_eq!(
+            &TEST_BUF[tiles[1].offset..][..tiles[1].size],
+            &[0xb0, 0xb1, 0xb2]
+        );
+    }
+
+    #[test]
+    fn index_single_tile() {
+        // No tile_start_and_end_present_flag or size fields with only one tile
+        const TEST_BUF: [u8; 3] = [0xa0, 0xa1, 0xa2];
+
+        let tile_info = TileInfo {
+            tile_cols: 1,
+            tile_rows: 1,
+            ..Default::default()
+        };
+        assert_eq!(
+            index_tiles(&TEST_BUF, &tile_info).unwrap(),
+            [TileEntry {
+                row: 0,
+                col: 0,
+                offset: 0,
+                size: 3,
+            }]
+        );
+    }
+
+    #[test]
+    fn tile_size_overrun() {
+        // tile_size_minus_1 = 0x100
+        const TEST_BUF: [u8; 5] = [0x00, 0x00, 0x01, 0xa0, 0xb0];
+
+        let err = parse_tile_group(&TEST_BUF, &two_tiles()).unwrap_err();
+        assert!(matches!(err, Error::Invalid(_)));
+    }
+
+    #[test]
+    fn tile_group_range_outside_frame() {
+        // tile_start_and_end_present_flag = 1, tg_start = 1, tg_end = 0
+        const TEST_BUF: [u8; 2] = [0b11000000, 0xb0];
+
+        let err = parse_tile_group(&TEST_BUF, &two_tiles()).unwrap_err();
+        assert!(matches!(err, Error::Invalid(_)));
+    }
+
+    #[test]
+    fn is_last_without_tiles() {
+        let tile_group = TileGroupObu::default();
+        assert!(!tile_group.is_last(&TileInfo::default()));
+        assert!(tile_group.is_last(&TileInfo {
+            tile_cols: 1,
+            tile_rows: 1,
+            ..Default::default()
+        }));
+        assert!(!tile_group.is_last(&two_tiles()));
+    }
+}
@@ -0,0 +1,147 @@
+use alloc::vec::Vec;
+
+use crate::av1::obu::ParseOptions;
+use crate::bits::BitstreamReader;
+use crate::Error;
+
+/// The largest tile_count_minus_1 allowed - 6.12.1
+const MAX_TILE_COUNT_MINUS_1: u32 = 511;
+
+/// tile_list_entry() - 5.12.2, with the coded tile data left as is
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct TileListEntry<'a> {
+    pub anchor_frame_idx: u8,
+    pub anchor_tile_row: u8,
+    pub anchor_tile_col: u8,
+    /// coded_tile_data, tile_data_size_minus_1 + 1 bytes
+    pub coded_tile_data: &'a [u8],
+}
+
+/// tile_list_obu() - 5.12.1, for large scale tile decoding
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct TileListObu<'a> {
+    pub output_frame_width_in_tiles_minus_1: u8,
+    pub output_frame_height_in_tiles_minus_1: u8,
+    pub tile_count_minus_1: u16,
+    pub entries: Vec<TileListEntry<'a>>,
+}
+
+/// tile_list_obu() - 5.12.1
+///
+/// tile_count_minus_1 is checked against `options.max_tiles` before any entries are parsed.
+pub fn parse_tile_list<'a>(
+    buf: &'a [u8],
+    options: &ParseOptions,
+) -> Result<TileListObu<'a>, Error> {
+    parse_tile_list_with_end(buf, options).map(|(tile_list, _)| tile_list)
+}
+
+/// tile_list_obu() - 5.12.1, also returning the number of bytes it takes up
+pub(crate) fn parse_tile_list_with_end<'a>(
+    buf: &'a [u8],
+    options: &ParseOptions,
+) -> Result<(TileListObu<'a>, usize), Error> {
+    let mut reader = BitstreamReader::from_slice(buf);
+    let mut tile_list = TileListObu {
+        output_frame_width_in_tiles_minus_1: reader.f(8)? as u8,
+        output_frame_height_in_tiles_minus_1: reader.f(8)? as u8,
+        tile_count_minus_1: reader.f(16)? as u16,
+        entries: Vec::new(),
+    };
+
+    let tile_count_minus_1 = tile_list.tile_count_minus_1 as u32;
+    if tile_count_minus_1 > MAX_TILE_COUNT_MINUS_1 {
+        return Err(Error::Invalid("tile_count_minus_1 is greater than 511"));
+    }
+    if tile_count_minus_1 as usize >= options.max_tiles {
+        return Err(Error::Invalid("tile count exceeds max_tiles"));
+    }
+
+    tile_list.entries.reserve(tile_count_minus_1 as usize + 1);
+    for _ in 0..=tile_count_minus_1 {
+        // tile_list_entry() - 5.12.2
+        let anchor_frame_idx = reader.f(8)? as u8;
+        let anchor_tile_row = reader.f(8)? as u8;
+        let anchor_tile_col = reader.f(8)? as u8;
+        let len = reader.f(16)? as usize + 1;
+
+        let offset = reader.byte_position() as usize;
+        if len > buf.len() - offset {
+            return Err(Error::Invalid(
+                "tile_data_size_minus_1 exceeds the tile list",
+            ));
+        }
+        tile_list.entries.push(TileListEntry {
+            anchor_frame_idx,
+            anchor_tile_row,
+            anchor_tile_col,
+            coded_tile_data: &buf[offset..offset + len],
+        });
+        reader.skip_bits(len as u64 * 8)?;
+    }
+
+    Ok((tile_list, reader.byte_position() as usize))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec;
+
+    #[test]
+    fn two_entries() {
+        // A 2x1 tile output frame from anchor frames 0 and 3, with 3 and 1 bytes of tile data
+        const TEST_BUF: [u8; 18] = [
+            0x01, 0x00, 0x00, 0x01, 0x00, 0x02, 0x05, 0x00, 0x02, 0xa0, 0xa1, 0xa2, 0x03, 0x01,
+            0x00, 0x00, 0x00, 0xb0,
+        ];
+
+        let tile_list = parse_tile_list(&TEST_BUF, &ParseOptions::default()).unwrap();
+        assert_eq!(tile_list.output_frame_width_in_tiles_minus_1, 1);
+        assert_eq!(tile_list.output_frame_height_in_tiles_minus_1, 0);
+        assert_eq!(tile_list.tile_count_minus_1, 1);
+        assert_eq!(
+            tile_list.entries,
+            vec![
+                TileListEntry {
+                    anchor_frame_idx: 0,
+                    anchor_tile_row: 2,
+                    anchor_tile_col: 5,
+                    coded_tile_data: &[0xa0, 0xa1, 0xa2],
+                },
+                TileListEntry {
+                    anchor_frame_idx: 3,
+                    anchor_tile_row: 1,
+                    anchor_tile_col: 0,
+                    coded_tile_data: &[0xb0],
+                },
+            ]
+        );
+
+        // The second entry's data cut short
+        let err = parse_tile_list(&TEST_BUF[..17], &ParseOptions::default()).unwrap_err();
+        assert!(matches!(err, Error::Invalid(_)));
+    }
+
+    #[test]
+    fn tile_count_limits() {
+        // tile_count_minus_1 = 512 and 0xffff, without any entries following
+        let err = parse_tile_list(&[0x00, 0x00, 0x02, 0x00], &ParseOptions::default());
+        assert!(matches!(err, Err(Error::Invalid(_))));
+        let err = parse_tile_list(&[0x00, 0x00, 0xff, 0xff], &ParseOptions::default());
+        assert!(matches!(err, Err(Error::Invalid(_))));
+
+        // tile_count_minus_1 = 2 with max_tiles = 2
+        const TEST_BUF: [u8; 4] = [0x00, 0x00, 0x00, 0x02];
+        let options = ParseOptions {
+            max_tiles: 2,
+            ..Default::default()
+        };
+        let err = parse_tile_list(&TEST_BUF, &options).unwrap_err();
+        assert!(matches!(err, Error::Invalid(_)));
+        let err = parse_tile_list(&TEST_BUF, &ParseOptions::default()).unwrap_err();
+        assert!(matches!(err, Error::Eof));
+    }
+}
@@ -1,75 +1,277 @@
-use std::io::Read;
+use alloc::vec::Vec;
+#[cfg(feature = "std")]
+use std::io::{Cursor, Read, Seek, SeekFrom};
 
-pub struct BitstreamReader<'a, T> {
-    reader: &'a mut T,
+use crate::Error;
+
+/// The bytes a `BitstreamReader` reads from. With the `std` feature this is implemented for
+/// every `Read`, otherwise for byte slices.
+pub trait ByteRead {
+    /// Reads up to `buf.len()` bytes into `buf`, returning how many were read - 0 only at the
+    /// end of the source
+    fn read_bytes(&mut self, buf: &mut [u8]) -> Result<usize, Error>;
+
+    /// Discards up to n bytes, returning how many were discarded - fewer only at the end of
+    /// the source
+    fn skip_bytes(&mut self, n: u64) -> Result<u64, Error> {
+        let mut scratch = [0u8; 64];
+        let mut skipped = 0;
+        while skipped < n {
+            let wanted = (n - skipped).min(scratch.len() as u64) as usize;
+            let read = self.read_bytes(&mut scratch[..wanted])?;
+            if read == 0 {
+                break;
+            }
+            skipped += read as u64;
+        }
+        Ok(skipped)
+    }
+}
+
+#[cfg(feature = "std")]
+impl<T: Read> ByteRead for T {
+    fn read_bytes(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
+        loop {
+            match self.read(buf) {
+                Ok(read) => return Ok(read),
+                Err(err) if err.kind() == std::io::ErrorKind::Interrupted => continue,
+                Err(err) => return Err(err.into()),
+            }
+        }
+    }
+
+    fn skip_bytes(&mut self, n: u64) -> Result<u64, Error> {
+        Ok(std::io::copy(&mut self.take(n), &mut std::io::sink())?)
+    }
+}
+
+#[cfg(not(feature = "std"))]
+impl ByteRead for &[u8] {
+    fn read_bytes(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
+        let read = buf.len().min(self.len());
+        let (head, tail) = self.split_at(read);
+        buf[..read].copy_from_slice(head);
+        *self = tail;
+        Ok(read)
+    }
+}
+
+/// The type parameter of a `BitstreamReader` created with `from_slice`
+#[cfg(feature = "std")]
+pub type SliceSource<'a> = Cursor<&'a [u8]>;
+/// The type parameter of a `BitstreamReader` created with `from_slice`
+#[cfg(not(feature = "std"))]
+pub type SliceSource<'a> = &'a [u8];
+
+/// Where a `BitstreamReader` gets its bytes from
+enum Source<'a, T> {
+    Reader(&'a mut T),
+    /// A slice read directly, and the index of the next byte to buffer
+    Slice(&'a [u8], usize),
+}
 
-    buf: u32,
+/// The bits buffered ahead of a reader, shared by the readers over each kind of source
+#[derive(Debug, Default)]
+pub(crate) struct BitBuffer {
+    buf: u64,      // buffered bits, most significant bit first
     remaining: u8, // remaining bits
     eof: bool,
+
+    position: u64, // bits consumed
+}
+
+impl BitBuffer {
+    /// Whether fewer than n bits are buffered and the source may have more
+    #[inline]
+    pub(crate) fn needs_refill(&self, n: u8) -> bool {
+        self.remaining < n && !self.eof
+    }
+
+    /// Whether the buffer has room for another byte
+    #[inline]
+    pub(crate) fn has_room(&self) -> bool {
+        self.remaining <= 56
+    }
+
+    /// Number of whole bytes that fit in the buffer
+    #[inline]
+    pub(crate) fn room(&self) -> usize {
+        ((64 - self.remaining) / 8) as usize
+    }
+
+    /// Appends the first `read` of `bytes`, with the rest zero, or marks the source as ended
+    /// when nothing was read
+    #[inline]
+    pub(crate) fn append(&mut self, bytes: [u8; 8], read: usize) {
+        if read == 0 {
+            self.eof = true;
+            return;
+        }
+
+        self.buf |= u64::from_be_bytes(bytes) >> self.remaining;
+        self.remaining += read as u8 * 8;
+    }
+
+    /// Returns the next n bits without consuming them, from what's already buffered
+    #[inline]
+    pub(crate) fn peek(&self, n: u8) -> Result<u32, Error> {
+        if n == 0 {
+            return Ok(0);
+        }
+        if self.remaining < n {
+            return Err(Error::Eof);
+        }
+
+        Ok((self.buf >> (64 - n)) as u32)
+    }
+
+    /// Consumes n bits, which must already be buffered
+    #[inline]
+    pub(crate) fn consume(&mut self, n: u8) {
+        if n > 0 {
+            self.remaining -= n;
+            self.position += n as u64;
+            self.buf <<= n;
+        }
+    }
+
+    /// Number of bits consumed
+    #[inline]
+    pub(crate) fn position(&self) -> u64 {
+        self.position
+    }
+}
+
+pub struct BitstreamReader<'a, T> {
+    source: Source<'a, T>,
+    bits: BitBuffer,
 }
 
-impl<'a, T: Read> BitstreamReader<'a, T> {
-    pub fn new(reader: &'a mut T) -> std::io::Result<Self> {
+impl<'a, T: ByteRead> BitstreamReader<'a, T> {
+    pub fn new(reader: &'a mut T) -> Result<Self, Error> {
         let mut empty = Self {
-            reader,
-            buf: 0,
-            remaining: 0,
-            eof: false,
+            source: Source::Reader(reader),
+            bits: BitBuffer::default(),
         };
 
         empty.refill()?;
         Ok(empty)
     }
 
-    /// f(n) - 4.10.2
-    pub fn f(&mut self, n: u8) -> std::io::Result<u32> {
+    /// Creates a reader that doesn't read anything from `reader` until the first bits are read
+    pub fn new_lazy(reader: &'a mut T) -> Self {
+        Self {
+            source: Source::Reader(reader),
+            bits: BitBuffer::default(),
+        }
+    }
+
+    /// Consumes the reader, returning the bytes that were read ahead from the underlying reader
+    /// but not consumed, along with the number of bits of the first byte that were already
+    /// consumed. Consumed bits are returned as zeros.
+    pub fn into_remaining(self) -> (Vec<u8>, u8) {
+        if self.bits.remaining == 0 {
+            return (Vec::new(), 0);
+        }
+
+        let consumed = (8 - self.bits.remaining % 8) % 8;
+        let bytes = (self.bits.remaining + consumed) / 8;
+        let value = self.bits.buf >> (64 - self.bits.remaining as u32);
+        let remaining = (0..bytes)
+            .rev()
+            .map(|i| (value >> (i as u32 * 8)) as u8)
+            .collect();
+        (remaining, consumed)
+    }
+
+    /// f(n) - 4.10.2, for n up to 32 - wider values are an `Error::Invalid`, read them with `f64`
+    pub fn f(&mut self, n: u8) -> Result<u32, Error> {
         self.get_bits(n)
     }
 
+    /// f(n) for n up to 64 - 4.10.2
+    pub fn f64(&mut self, n: u8) -> Result<u64, Error> {
+        if n > 64 {
+            return Err(Error::Invalid("bit width exceeds 64"));
+        }
+        if n <= 32 {
+            return Ok(self.get_bits(n)? as u64);
+        }
+
+        let high = self.get_bits(n - 32)? as u64;
+        let low = self.get_bits(32)? as u64;
+        Ok((high << 32) | low)
+    }
+
     /// Special helper for f(1) - 4.10.2
-    pub fn f1(&mut self) -> std::io::Result<bool> {
+    pub fn f1(&mut self) -> Result<bool, Error> {
         Ok(self.get_bits(1)? == 1)
     }
 
     /// uvlc() - 4.10.3
-    pub fn uvlc(&mut self) -> std::io::Result<u32> {
-        let mut leading_zeros = 0;
+    pub fn uvlc(&mut self) -> Result<u32, Error> {
+        // Zeros are read up to the terminating one bit, but from 32 of them on the value is
+        // capped without reading any value bits
+        let mut leading_zeros = 0u32;
         while !self.f1()? {
-            leading_zeros += 1;
+            leading_zeros = leading_zeros.saturating_add(1);
         }
 
         if leading_zeros >= 32 {
-            Ok(std::u32::MAX)
+            Ok(u32::MAX)
         } else {
-            Ok(self.f(leading_zeros)? + (1 << leading_zeros) - 1)
+            Ok(self.f(leading_zeros as u8)? + (1 << leading_zeros) - 1)
         }
     }
 
-    /// le(n) - 4.10.4
-    pub fn le(&mut self, n: u8) -> std::io::Result<u32> {
-        self.get_bits(n * 4)
+    /// le(n) - 4.10.4, `n` little-endian bytes
+    pub fn le(&mut self, n: u8) -> Result<u32, Error> {
+        if n > 4 {
+            return Err(Error::Invalid("le(n) wider than 4 bytes"));
+        }
+        if !self.is_byte_aligned() {
+            return Err(Error::Invalid("le(n) is not byte aligned"));
+        }
+
+        let mut value = 0;
+        for i in 0..n {
+            value |= self.get_bits(8)? << (i * 8);
+        }
+        Ok(value)
     }
 
     /// leb128() - 4.10.5
-    pub fn leb128(&mut self) -> std::io::Result<u32> {
+    pub fn leb128(&mut self) -> Result<u64, Error> {
+        Ok(self.leb128_with_len()?.0)
+    }
+
+    /// leb128() - 4.10.5, also returning the number of bytes the value was encoded in
+    pub fn leb128_with_len(&mut self) -> Result<(u64, u8), Error> {
         let mut value = 0;
         for i in 0..8 {
-            let byte = self.f(8)?;
+            let byte = self.f(8)? as u64;
             value |= (byte & 0x7f) << (i * 7);
 
-            if byte & 0x80 != 0 {
-                break;
+            if byte & 0x80 == 0 {
+                return Ok((value, i + 1));
             }
         }
-        Ok(value)
+
+        Err(Error::Invalid("unterminated leb128"))
     }
 
     /// su(n) - 4.10.6
-    pub fn su(&mut self, n: u8) -> std::io::Result<i32> {
+    pub fn su(&mut self, n: u8) -> Result<i32, Error> {
+        if n == 0 {
+            return Ok(0);
+        }
+
         let value = self.f(n)?;
         let sign_mask = 1 << (n - 1);
 
         if (value & sign_mask) != 0 {
+            // value - 2 * sign_mask, which is always in range even though the first subtraction
+            // wraps when n is 32
             Ok((value as i32)
                 .overflowing_sub_unsigned(sign_mask)
                 .0
@@ -81,77 +283,289 @@ impl<'a, T: Read> BitstreamReader<'a, T> {
     }
 
     /// ns(n) - 4.10.7
-    pub fn ns(&mut self, n: u8) -> std::io::Result<u32> {
+    pub fn ns(&mut self, n: u32) -> Result<u32, Error> {
+        // There's only one possible value, which takes no bits
+        if n <= 1 {
+            return Ok(0);
+        }
+
+        // Computed in 64 bits, as w is 32 for n above 2^31
         let w = n.ilog2() as u8 + 1;
-        let m = (1 << w) - (n as u32);
-        let v = self.f(w - 1)?;
+        let m = (1u64 << w) - n as u64;
+        let v = self.f(w - 1)? as u64;
 
         if v < m {
-            Ok(v)
+            Ok(v as u32)
         } else {
-            Ok((v << 1) - m + self.f(1)?)
+            Ok(((v << 1) - m + self.f(1)? as u64) as u32)
         }
     }
 
-    fn get_bits(&mut self, n: u8) -> std::io::Result<u32> {
-        assert!(n <= 32);
-
-        let (res, taken) = if self.remaining >= n {
-            (self.buf >> (32 - n), n)
-        } else if self.eof {
-            return Err(std::io::Error::from(std::io::ErrorKind::UnexpectedEof));
+    /// read_delta_q() - 5.9.13, a delta_coded flag followed by su(1 + 6) when set
+    pub fn delta_q(&mut self) -> Result<i32, Error> {
+        if self.f1()? {
+            self.su(1 + 6)
         } else {
-            let initial_bits = self.remaining;
-            let remainder = n - initial_bits;
-            let initial = self.buf >> (32 - initial_bits - remainder);
+            Ok(0)
+        }
+    }
+
+    /// Number of bits consumed since construction
+    pub fn position(&self) -> u64 {
+        self.bits.position()
+    }
+
+    /// Number of bytes consumed since construction - must be byte aligned
+    pub fn byte_position(&self) -> u64 {
+        debug_assert!(self.is_byte_aligned(), "reader is not byte aligned");
+        self.bits.position / 8
+    }
+
+    /// Whether the next bit read starts a byte
+    pub fn is_byte_aligned(&self) -> bool {
+        self.bits.position.is_multiple_of(8)
+    }
+
+    /// Whether any bits are left to read - only false once the buffer is drained and the reader
+    /// has reported EOF
+    pub fn has_more_data(&self) -> bool {
+        self.bits.remaining > 0 || !self.bits.eof
+    }
+
+    /// Returns the next n bits without consuming them
+    pub fn peek(&mut self, n: u8) -> Result<u32, Error> {
+        if n > 32 {
+            return Err(Error::Invalid("bit width exceeds 32"));
+        }
+        if n == 0 {
+            return Ok(0);
+        }
 
+        if self.bits.needs_refill(n) {
             self.refill()?;
+        }
+        self.bits.peek(n)
+    }
 
-            (initial | (self.buf >> (32 - remainder)), remainder)
-        };
+    /// Discards the next n bits
+    pub fn skip_bits(&mut self, n: u64) -> Result<(), Error> {
+        if n <= self.bits.remaining as u64 {
+            self.bits.remaining -= n as u8;
+            self.bits.position += n;
+            self.bits.buf = if n == 64 { 0 } else { self.bits.buf << n };
+            return Ok(());
+        }
+
+        // Drop everything that's buffered, then skip whole bytes directly on the reader
+        let n = n - self.bits.remaining as u64;
+        self.bits.position += self.bits.remaining as u64;
+        self.bits.buf = 0;
+        self.bits.remaining = 0;
+        if self.bits.eof {
+            return Err(Error::Eof);
+        }
 
-        self.remaining -= taken;
-        self.buf = if taken == 32 { 0 } else { self.buf << taken };
+        let bytes = n / 8;
+        let skipped = match &mut self.source {
+            Source::Reader(reader) => reader.skip_bytes(bytes)?,
+            Source::Slice(slice, next) => {
+                let skipped = bytes.min((slice.len() - *next) as u64);
+                *next += skipped as usize;
+                skipped
+            }
+        };
+        self.bits.position += skipped * 8;
+        if skipped < bytes {
+            self.bits.eof = true;
+            return Err(Error::Eof);
+        }
 
-        Ok(res as u32)
+        self.refill()?;
+        self.get_bits((n % 8) as u8)?;
+        Ok(())
     }
 
-    fn refill(&mut self) -> std::io::Result<()> {
-        self.remaining = 0;
-        while self.remaining < 32 {
-            match self.read_u8() {
-                Ok(byte) => {
-                    self.buf = (self.buf << 8) | (byte as u32);
-                    self.remaining += 8;
-                }
-                Err(err) => {
-                    match err.kind() {
-                        std::io::ErrorKind::UnexpectedEof => {
-                            // We aren't reading any more bits - shift the buffer to the end
-                            if self.remaining > 0 {
-                                self.buf <<= 32 - self.remaining;
-                            }
-                            self.eof = true;
-                            break;
-                        }
-                        _ => return Err(err),
+    /// Reads n whole bytes, starting byte aligned. Whatever isn't already buffered is copied
+    /// straight from the source rather than through the bit buffer.
+    pub fn read_bytes(&mut self, n: usize) -> Result<Vec<u8>, Error> {
+        if !self.is_byte_aligned() {
+            return Err(Error::Invalid("read_bytes is not byte aligned"));
+        }
+
+        let mut bytes = Vec::new();
+        while bytes.len() < n && self.bits.remaining >= 8 {
+            bytes.push(self.get_bits(8)? as u8);
+        }
+        if bytes.len() == n {
+            return Ok(bytes);
+        }
+        if self.bits.eof {
+            return Err(Error::Eof);
+        }
+
+        // The buffer is empty, as it only ever holds whole bytes while aligned
+        match &mut self.source {
+            Source::Reader(reader) => {
+                // Grown a chunk at a time, so a bogus n fails at the end of the source rather
+                // than allocating all of it up front
+                while bytes.len() < n {
+                    let start = bytes.len();
+                    bytes.resize(start + (n - start).min(4096), 0);
+                    let read = reader.read_bytes(&mut bytes[start..])?;
+                    bytes.truncate(start + read);
+                    self.bits.position += read as u64 * 8;
+                    if read == 0 {
+                        self.bits.eof = true;
+                        return Err(Error::Eof);
                     }
                 }
             }
+            Source::Slice(slice, next) => {
+                let read = (n - bytes.len()).min(slice.len() - *next);
+                bytes.extend_from_slice(&slice[*next..*next + read]);
+                *next += read;
+                self.bits.position += read as u64 * 8;
+                if bytes.len() < n {
+                    self.bits.eof = true;
+                    return Err(Error::Eof);
+                }
+            }
+        }
+
+        self.refill()?;
+        Ok(bytes)
+    }
+
+    /// byte_alignment() - 5.3.5
+    pub fn byte_alignment(&mut self) -> Result<(), Error> {
+        if self.is_byte_aligned() {
+            return Ok(());
         }
 
+        // The buffer is always refilled a whole byte at a time, so the bits left over in the
+        // current byte are exactly the bits needed to reach the next byte boundary.
+        let padding = self.bits.remaining % 8;
+
+        if self.get_bits(padding)? != 0 {
+            return Err(Error::Invalid("non-zero byte alignment bits"));
+        }
         Ok(())
     }
 
-    #[inline]
-    fn read_u8(&mut self) -> std::io::Result<u8> {
-        let mut buf = [0u8; 1];
-        self.reader.read_exact(&mut buf)?;
-        Ok(buf[0])
+    /// trailing_bits() - 5.3.4, a one bit followed by zero bits up to the next byte boundary
+    pub fn trailing_bits(&mut self) -> Result<(), Error> {
+        if !self.f1()? {
+            return Err(Error::Invalid("trailing_one_bit is not set"));
+        }
+
+        let padding = self.bits.remaining % 8;
+        if self.get_bits(padding)? != 0 {
+            return Err(Error::Invalid("non-zero trailing bits"));
+        }
+        Ok(())
+    }
+
+    fn get_bits(&mut self, n: u8) -> Result<u32, Error> {
+        let res = self.peek(n)?;
+        self.bits.consume(n);
+        Ok(res)
+    }
+
+    /// Tops up the buffer with whole bytes, keeping any bits that haven't been consumed yet
+    fn refill(&mut self) -> Result<(), Error> {
+        let Source::Slice(slice, next) = &mut self.source else {
+            return self.refill_from_reader();
+        };
+
+        // 32 bits at a time while they fit, then whatever bytes are left over
+        while self.bits.remaining <= 32 {
+            let Some(bytes) = slice.get(*next..*next + 4) else {
+                break;
+            };
+            let word = u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+            self.bits.buf |= (word as u64) << (32 - self.bits.remaining);
+            self.bits.remaining += 32;
+            *next += 4;
+        }
+        while self.bits.remaining <= 56 {
+            let Some(&byte) = slice.get(*next) else {
+                self.bits.eof = true;
+                break;
+            };
+            self.bits.buf |= (byte as u64) << (56 - self.bits.remaining);
+            self.bits.remaining += 8;
+            *next += 1;
+        }
+        Ok(())
+    }
+
+    #[inline(never)]
+    fn refill_from_reader(&mut self) -> Result<(), Error> {
+        let Source::Reader(reader) = &mut self.source else {
+            unreachable!("refilling a slice through ByteRead");
+        };
+
+        // Read as many whole bytes as fit in the buffer, which may take several reads if the
+        // reader returns short reads
+        while self.bits.has_room() {
+            let mut bytes = [0u8; 8];
+            let read = reader.read_bytes(&mut bytes[..self.bits.room()])?;
+
+            self.bits.append(bytes, read);
+            if read == 0 {
+                // We aren't reading any more bits
+                break;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl<'a> BitstreamReader<'a, SliceSource<'a>> {
+    /// Creates a reader over a slice, which is read directly rather than through `ByteRead`
+    pub fn from_slice(buf: &'a [u8]) -> Self {
+        let mut reader = Self {
+            source: Source::Slice(buf, 0),
+            bits: BitBuffer::default(),
+        };
+
+        // Refilling from a slice never fails
+        let _ = reader.refill();
+        reader
+    }
+}
+
+#[cfg(feature = "std")]
+impl<'a, T: Read + Seek> BitstreamReader<'a, T> {
+    /// Moves the reader to a byte offset, relative to where the reader started (the same origin
+    /// as `byte_position()`)
+    pub fn rewind_to_byte(&mut self, byte: u64) -> Result<(), Error> {
+        match &mut self.source {
+            Source::Reader(reader) => {
+                // Everything pulled from the reader so far has been whole bytes
+                let pulled = (self.bits.position + self.bits.remaining as u64) / 8;
+                let start = reader.stream_position()? - pulled;
+                reader.seek(SeekFrom::Start(start + byte))?;
+            }
+            Source::Slice(slice, next) => {
+                // Leaves the reader where it was, like a failed seek
+                *next = usize::try_from(byte)
+                    .ok()
+                    .filter(|byte| *byte <= slice.len())
+                    .ok_or(Error::Eof)?;
+            }
+        }
+
+        self.bits.buf = 0;
+        self.bits.remaining = 0;
+        self.bits.eof = false;
+        self.bits.position = byte * 8;
+        self.refill()
     }
 }
 
-#[cfg(test)]
+#[cfg(all(test, feature = "std"))]
 mod tests {
     use super::*;
     use std::io::Cursor;
@@ -186,7 +600,7 @@ mod tests {
         let dword = ((DATA_BUF[0] as u32) << 24)
             | ((DATA_BUF[1] as u32) << 16)
             | ((DATA_BUF[2] as u32) << 8)
-            | ((DATA_BUF[3] as u32) << 0);
+            | (DATA_BUF[3] as u32);
         assert_eq!(bs.f(31).unwrap(), dword >> 1);
         assert_eq!(bs.f(2).unwrap(), ((dword & 1) << 1) | 1);
     }
@@ -197,7 +611,7 @@ mod tests {
 
         let mut bytes = Cursor::new(&TEST_BUF);
         let mut bs = BitstreamReader::new(&mut bytes).unwrap();
-        assert_eq!(bs.uvlc().unwrap(), std::u32::MAX);
+        assert_eq!(bs.uvlc().unwrap(), u32::MAX);
     }
 
     #[test]
@@ -206,7 +620,31 @@ mod tests {
 
         let mut bytes = Cursor::new(&TEST_BUF);
         let mut bs = BitstreamReader::new(&mut bytes).unwrap();
-        assert_eq!(bs.uvlc().unwrap(), std::u32::MAX);
+        assert_eq!(bs.uvlc().unwrap(), u32::MAX);
+    }
+
+    #[test]
+    fn uvlc_40_leading_zeroes() {
+        const TEST_BUF: [u8; 7] = [0x00, 0x00, 0x00, 0x00, 0x00, 0x80, 0xff];
+
+        let mut bs = BitstreamReader::from_slice(&TEST_BUF);
+        assert_eq!(bs.uvlc().unwrap(), u32::MAX);
+        // The bits after the terminating one bit are left for the next syntax element
+        assert_eq!(bs.position(), 41);
+        assert_eq!(bs.f(7).unwrap(), 0);
+        assert_eq!(bs.f(8).unwrap(), 0xff);
+    }
+
+    #[test]
+    fn uvlc_all_zeroes() {
+        const TEST_BUF: [u8; 16] = [0x00; 16];
+
+        let mut bs = BitstreamReader::from_slice(&TEST_BUF);
+        assert!(matches!(bs.uvlc(), Err(Error::Eof)));
+
+        let mut bytes = Cursor::new(&TEST_BUF);
+        let mut bs = BitstreamReader::new(&mut bytes).unwrap();
+        assert!(matches!(bs.uvlc(), Err(Error::Eof)));
     }
 
     #[test]
@@ -295,6 +733,32 @@ mod tests {
         assert_eq!(bs.su(32).unwrap(), -1);
     }
 
+    #[test]
+    fn su_32_extremes() {
+        const TEST_BUF: [u8; 12] = [
+            0x80, 0x00, 0x00, 0x00, 0x7f, 0xff, 0xff, 0xff, 0x80, 0x00, 0x00, 0x01,
+        ];
+
+        let mut bs = BitstreamReader::from_slice(&TEST_BUF);
+        assert_eq!(bs.su(32).unwrap(), i32::MIN);
+        assert_eq!(bs.su(32).unwrap(), i32::MAX);
+        assert_eq!(bs.su(32).unwrap(), i32::MIN + 1);
+    }
+
+    #[test]
+    fn su_31_extremes() {
+        // 0x40000000, 0x3fffffff and 0x7fffffff as 31 bit values
+        const TEST_BUF: [u8; 12] = [
+            0x80, 0x00, 0x00, 0x00, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xf8,
+        ];
+
+        let mut bs = BitstreamReader::from_slice(&TEST_BUF);
+        assert_eq!(bs.su(31).unwrap(), -(1 << 30));
+        assert_eq!(bs.su(31).unwrap(), (1 << 30) - 1);
+        assert_eq!(bs.su(31).unwrap(), -1);
+        assert_eq!(bs.position(), 93);
+    }
+
     #[test]
     fn ns_5() {
         const TEST_BUF: [u8; 2] = [0b00011011, 0b01110000];
@@ -307,4 +771,809 @@ mod tests {
         assert_eq!(bs.ns(5).unwrap(), 3);
         assert_eq!(bs.ns(5).unwrap(), 4);
     }
+
+    /// Counts the bytes read from the underlying reader
+    struct CountingReader<'a> {
+        inner: Cursor<&'a [u8]>,
+        read: usize,
+    }
+
+    impl Read for CountingReader<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            let read = self.inner.read(buf)?;
+            self.read += read;
+            Ok(read)
+        }
+    }
+
+    /// Returns the data in chunks of varying odd sizes, interrupted every few reads
+    struct ChunkedReader<'a> {
+        data: &'a [u8],
+        reads: usize,
+    }
+
+    impl Read for ChunkedReader<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            self.reads += 1;
+            if self.reads.is_multiple_of(4) {
+                return Err(std::io::ErrorKind::Interrupted.into());
+            }
+
+            let len = [1, 3, 5, 7][self.reads % 4]
+                .min(buf.len())
+                .min(self.data.len());
+            buf[..len].copy_from_slice(&self.data[..len]);
+            self.data = &self.data[len..];
+            Ok(len)
+        }
+    }
+
+    #[test]
+    fn short_reads() {
+        let mut reader = ChunkedReader {
+            data: &DATA_BUF,
+            reads: 0,
+        };
+        let mut chunked_bs = BitstreamReader::new(&mut reader).unwrap();
+        let mut bytes = Cursor::new(&DATA_BUF);
+        let mut cursor_bs = BitstreamReader::new(&mut bytes).unwrap();
+
+        for n in (1..=32).cycle().take(100) {
+            let expected = cursor_bs.f(n).map_err(|err| err.to_string());
+            assert_eq!(chunked_bs.f(n).map_err(|err| err.to_string()), expected);
+        }
+        assert!(!chunked_bs.has_more_data());
+        assert_eq!(chunked_bs.position(), DATA_BUF.len() as u64 * 8);
+    }
+
+    /// Fails every read with an error other than EOF
+    struct FailingReader;
+
+    impl Read for FailingReader {
+        fn read(&mut self, _buf: &mut [u8]) -> std::io::Result<usize> {
+            Err(std::io::ErrorKind::BrokenPipe.into())
+        }
+    }
+
+    #[test]
+    fn read_error() {
+        let err = BitstreamReader::new(&mut FailingReader).err().unwrap();
+        assert!(matches!(err, Error::Io(_)));
+    }
+
+    #[test]
+    fn new_lazy() {
+        let mut reader = CountingReader {
+            inner: Cursor::new(&DATA_BUF),
+            read: 0,
+        };
+        let mut bs = BitstreamReader::new_lazy(&mut reader);
+        assert_eq!(bs.position(), 0);
+        assert!(bs.has_more_data());
+        assert_eq!(bs.f(8).unwrap(), DATA_BUF[0] as u32);
+        assert!(reader.read > 0);
+
+        let mut reader = CountingReader {
+            inner: Cursor::new(&DATA_BUF),
+            read: 0,
+        };
+        let bs = BitstreamReader::new_lazy(&mut reader);
+        assert_eq!(bs.into_remaining(), (Vec::new(), 0));
+        assert_eq!(reader.read, 0);
+    }
+
+    #[test]
+    fn into_remaining() {
+        const TEST_BUF: [u8; 4] = [0xa5, 0xff, 0x12, 0x34];
+
+        let mut bytes = Cursor::new(&TEST_BUF);
+        let mut bs = BitstreamReader::new(&mut bytes).unwrap();
+        assert_eq!(bs.f(8).unwrap(), 0xa5);
+        assert_eq!(bs.f(3).unwrap(), 0b111);
+
+        let (remaining, consumed) = bs.into_remaining();
+        assert_eq!(remaining, vec![0x1f, 0x12, 0x34]);
+        assert_eq!(consumed, 3);
+        assert_eq!(bytes.position(), 4);
+    }
+
+    /// Reads `buf` with a mix of operations, recording every result
+    fn read_all<T: Read + Seek>(bs: &mut BitstreamReader<T>) -> Vec<Result<u64, String>> {
+        let mut results = Vec::new();
+        let mut n = 0;
+        while bs.has_more_data() {
+            n = (n + 7) % 33;
+            let result = match n % 5 {
+                0 => bs.peek(n as u8).map(|v| v as u64),
+                1 => bs.skip_bits(n as u64 * 3).map(|_| bs.position()),
+                2 => bs.byte_alignment().map(|_| bs.position()),
+                _ => bs.f(n as u8).map(|v| v as u64),
+            };
+            results.push(result.map_err(|err| err.to_string()));
+        }
+        results.push(
+            bs.rewind_to_byte(3)
+                .map(|_| bs.f(32).unwrap() as u64)
+                .map_err(|err| err.to_string()),
+        );
+        results
+    }
+
+    #[test]
+    fn from_slice_matches_cursor() {
+        let test_buf: Vec<u8> = (0..1000u32)
+            .map(|i| (i.wrapping_mul(2654435761) >> 13) as u8)
+            .collect();
+
+        let mut bytes = Cursor::new(test_buf.as_slice());
+        let mut cursor_bs = BitstreamReader::new(&mut bytes).unwrap();
+        let mut slice_bs = BitstreamReader::from_slice(&test_buf);
+        assert_eq!(read_all(&mut slice_bs), read_all(&mut cursor_bs));
+        assert_eq!(slice_bs.position(), cursor_bs.position());
+    }
+
+    #[test]
+    fn from_slice_eof() {
+        const TEST_BUF: [u8; 5] = [0x12, 0x34, 0x56, 0x78, 0x9a];
+
+        let mut bs = BitstreamReader::from_slice(&TEST_BUF);
+        assert_eq!(bs.f(32).unwrap(), 0x12345678);
+        assert_eq!(bs.f(4).unwrap(), 0x9);
+        assert!(matches!(bs.f(5).unwrap_err(), Error::Eof));
+        assert_eq!(bs.f(4).unwrap(), 0xa);
+        assert!(!bs.has_more_data());
+
+        bs.rewind_to_byte(1).unwrap();
+        assert_eq!(bs.byte_position(), 1);
+        assert_eq!(bs.f(8).unwrap(), 0x34);
+    }
+
+    #[test]
+    fn le_bytes() {
+        const TEST_BUF: [u8; 7] = [0xa5, 0x34, 0x12, 0x78, 0x56, 0x34, 0x12];
+
+        let mut bytes = Cursor::new(&TEST_BUF);
+        let mut bs = BitstreamReader::new(&mut bytes).unwrap();
+        assert_eq!(bs.le(1).unwrap(), 0xa5);
+        assert_eq!(bs.le(2).unwrap(), 0x1234);
+        assert_eq!(bs.le(4).unwrap(), 0x12345678);
+        assert_eq!(bs.position(), 56);
+    }
+
+    #[test]
+    fn le_eof() {
+        const TEST_BUF: [u8; 1] = [0xa5];
+
+        let mut bytes = Cursor::new(&TEST_BUF);
+        let mut bs = BitstreamReader::new(&mut bytes).unwrap();
+        assert!(matches!(bs.le(2).unwrap_err(), Error::Eof));
+    }
+
+    #[test]
+    fn le_unaligned() {
+        const TEST_BUF: [u8; 2] = [0xa5, 0x5a];
+
+        let mut bytes = Cursor::new(&TEST_BUF);
+        let mut bs = BitstreamReader::new(&mut bytes).unwrap();
+        bs.f1().unwrap();
+        assert!(matches!(bs.le(1), Err(Error::Invalid(_))));
+    }
+
+    #[test]
+    fn is_byte_aligned() {
+        const TEST_BUF: [u8; 4] = [0xa5, 0x5a, 0x00, 0x12];
+
+        let mut bs = BitstreamReader::from_slice(&TEST_BUF);
+        assert!(bs.is_byte_aligned());
+        for _ in 0..7 {
+            bs.f1().unwrap();
+            assert!(!bs.is_byte_aligned());
+            assert!(matches!(bs.le(2), Err(Error::Invalid(_))));
+        }
+        bs.f1().unwrap();
+        assert!(bs.is_byte_aligned());
+        assert_eq!(bs.le(1).unwrap(), 0x5a);
+
+        bs.f(3).unwrap();
+        bs.byte_alignment().unwrap();
+        assert!(bs.is_byte_aligned());
+        assert_eq!(bs.le(1).unwrap(), 0x12);
+    }
+
+    #[test]
+    fn trailing_bits() {
+        const TEST_BUF: [u8; 4] = [0b10100000, 0b00000011, 0b10000000, 0xa5];
+
+        let mut bytes = Cursor::new(&TEST_BUF);
+        let mut bs = BitstreamReader::new(&mut bytes).unwrap();
+        assert_eq!(bs.f(2).unwrap(), 0b10);
+        bs.trailing_bits().unwrap();
+        assert_eq!(bs.position(), 8);
+
+        // The trailing one bit can be the last bit of a byte, or the first of a new byte
+        assert_eq!(bs.f(7).unwrap(), 0b0000001);
+        bs.trailing_bits().unwrap();
+        assert_eq!(bs.position(), 16);
+        bs.trailing_bits().unwrap();
+        assert_eq!(bs.position(), 24);
+        assert_eq!(bs.f(8).unwrap(), 0xa5);
+    }
+
+    #[test]
+    fn trailing_bits_corrupted() {
+        // Padding after the trailing one bit isn't zero
+        const TEST_BUF: [u8; 1] = [0b11100000];
+
+        let mut bytes = Cursor::new(&TEST_BUF);
+        let mut bs = BitstreamReader::new(&mut bytes).unwrap();
+        assert!(bs.f1().unwrap());
+        assert!(matches!(bs.trailing_bits().unwrap_err(), Error::Invalid(_)));
+
+        // No trailing one bit
+        const ZERO_BUF: [u8; 1] = [0x00];
+
+        let mut bytes = Cursor::new(&ZERO_BUF);
+        let mut bs = BitstreamReader::new(&mut bytes).unwrap();
+        assert!(matches!(bs.trailing_bits().unwrap_err(), Error::Invalid(_)));
+    }
+
+    #[test]
+    fn su_0() {
+        const TEST_BUF: [u8; 1] = [0xff];
+
+        let mut bytes = Cursor::new(&TEST_BUF);
+        let mut bs = BitstreamReader::new(&mut bytes).unwrap();
+        assert_eq!(bs.su(0).unwrap(), 0);
+        assert_eq!(bs.position(), 0);
+    }
+
+    #[test]
+    fn su_1() {
+        const TEST_BUF: [u8; 1] = [0b01000000];
+
+        let mut bytes = Cursor::new(&TEST_BUF);
+        let mut bs = BitstreamReader::new(&mut bytes).unwrap();
+        assert_eq!(bs.su(1).unwrap(), 0);
+        assert_eq!(bs.su(1).unwrap(), -1);
+        assert_eq!(bs.position(), 2);
+    }
+
+    #[test]
+    fn ns_0_and_1() {
+        const TEST_BUF: [u8; 1] = [0xff];
+
+        let mut bytes = Cursor::new(&TEST_BUF);
+        let mut bs = BitstreamReader::new(&mut bytes).unwrap();
+        assert_eq!(bs.ns(0).unwrap(), 0);
+        assert_eq!(bs.ns(1).unwrap(), 0);
+        assert_eq!(bs.position(), 0);
+    }
+
+    #[test]
+    fn ns_above_2_31() {
+        // w is 32, so v is read in 31 bits and the extra bit is needed for v >= m
+        const TEST_BUF: [u8; 8] = [0xff, 0xff, 0xff, 0xff, 0x00, 0x00, 0x00, 0x00];
+
+        let mut bs = BitstreamReader::from_slice(&TEST_BUF);
+        assert_eq!(bs.ns(u32::MAX).unwrap(), u32::MAX - 1);
+        assert_eq!(bs.position(), 32);
+        assert_eq!(bs.ns(1 << 31).unwrap(), 0);
+        assert_eq!(bs.position(), 63);
+    }
+
+    #[test]
+    fn oversized_widths() {
+        let mut bs = BitstreamReader::from_slice(&DATA_BUF);
+        assert!(matches!(bs.f(33), Err(Error::Invalid(_))));
+        assert!(matches!(bs.su(33), Err(Error::Invalid(_))));
+        assert!(matches!(bs.f64(65), Err(Error::Invalid(_))));
+        assert!(matches!(bs.le(5), Err(Error::Invalid(_))));
+        assert_eq!(bs.position(), 0);
+    }
+
+    #[test]
+    fn f_33_bits() {
+        let mut bs = BitstreamReader::from_slice(&DATA_BUF);
+        assert!(matches!(bs.peek(33), Err(Error::Invalid(_))));
+        assert!(matches!(bs.f(33), Err(Error::Invalid(_))));
+        // Nothing was consumed, and the same bits are still readable with f64
+        assert_eq!(
+            bs.f64(33).unwrap(),
+            0b1_0110_0111_0001_1110_0001_1111_0000_0111
+        );
+        assert_eq!(bs.position(), 33);
+    }
+
+    #[test]
+    fn delta_q() {
+        // Uncoded, 63, -64, uncoded
+        const TEST_BUF: [u8; 3] = [0b01011111, 0b11100000, 0b00000000];
+
+        let mut bs = BitstreamReader::from_slice(&TEST_BUF);
+        assert_eq!(bs.delta_q().unwrap(), 0);
+        assert_eq!(bs.delta_q().unwrap(), 63);
+        assert_eq!(bs.delta_q().unwrap(), -64);
+        assert_eq!(bs.delta_q().unwrap(), 0);
+        assert_eq!(bs.position(), 18);
+    }
+
+    #[test]
+    fn byte_alignment_aligned() {
+        const TEST_BUF: [u8; 2] = [0xa5, 0x5a];
+
+        let mut bytes = Cursor::new(&TEST_BUF);
+        let mut bs = BitstreamReader::new(&mut bytes).unwrap();
+        bs.byte_alignment().unwrap();
+        assert_eq!(bs.f(8).unwrap(), 0xa5);
+        bs.byte_alignment().unwrap();
+        assert_eq!(bs.f(8).unwrap(), 0x5a);
+
+        // Aligned at the end of the stream - nothing left to consume
+        bs.byte_alignment().unwrap();
+    }
+
+    #[test]
+    fn byte_alignment_offsets() {
+        for offset in 1..8 {
+            // `offset` bits of ones, zero padding, then a marker byte
+            let test_buf = [!(0xffu8 >> offset), 0xa5];
+
+            let mut bytes = Cursor::new(&test_buf);
+            let mut bs = BitstreamReader::new(&mut bytes).unwrap();
+            assert_eq!(bs.f(offset).unwrap(), (1 << offset) - 1);
+            bs.byte_alignment().unwrap();
+            assert_eq!(bs.f(8).unwrap(), 0xa5);
+        }
+    }
+
+    #[test]
+    fn byte_alignment_across_refill() {
+        const TEST_BUF: [u8; 6] = [0x00, 0x00, 0x00, 0x00, 0b11100000, 0xa5];
+
+        let mut bytes = Cursor::new(&TEST_BUF);
+        let mut bs = BitstreamReader::new(&mut bytes).unwrap();
+        assert_eq!(bs.f(32).unwrap(), 0);
+        assert_eq!(bs.f(3).unwrap(), 0b111);
+        bs.byte_alignment().unwrap();
+        assert_eq!(bs.f(8).unwrap(), 0xa5);
+    }
+
+    #[test]
+    fn byte_alignment_at_eof() {
+        const TEST_BUF: [u8; 1] = [0b10100000];
+
+        let mut bytes = Cursor::new(&TEST_BUF);
+        let mut bs = BitstreamReader::new(&mut bytes).unwrap();
+        assert_eq!(bs.f(3).unwrap(), 0b101);
+        bs.byte_alignment().unwrap();
+        assert!(bs.f(1).is_err());
+    }
+
+    #[test]
+    fn byte_alignment_nonzero_padding() {
+        const TEST_BUF: [u8; 1] = [0b10000001];
+
+        let mut bytes = Cursor::new(&TEST_BUF);
+        let mut bs = BitstreamReader::new(&mut bytes).unwrap();
+        assert!(bs.f1().unwrap());
+        let err = bs.byte_alignment().unwrap_err();
+        assert!(matches!(err, Error::Invalid(_)));
+    }
+
+    #[test]
+    fn position_mixed() {
+        const TEST_BUF: [u8; 4] = [0b10110000, 0b10001010, 0b00000000, 0b11000000];
+
+        let mut bytes = Cursor::new(&TEST_BUF);
+        let mut bs = BitstreamReader::new(&mut bytes).unwrap();
+        assert_eq!(bs.position(), 0);
+
+        assert_eq!(bs.f(3).unwrap(), 0b101);
+        assert_eq!(bs.position(), 3);
+
+        // uvlc() of 0 is a single bit
+        assert_eq!(bs.uvlc().unwrap(), 0);
+        assert_eq!(bs.position(), 4);
+
+        // 4 leading zeros, stop bit and 4 value bits
+        assert_eq!(bs.uvlc().unwrap(), 0b0001 + (1 << 4) - 1);
+        assert_eq!(bs.position(), 13);
+
+        assert_eq!(bs.f(11).unwrap(), 0b010_0000_0000);
+        assert_eq!(bs.position(), 24);
+        assert_eq!(bs.byte_position(), 3);
+
+        assert!(bs.f1().unwrap());
+        assert_eq!(bs.uvlc().unwrap(), 0);
+        assert_eq!(bs.position(), 26);
+    }
+
+    #[test]
+    fn position_across_refill() {
+        let mut bytes = Cursor::new(&DATA_BUF);
+        let mut bs = BitstreamReader::new(&mut bytes).unwrap();
+
+        let mut expected = 0;
+        for i in 1..=16 {
+            bs.f(i).unwrap();
+            bs.f(i).unwrap();
+            expected += 2 * i as u64;
+            assert_eq!(bs.position(), expected);
+        }
+        assert_eq!(bs.byte_position(), DATA_BUF.len() as u64);
+
+        // Failed reads don't advance the position
+        assert!(bs.f(1).is_err());
+        assert_eq!(bs.position(), expected);
+    }
+
+    /// Reads `n` bits starting at bit `offset` of `buf`, one bit at a time
+    fn reference_bits(buf: &[u8], offset: usize, n: usize) -> u64 {
+        (offset..offset + n).fold(0, |acc, bit| {
+            (acc << 1) | ((buf[bit / 8] >> (7 - bit % 8)) & 1) as u64
+        })
+    }
+
+    #[test]
+    fn f64_sizes() {
+        for n in [33, 40, 48, 64] {
+            // Start at a few different offsets so the reads straddle refills
+            for offset in [0, 5, 29, 32, 63] {
+                let mut bytes = Cursor::new(&DATA_BUF);
+                let mut bs = BitstreamReader::new(&mut bytes).unwrap();
+
+                bs.f64(offset as u8).unwrap();
+                assert_eq!(
+                    bs.f64(n).unwrap(),
+                    reference_bits(&DATA_BUF, offset, n as usize),
+                    "n = {}, offset = {}",
+                    n,
+                    offset
+                );
+                assert_eq!(bs.position(), offset as u64 + n as u64);
+            }
+        }
+    }
+
+    #[test]
+    fn f64_small() {
+        let mut bytes = Cursor::new(&DATA_BUF);
+        let mut bs = BitstreamReader::new(&mut bytes).unwrap();
+
+        assert_eq!(bs.f64(0).unwrap(), 0);
+        assert_eq!(bs.f64(12).unwrap(), reference_bits(&DATA_BUF, 0, 12));
+        assert_eq!(bs.f64(32).unwrap(), reference_bits(&DATA_BUF, 12, 32));
+    }
+
+    #[test]
+    fn f64_eof() {
+        const TEST_BUF: [u8; 5] = [0xff; 5];
+
+        let mut bytes = Cursor::new(&TEST_BUF);
+        let mut bs = BitstreamReader::new(&mut bytes).unwrap();
+        assert!(bs.f64(48).is_err());
+    }
+
+    #[test]
+    fn leb128_single_byte() {
+        const TEST_BUF: [u8; 3] = [0x00, 0x01, 0x7f];
+
+        let mut bytes = Cursor::new(&TEST_BUF);
+        let mut bs = BitstreamReader::new(&mut bytes).unwrap();
+        assert_eq!(bs.leb128().unwrap(), 0);
+        assert_eq!(bs.leb128().unwrap(), 1);
+        assert_eq!(bs.leb128().unwrap(), 127);
+        assert_eq!(bs.byte_position(), 3);
+    }
+
+    #[test]
+    fn leb128_multi_byte() {
+        const TEST_BUF: [u8; 7] = [0x80, 0x01, 0xe5, 0x8e, 0x26, 0xff, 0x7f];
+
+        let mut bytes = Cursor::new(&TEST_BUF);
+        let mut bs = BitstreamReader::new(&mut bytes).unwrap();
+        assert_eq!(bs.leb128().unwrap(), 128);
+        assert_eq!(bs.byte_position(), 2);
+        assert_eq!(bs.leb128().unwrap(), 624485);
+        assert_eq!(bs.byte_position(), 5);
+        assert_eq!(bs.leb128().unwrap(), 16383);
+        assert_eq!(bs.byte_position(), 7);
+    }
+
+    #[test]
+    fn leb128_max_length() {
+        const TEST_BUF: [u8; 8] = [0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x7f];
+
+        let mut bytes = Cursor::new(&TEST_BUF);
+        let mut bs = BitstreamReader::new(&mut bytes).unwrap();
+        assert_eq!(bs.leb128().unwrap(), (1 << 56) - 1);
+        assert_eq!(bs.byte_position(), 8);
+    }
+
+    #[test]
+    fn leb128_after_bits() {
+        const TEST_BUF: [u8; 4] = [0b10100000, 0xac, 0x02, 0x05];
+
+        let mut bytes = Cursor::new(&TEST_BUF);
+        let mut bs = BitstreamReader::new(&mut bytes).unwrap();
+        assert_eq!(bs.f(8).unwrap(), 0b10100000);
+        assert_eq!(bs.leb128().unwrap(), 300);
+        assert_eq!(bs.position(), 24);
+        assert_eq!(bs.leb128().unwrap(), 5);
+        assert_eq!(bs.position(), 32);
+    }
+
+    #[test]
+    fn leb128_unterminated() {
+        const TEST_BUF: [u8; 9] = [0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x01];
+
+        let mut bytes = Cursor::new(&TEST_BUF);
+        let mut bs = BitstreamReader::new(&mut bytes).unwrap();
+        let err = bs.leb128().unwrap_err();
+        assert!(matches!(err, Error::Invalid(_)));
+    }
+
+    #[test]
+    fn leb128_truncated() {
+        const TEST_BUF: [u8; 2] = [0x80, 0x80];
+
+        let mut bytes = Cursor::new(&TEST_BUF);
+        let mut bs = BitstreamReader::new(&mut bytes).unwrap();
+        let err = bs.leb128().unwrap_err();
+        assert!(matches!(err, Error::Eof));
+    }
+
+    #[test]
+    fn leb128_with_len() {
+        const TEST_BUF: [u8; 14] = [
+            0x05, 0x80, 0x01, 0xe5, 0x8e, 0x26, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x7f,
+        ];
+
+        let mut bytes = Cursor::new(&TEST_BUF);
+        let mut bs = BitstreamReader::new(&mut bytes).unwrap();
+        assert_eq!(bs.leb128_with_len().unwrap(), (5, 1));
+        assert_eq!(bs.leb128_with_len().unwrap(), (128, 2));
+        assert_eq!(bs.leb128_with_len().unwrap(), (624485, 3));
+        assert_eq!(bs.leb128_with_len().unwrap(), ((1 << 56) - 1, 8));
+    }
+
+    #[test]
+    fn leb128_with_len_padded() {
+        // Values may be encoded with redundant continuation bytes
+        const TEST_BUF: [u8; 4] = [0x81, 0x80, 0x80, 0x00];
+
+        let mut bytes = Cursor::new(&TEST_BUF);
+        let mut bs = BitstreamReader::new(&mut bytes).unwrap();
+        assert_eq!(bs.leb128_with_len().unwrap(), (1, 4));
+    }
+
+    #[test]
+    fn peek_then_f() {
+        let mut bytes = Cursor::new(&DATA_BUF);
+        let mut bs = BitstreamReader::new(&mut bytes).unwrap();
+
+        for i in 1..=16 {
+            for expected in [(1u32 << i) - 1, 0] {
+                assert_eq!(bs.peek(i).unwrap(), expected);
+                assert_eq!(bs.peek(i).unwrap(), expected);
+
+                let before = bs.position();
+                assert_eq!(bs.f(i).unwrap(), expected);
+                assert_eq!(bs.position(), before + i as u64);
+            }
+        }
+
+        assert_eq!(bs.peek(0).unwrap(), 0);
+        assert!(bs.peek(1).is_err());
+    }
+
+    #[test]
+    fn peek_across_refill() {
+        for offset in 1..32 {
+            let mut bytes = Cursor::new(&DATA_BUF);
+            let mut bs = BitstreamReader::new(&mut bytes).unwrap();
+
+            // Drain the initial fill so the peek needs bits that haven't been read yet
+            for _ in 0..(64 / offset) {
+                bs.f(offset).unwrap();
+            }
+            let position = bs.position() as usize;
+
+            let expected = reference_bits(&DATA_BUF, position, 32) as u32;
+            assert_eq!(bs.peek(32).unwrap(), expected, "offset = {}", offset);
+            assert_eq!(bs.position() as usize, position);
+            assert_eq!(bs.f(32).unwrap(), expected, "offset = {}", offset);
+        }
+    }
+
+    #[test]
+    fn peek_past_eof() {
+        const TEST_BUF: [u8; 2] = [0xab, 0xcd];
+
+        let mut bytes = Cursor::new(&TEST_BUF);
+        let mut bs = BitstreamReader::new(&mut bytes).unwrap();
+        assert_eq!(bs.f(4).unwrap(), 0xa);
+        assert!(bs.peek(13).is_err());
+
+        // The failed peek doesn't disturb the buffered bits
+        assert_eq!(bs.peek(12).unwrap(), 0xbcd);
+        assert_eq!(bs.f(12).unwrap(), 0xbcd);
+    }
+
+    #[test]
+    fn skip_bits_buffered() {
+        let mut bytes = Cursor::new(&DATA_BUF);
+        let mut bs = BitstreamReader::new(&mut bytes).unwrap();
+
+        bs.skip_bits(0).unwrap();
+        bs.skip_bits(3).unwrap();
+        assert_eq!(bs.f(5).unwrap() as u64, reference_bits(&DATA_BUF, 3, 5));
+        bs.skip_bits(56).unwrap();
+        assert_eq!(bs.position(), 64);
+        assert_eq!(bs.f(32).unwrap() as u64, reference_bits(&DATA_BUF, 64, 32));
+    }
+
+    #[test]
+    fn skip_bits_across_refill() {
+        for skip in [61, 64, 65, 100, 129, 200, 263] {
+            let mut bytes = Cursor::new(&DATA_BUF);
+            let mut bs = BitstreamReader::new(&mut bytes).unwrap();
+
+            bs.f(7).unwrap();
+            bs.skip_bits(skip).unwrap();
+            assert_eq!(bs.position(), 7 + skip);
+
+            let expected = reference_bits(&DATA_BUF, 7 + skip as usize, 2);
+            assert_eq!(bs.f(2).unwrap() as u64, expected, "skip = {}", skip);
+        }
+    }
+
+    #[test]
+    fn skip_bits_to_end() {
+        let mut bytes = Cursor::new(&DATA_BUF);
+        let mut bs = BitstreamReader::new(&mut bytes).unwrap();
+
+        bs.skip_bits(DATA_BUF.len() as u64 * 8).unwrap();
+        assert!(bs.f(1).is_err());
+    }
+
+    #[test]
+    fn read_bytes_across_refill() {
+        for start in [0, 3, 8, 13] {
+            let expected = &DATA_BUF[start..start + 20];
+
+            let mut bytes = Cursor::new(&DATA_BUF);
+            let mut bs = BitstreamReader::new(&mut bytes).unwrap();
+            bs.skip_bits(start as u64 * 8).unwrap();
+            assert_eq!(bs.read_bytes(20).unwrap(), expected, "start = {}", start);
+            assert_eq!(bs.position(), (start as u64 + 20) * 8);
+            assert_eq!(bs.f(8).unwrap(), DATA_BUF[start + 20] as u32);
+
+            let mut reader = ChunkedReader {
+                data: &DATA_BUF,
+                reads: 0,
+            };
+            let mut bs = BitstreamReader::new(&mut reader).unwrap();
+            bs.skip_bits(start as u64 * 8).unwrap();
+            assert_eq!(bs.read_bytes(20).unwrap(), expected, "start = {}", start);
+            assert_eq!(bs.f(8).unwrap(), DATA_BUF[start + 20] as u32);
+
+            let mut bs = BitstreamReader::from_slice(&DATA_BUF);
+            bs.skip_bits(start as u64 * 8).unwrap();
+            assert_eq!(bs.read_bytes(20).unwrap(), expected, "start = {}", start);
+            assert_eq!(bs.f(8).unwrap(), DATA_BUF[start + 20] as u32);
+        }
+    }
+
+    #[test]
+    fn read_bytes_errors() {
+        let mut bs = BitstreamReader::from_slice(&DATA_BUF);
+        assert_eq!(bs.read_bytes(0).unwrap(), Vec::<u8>::new());
+        bs.f(1).unwrap();
+        assert!(matches!(bs.read_bytes(1), Err(Error::Invalid(_))));
+
+        let mut bs = BitstreamReader::from_slice(&DATA_BUF);
+        assert!(matches!(bs.read_bytes(DATA_BUF.len() + 1), Err(Error::Eof)));
+        let mut bytes = Cursor::new(&DATA_BUF);
+        let mut bs = BitstreamReader::new(&mut bytes).unwrap();
+        assert!(matches!(bs.read_bytes(usize::MAX), Err(Error::Eof)));
+
+        let mut bs = BitstreamReader::from_slice(&DATA_BUF);
+        assert_eq!(bs.read_bytes(DATA_BUF.len()).unwrap(), DATA_BUF);
+        assert!(!bs.has_more_data());
+    }
+
+    #[test]
+    fn skip_bits_past_eof() {
+        for skip in [DATA_BUF.len() as u64 * 8 + 1, 1000] {
+            let mut bytes = Cursor::new(&DATA_BUF);
+            let mut bs = BitstreamReader::new(&mut bytes).unwrap();
+
+            let err = bs.skip_bits(skip).unwrap_err();
+            assert!(matches!(err, Error::Eof));
+        }
+    }
+
+    #[test]
+    fn rewind_to_byte() {
+        let mut bytes = Cursor::new(&DATA_BUF);
+        let mut bs = BitstreamReader::new(&mut bytes).unwrap();
+
+        let first: Vec<u32> = (1..=16).map(|i| bs.f(i).unwrap()).collect();
+        assert_eq!(bs.position(), 136);
+
+        bs.rewind_to_byte(0).unwrap();
+        assert_eq!(bs.position(), 0);
+        let second: Vec<u32> = (1..=16).map(|i| bs.f(i).unwrap()).collect();
+        assert_eq!(first, second);
+
+        bs.rewind_to_byte(9).unwrap();
+        assert_eq!(bs.byte_position(), 9);
+        assert_eq!(bs.f(8).unwrap(), DATA_BUF[9] as u32);
+    }
+
+    #[test]
+    fn rewind_after_eof() {
+        let mut bytes = Cursor::new(&DATA_BUF);
+        let mut bs = BitstreamReader::new(&mut bytes).unwrap();
+
+        bs.skip_bits(DATA_BUF.len() as u64 * 8).unwrap();
+        assert!(bs.f(1).is_err());
+
+        bs.rewind_to_byte(33).unwrap();
+        assert_eq!(bs.f(8).unwrap(), DATA_BUF[33] as u32);
+        assert!(bs.f(1).is_err());
+    }
+
+    #[test]
+    fn rewind_slice_past_end() {
+        let mut bs = BitstreamReader::from_slice(&DATA_BUF);
+        bs.f(12).unwrap();
+
+        assert!(matches!(bs.rewind_to_byte(35), Err(Error::Eof)));
+        assert!(matches!(bs.rewind_to_byte(u64::MAX), Err(Error::Eof)));
+        assert_eq!(bs.position(), 12);
+        assert_eq!(bs.f(4).unwrap(), DATA_BUF[1] as u32 & 0x0f);
+
+        // The end itself is fine
+        bs.rewind_to_byte(34).unwrap();
+        assert_eq!(bs.byte_position(), 34);
+        assert!(!bs.has_more_data());
+        assert!(matches!(bs.f(1), Err(Error::Eof)));
+    }
+
+    #[test]
+    fn rewind_relative_to_start() {
+        let mut bytes = Cursor::new(&DATA_BUF);
+        bytes.set_position(4);
+        let mut bs = BitstreamReader::new(&mut bytes).unwrap();
+
+        bs.f(20).unwrap();
+        bs.rewind_to_byte(1).unwrap();
+        assert_eq!(bs.f(8).unwrap(), DATA_BUF[5] as u32);
+    }
+
+    #[test]
+    fn has_more_data() {
+        const TEST_BUF: [u8; 3] = [0x12, 0x34, 0x56];
+
+        let mut bytes = Cursor::new(&TEST_BUF);
+        let mut bs = BitstreamReader::new(&mut bytes).unwrap();
+        for _ in 0..23 {
+            assert!(bs.has_more_data());
+            bs.f1().unwrap();
+        }
+
+        assert!(bs.has_more_data());
+        bs.f1().unwrap();
+        assert!(!bs.has_more_data());
+        assert!(bs.f1().is_err());
+    }
+
+    #[test]
+    fn has_more_data_empty() {
+        let mut bytes = Cursor::new(&[]);
+        let bs = BitstreamReader::new(&mut bytes).unwrap();
+        assert!(!bs.has_more_data());
+    }
 }
@@ -0,0 +1,208 @@
+use tokio::io::{AsyncRead, AsyncReadExt};
+
+use crate::bits::BitBuffer;
+use crate::Error;
+
+/// A `BitstreamReader` over an `AsyncRead`, for parsing a stream as it arrives without
+/// blocking the executor. Reads only wait for as many bytes as the bits being read need.
+pub struct AsyncBitstreamReader<'a, T> {
+    reader: &'a mut T,
+    bits: BitBuffer,
+}
+
+impl<'a, T: AsyncRead + Unpin> AsyncBitstreamReader<'a, T> {
+    /// Creates a reader that doesn't read anything from `reader` until the first bits are read
+    pub fn new(reader: &'a mut T) -> Self {
+        Self {
+            reader,
+            bits: BitBuffer::default(),
+        }
+    }
+
+    /// f(n) - 4.10.2, for n up to 32 - wider values are an `Error::Invalid`
+    pub async fn f(&mut self, n: u8) -> Result<u32, Error> {
+        if n > 32 {
+            return Err(Error::Invalid("bit width exceeds 32"));
+        }
+        if self.bits.needs_refill(n) {
+            self.refill(n).await?;
+        }
+
+        let res = self.bits.peek(n)?;
+        self.bits.consume(n);
+        Ok(res)
+    }
+
+    /// Special helper for f(1) - 4.10.2
+    pub async fn f1(&mut self) -> Result<bool, Error> {
+        Ok(self.f(1).await? == 1)
+    }
+
+    /// uvlc() - 4.10.3
+    pub async fn uvlc(&mut self) -> Result<u32, Error> {
+        let mut leading_zeros = 0u32;
+        while !self.f1().await? {
+            leading_zeros = leading_zeros.saturating_add(1);
+        }
+
+        if leading_zeros >= 32 {
+            Ok(u32::MAX)
+        } else {
+            Ok(self.f(leading_zeros as u8).await? + (1 << leading_zeros) - 1)
+        }
+    }
+
+    /// leb128() - 4.10.5
+    pub async fn leb128(&mut self) -> Result<u64, Error> {
+        Ok(self.leb128_with_len().await?.0)
+    }
+
+    /// leb128() - 4.10.5, also returning the number of bytes the value was encoded in
+    pub async fn leb128_with_len(&mut self) -> Result<(u64, u8), Error> {
+        let mut value = 0;
+        for i in 0..8 {
+            let byte = self.f(8).await? as u64;
+            value |= (byte & 0x7f) << (i * 7);
+
+            if byte & 0x80 == 0 {
+                return Ok((value, i + 1));
+            }
+        }
+
+        Err(Error::Invalid("unterminated leb128"))
+    }
+
+    /// Number of bits consumed since construction
+    pub fn position(&self) -> u64 {
+        self.bits.position()
+    }
+
+    /// Reads until at least n bits are buffered or the reader is at EOF. Unlike the blocking
+    /// reader this doesn't fill the whole buffer, as the rest of a live stream may not have
+    /// arrived yet.
+    async fn refill(&mut self, n: u8) -> Result<(), Error> {
+        while self.bits.needs_refill(n) {
+            let mut bytes = [0u8; 8];
+            let read = match self.reader.read(&mut bytes[..self.bits.room()]).await {
+                Ok(read) => read,
+                Err(err) if err.kind() == std::io::ErrorKind::Interrupted => continue,
+                Err(err) => return Err(err.into()),
+            };
+            self.bits.append(bytes, read);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::pin::Pin;
+    use std::task::{Context, Poll};
+
+    use tokio::io::ReadBuf;
+
+    use super::*;
+    use crate::bits::BitstreamReader;
+
+    /// Returns at most `chunk` bytes per read, and is pending before every other read
+    struct ChunkedReader<'a> {
+        buf: &'a [u8],
+        chunk: usize,
+        pending: bool,
+    }
+
+    impl AsyncRead for ChunkedReader<'_> {
+        fn poll_read(
+            mut self: Pin<&mut Self>,
+            cx: &mut Context<'_>,
+            out: &mut ReadBuf<'_>,
+        ) -> Poll<std::io::Result<()>> {
+            self.pending = !self.pending;
+            if self.pending {
+                cx.waker().wake_by_ref();
+                return Poll::Pending;
+            }
+
+            let len = self.chunk.min(self.buf.len()).min(out.remaining());
+            out.put_slice(&self.buf[..len]);
+            self.buf = &self.buf[len..];
+            Poll::Ready(Ok(()))
+        }
+    }
+
+    #[tokio::test]
+    async fn matches_sync_reader() {
+        const TEST_BUF: [u8; 16] = [
+            0x12, 0x00, 0x0a, 0x0b, 0x00, 0x00, 0x00, 0x24, 0xc4, 0xff, 0xdf, 0x00, 0x68, 0x02,
+            0xe5, 0x8e,
+        ];
+
+        for chunk in 1..=3 {
+            let mut sync = BitstreamReader::from_slice(&TEST_BUF);
+            let mut reader = ChunkedReader {
+                buf: &TEST_BUF,
+                chunk,
+                pending: false,
+            };
+            let mut bs = AsyncBitstreamReader::new(&mut reader);
+
+            // An OBU header and obu_size, then a mix of descriptors
+            assert_eq!(bs.f(8).await.unwrap(), sync.f(8).unwrap());
+            assert_eq!(bs.leb128().await.unwrap(), sync.leb128().unwrap());
+            assert_eq!(bs.f1().await.unwrap(), sync.f1().unwrap());
+            assert_eq!(bs.f(4).await.unwrap(), sync.f(4).unwrap());
+            assert_eq!(bs.f(3).await.unwrap(), sync.f(3).unwrap());
+            assert_eq!(
+                bs.leb128_with_len().await.unwrap(),
+                sync.leb128_with_len().unwrap()
+            );
+            assert_eq!(bs.uvlc().await.unwrap(), sync.uvlc().unwrap());
+            assert_eq!(bs.f(32).await.unwrap(), sync.f(32).unwrap());
+            assert_eq!(bs.position(), sync.position());
+            while sync.has_more_data() {
+                let n = (sync.position() % 7 + 1) as u8;
+                match sync.f(n) {
+                    Ok(value) => assert_eq!(bs.f(n).await.unwrap(), value),
+                    Err(_) => assert!(matches!(bs.f(n).await, Err(Error::Eof))),
+                }
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn f_33_bits() {
+        let mut reader = ChunkedReader {
+            buf: &[0xff; 8],
+            chunk: 8,
+            pending: false,
+        };
+        let mut bs = AsyncBitstreamReader::new(&mut reader);
+        assert!(matches!(bs.f(33).await, Err(Error::Invalid(_))));
+        assert_eq!(bs.position(), 0);
+    }
+
+    #[tokio::test]
+    async fn uvlc_all_zeroes() {
+        // More zeros than fit in the u8 the bit widths are read with
+        let mut reader = ChunkedReader {
+            buf: &[0x00; 40],
+            chunk: 3,
+            pending: false,
+        };
+        let mut bs = AsyncBitstreamReader::new(&mut reader);
+        assert!(matches!(bs.uvlc().await, Err(Error::Eof)));
+    }
+
+    #[tokio::test]
+    async fn reads_only_what_is_needed() {
+        // A live stream that has only delivered one byte so far
+        let mut reader = ChunkedReader {
+            buf: &[0x12],
+            chunk: 8,
+            pending: false,
+        };
+        let mut bs = AsyncBitstreamReader::new(&mut reader);
+        assert_eq!(bs.f(8).await.unwrap(), 0x12);
+        assert!(matches!(bs.f(1).await, Err(Error::Eof)));
+    }
+}
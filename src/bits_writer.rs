@@ -0,0 +1,324 @@
+use std::io::Write;
+
+use crate::Error;
+
+/// Writes the descriptors of 4.10 most significant bit first, the inverse of
+/// `BitstreamReader`. Bits are written to the underlying writer a whole byte at a time, so a
+/// partial final byte is only written once it's padded with `byte_alignment()` or
+/// `trailing_bits()`.
+pub struct BitstreamWriter<'a, W> {
+    writer: &'a mut W,
+
+    buf: u64, // pending bits, in the least significant `pending` bits
+    pending: u8,
+
+    position: u64, // bits written
+}
+
+impl<'a, W: Write> BitstreamWriter<'a, W> {
+    pub fn new(writer: &'a mut W) -> Self {
+        Self {
+            writer,
+            buf: 0,
+            pending: 0,
+            position: 0,
+        }
+    }
+
+    /// f(n) - 4.10.2
+    pub fn f(&mut self, n: u8, value: u32) -> Result<(), Error> {
+        if n > 32 {
+            return Err(Error::Invalid("bit width exceeds 32"));
+        }
+        if (value as u64) >> n != 0 {
+            return Err(Error::Invalid("value doesn't fit in n bits"));
+        }
+        self.put_bits(n, value)
+    }
+
+    /// Special helper for f(1) - 4.10.2
+    pub fn f1(&mut self, value: bool) -> Result<(), Error> {
+        self.put_bits(1, value as u32)
+    }
+
+    /// uvlc() - 4.10.3
+    pub fn uvlc(&mut self, value: u32) -> Result<(), Error> {
+        // 32 or more leading zeros decode as u32::MAX without any value bits
+        if value == u32::MAX {
+            self.put_bits(32, 0)?;
+            return self.put_bits(1, 1);
+        }
+
+        let leading_zeros = (value + 1).ilog2() as u8;
+        self.put_bits(leading_zeros, 0)?;
+        self.put_bits(1, 1)?;
+        self.put_bits(leading_zeros, value + 1 - (1 << leading_zeros))
+    }
+
+    /// leb128() - 4.10.5, in as few bytes as possible
+    pub fn leb128(&mut self, mut value: u64) -> Result<(), Error> {
+        if value >> 56 != 0 {
+            return Err(Error::Invalid("value doesn't fit in 8 leb128 bytes"));
+        }
+        loop {
+            let byte = (value & 0x7f) as u32;
+            value >>= 7;
+            if value == 0 {
+                return self.put_bits(8, byte);
+            }
+            self.put_bits(8, byte | 0x80)?;
+        }
+    }
+
+    /// su(n) - 4.10.6
+    pub fn su(&mut self, n: u8, value: i32) -> Result<(), Error> {
+        if n > 32 {
+            return Err(Error::Invalid("bit width exceeds 32"));
+        }
+        if n == 0 {
+            if value != 0 {
+                return Err(Error::Invalid("value doesn't fit in n bits"));
+            }
+            return Ok(());
+        }
+
+        let limit = 1i64 << (n - 1);
+        if !(-limit..limit).contains(&(value as i64)) {
+            return Err(Error::Invalid("value doesn't fit in n bits"));
+        }
+        let mask = (1u64 << n) - 1;
+        self.put_bits(n, (value as u64 & mask) as u32)
+    }
+
+    /// Number of bits written since construction
+    pub fn position(&self) -> u64 {
+        self.position
+    }
+
+    /// byte_alignment() - 5.3.5, zero bits up to the next byte boundary
+    pub fn byte_alignment(&mut self) -> Result<(), Error> {
+        let padding = (8 - self.position % 8) % 8;
+        self.put_bits(padding as u8, 0)
+    }
+
+    /// trailing_bits() - 5.3.4, a one bit followed by zero bits up to the next byte boundary
+    pub fn trailing_bits(&mut self) -> Result<(), Error> {
+        self.put_bits(1, 1)?;
+        self.byte_alignment()
+    }
+
+    fn put_bits(&mut self, n: u8, value: u32) -> Result<(), Error> {
+        if n == 0 {
+            return Ok(());
+        }
+
+        self.buf = (self.buf << n) | value as u64;
+        self.pending += n;
+        self.position += n as u64;
+
+        // At most 7 + 32 bits are pending, so this is at most 4 bytes
+        let mut bytes = [0; 4];
+        let mut len = 0;
+        while self.pending >= 8 {
+            self.pending -= 8;
+            bytes[len] = (self.buf >> self.pending) as u8;
+            len += 1;
+        }
+        self.buf &= (1 << self.pending) - 1;
+        self.writer.write_all(&bytes[..len])?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bits::BitstreamReader;
+
+    /// xorshift64*, for deterministic random values
+    struct Rng(u64);
+
+    impl Rng {
+        fn next(&mut self) -> u64 {
+            self.0 ^= self.0 >> 12;
+            self.0 ^= self.0 << 25;
+            self.0 ^= self.0 >> 27;
+            self.0.wrapping_mul(0x2545f4914f6cdd1d)
+        }
+
+        /// A random value with a random number of significant bits, up to `max_bits`
+        fn bits(&mut self, max_bits: u8) -> u64 {
+            let n = (self.next() % (max_bits as u64 + 1)) as u32;
+            self.next().checked_shr(64 - n).unwrap_or(0)
+        }
+    }
+
+    const ITERATIONS: usize = 1000;
+
+    #[test]
+    fn f_simple() {
+        let mut out = Vec::new();
+        let mut bw = BitstreamWriter::new(&mut out);
+        bw.f(3, 0b101).unwrap();
+        bw.f1(true).unwrap();
+        bw.f(12, 0xabc).unwrap();
+        assert_eq!(bw.position(), 16);
+        assert_eq!(out, [0b10111010, 0b10111100]);
+    }
+
+    #[test]
+    fn partial_byte_pending() {
+        let mut out = Vec::new();
+        let mut bw = BitstreamWriter::new(&mut out);
+        bw.f(12, 0xfff).unwrap();
+        assert_eq!(bw.position(), 12);
+        bw.byte_alignment().unwrap();
+        assert_eq!(bw.position(), 16);
+        bw.byte_alignment().unwrap();
+        assert_eq!(bw.position(), 16);
+        bw.trailing_bits().unwrap();
+        assert_eq!(out, [0xff, 0xf0, 0x80]);
+    }
+
+    #[test]
+    fn f_round_trip() {
+        let mut rng = Rng(1);
+        let mut out = Vec::new();
+        let mut bw = BitstreamWriter::new(&mut out);
+        let mut values = Vec::new();
+        for _ in 0..ITERATIONS {
+            let n = (rng.next() % 33) as u8;
+            let value = rng.next() as u32 & ((1u64 << n) - 1) as u32;
+            bw.f(n, value).unwrap();
+            values.push((n, value));
+        }
+        bw.byte_alignment().unwrap();
+
+        let mut br = BitstreamReader::from_slice(&out);
+        for (n, value) in values {
+            assert_eq!(br.f(n).unwrap(), value);
+        }
+    }
+
+    #[test]
+    fn uvlc_round_trip() {
+        let mut rng = Rng(2);
+        let mut out = Vec::new();
+        let mut bw = BitstreamWriter::new(&mut out);
+        let mut values = vec![0, 1, u32::MAX - 1, u32::MAX];
+        values.extend((0..ITERATIONS).map(|_| rng.bits(32) as u32));
+        for &value in &values {
+            bw.uvlc(value).unwrap();
+        }
+        bw.byte_alignment().unwrap();
+
+        let mut br = BitstreamReader::from_slice(&out);
+        for value in values {
+            assert_eq!(br.uvlc().unwrap(), value);
+        }
+    }
+
+    #[test]
+    fn leb128_round_trip() {
+        let mut rng = Rng(3);
+        let mut out = Vec::new();
+        let mut bw = BitstreamWriter::new(&mut out);
+        let mut values = vec![0, 0x7f, 0x80, (1 << 56) - 1];
+        values.extend((0..ITERATIONS).map(|_| rng.bits(56)));
+        // Unaligned values are read back the same way
+        bw.f1(true).unwrap();
+        for &value in &values {
+            bw.leb128(value).unwrap();
+        }
+        bw.byte_alignment().unwrap();
+
+        let mut br = BitstreamReader::from_slice(&out);
+        assert!(br.f1().unwrap());
+        for value in values {
+            let (read, len) = br.leb128_with_len().unwrap();
+            assert_eq!(read, value);
+            assert_eq!(len as u32, (64 - value.leading_zeros()).div_ceil(7).max(1));
+        }
+    }
+
+    #[test]
+    fn su_round_trip() {
+        let mut rng = Rng(4);
+        let mut out = Vec::new();
+        let mut bw = BitstreamWriter::new(&mut out);
+        let mut values = vec![(1, -1), (1, 0), (32, i32::MIN), (32, i32::MAX)];
+        for _ in 0..ITERATIONS {
+            let n = (rng.next() % 32 + 1) as u8;
+            let value = (rng.next() as i64 >> (64 - n)) as i32;
+            values.push((n, value));
+        }
+        for &(n, value) in &values {
+            bw.su(n, value).unwrap();
+        }
+        bw.byte_alignment().unwrap();
+
+        let mut br = BitstreamReader::from_slice(&out);
+        for (n, value) in values {
+            assert_eq!(br.su(n).unwrap(), value);
+        }
+    }
+
+    #[test]
+    fn byte_alignment_round_trip() {
+        let mut rng = Rng(5);
+        let mut out = Vec::new();
+        let mut bw = BitstreamWriter::new(&mut out);
+        let mut values = Vec::new();
+        for _ in 0..ITERATIONS {
+            let n = (rng.next() % 8) as u8;
+            let value = rng.next() as u32 & ((1 << n) - 1);
+            bw.f(n, value).unwrap();
+            bw.byte_alignment().unwrap();
+            values.push((n, value));
+        }
+        bw.trailing_bits().unwrap();
+
+        let mut br = BitstreamReader::from_slice(&out);
+        for (n, value) in values {
+            assert_eq!(br.f(n).unwrap(), value);
+            br.byte_alignment().unwrap();
+        }
+        br.trailing_bits().unwrap();
+        assert!(!br.has_more_data());
+    }
+
+    #[test]
+    fn f_value_too_large() {
+        let mut out = Vec::new();
+        let mut bw = BitstreamWriter::new(&mut out);
+        assert!(matches!(bw.f(4, 16), Err(Error::Invalid(_))));
+        assert!(matches!(bw.f(33, 0), Err(Error::Invalid(_))));
+        // Nothing is written by a failed call
+        assert_eq!(bw.position(), 0);
+        bw.f(4, 15).unwrap();
+    }
+
+    #[test]
+    fn su_value_too_large() {
+        let mut out = Vec::new();
+        let mut bw = BitstreamWriter::new(&mut out);
+        assert!(matches!(bw.su(4, 8), Err(Error::Invalid(_))));
+        assert!(matches!(bw.su(4, -9), Err(Error::Invalid(_))));
+        assert!(matches!(bw.su(0, 1), Err(Error::Invalid(_))));
+        assert!(matches!(bw.su(33, 0), Err(Error::Invalid(_))));
+        assert_eq!(bw.position(), 0);
+        bw.su(4, -8).unwrap();
+        bw.su(0, 0).unwrap();
+        assert_eq!(bw.position(), 4);
+    }
+
+    #[test]
+    fn leb128_value_too_large() {
+        let mut out = Vec::new();
+        let mut bw = BitstreamWriter::new(&mut out);
+        assert!(matches!(bw.leb128(1 << 56), Err(Error::Invalid(_))));
+        assert_eq!(bw.position(), 0);
+        bw.leb128((1 << 56) - 1).unwrap();
+        assert_eq!(bw.position(), 64);
+    }
+}
@@ -0,0 +1,143 @@
+use alloc::vec::Vec;
+
+use crate::av1::obu::{iter_obus, parse_sequence_header, Av1Profile, ObuType, SequenceHeaderObu};
+use crate::bits::BitstreamReader;
+use crate::Error;
+
+const AV1C_HEADER_LEN: usize = 4;
+
+/// AV1CodecConfigurationRecord, the payload of the ISOBMFF `av1C` box
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Av1CodecConfig {
+    pub seq_profile: Av1Profile,
+    pub seq_level_idx_0: u8,
+    pub seq_tier_0: u8,
+    pub high_bitdepth: bool,
+    pub twelve_bit: bool,
+    pub monochrome: bool,
+    pub chroma_subsampling_x: bool,
+    pub chroma_subsampling_y: bool,
+    pub chroma_sample_position: u8,
+    pub initial_presentation_delay_minus_one: Option<u8>,
+    /// The OBUs following the fixed fields, in the low overhead bitstream format
+    pub config_obus: Vec<u8>,
+    /// The first sequence header in `config_obus`, if there is one
+    pub sequence_header: Option<SequenceHeaderObu>,
+}
+
+/// Parses an AV1CodecConfigurationRecord from the payload of an `av1C` box
+pub fn parse_av1c(buf: &[u8]) -> Result<Av1CodecConfig, Error> {
+    if buf.len() < AV1C_HEADER_LEN {
+        return Err(Error::Eof);
+    }
+
+    let mut reader = BitstreamReader::from_slice(&buf[..AV1C_HEADER_LEN]);
+    if !reader.f1()? {
+        return Err(Error::Invalid("av1C marker isn't set"));
+    }
+    if reader.f(7)? != 1 {
+        return Err(Error::Unsupported("av1C version isn't 1"));
+    }
+
+    let seq_profile = Av1Profile::try_from(reader.f(3)? as u8)?;
+    let seq_level_idx_0 = reader.f(5)? as u8;
+    let seq_tier_0 = reader.f(1)? as u8;
+    let high_bitdepth = reader.f1()?;
+    let twelve_bit = reader.f1()?;
+    let monochrome = reader.f1()?;
+    let chroma_subsampling_x = reader.f1()?;
+    let chroma_subsampling_y = reader.f1()?;
+    let chroma_sample_position = reader.f(2)? as u8;
+    let _reserved = reader.f(3)?;
+    let initial_presentation_delay_present = reader.f1()?;
+    let initial_presentation_delay_minus_one = reader.f(4)? as u8;
+
+    let config_obus = buf[AV1C_HEADER_LEN..].to_vec();
+    let mut sequence_header = None;
+    for obu in iter_obus(&config_obus) {
+        let obu = obu?;
+        if obu.header.obu_type == ObuType::SequenceHeader {
+            let mut reader = BitstreamReader::from_slice(obu.payload());
+            sequence_header = Some(parse_sequence_header(&mut reader)?);
+            break;
+        }
+    }
+
+    Ok(Av1CodecConfig {
+        seq_profile,
+        seq_level_idx_0,
+        seq_tier_0,
+        high_bitdepth,
+        twelve_bit,
+        monochrome,
+        chroma_subsampling_x,
+        chroma_subsampling_y,
+        chroma_sample_position,
+        initial_presentation_delay_minus_one: initial_presentation_delay_present
+            .then_some(initial_presentation_delay_minus_one),
+        config_obus,
+        sequence_header,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A 1920x1080 10-bit 4:2:0 sequence header at level 4.0, main tier
+    const SEQ_1080P_10BIT: [u8; 11] = [
+        0x00, 0x00, 0x00, 0x42, 0xab, 0xbf, 0xc3, 0x73, 0xff, 0xe7, 0x01,
+    ];
+
+    fn av1c(header: [u8; AV1C_HEADER_LEN]) -> Vec<u8> {
+        let mut buf = header.to_vec();
+        buf.extend_from_slice(&[0x0a, SEQ_1080P_10BIT.len() as u8]);
+        buf.extend_from_slice(&SEQ_1080P_10BIT);
+        buf
+    }
+
+    #[test]
+    fn av1c_1080p_10bit() {
+        let config = parse_av1c(&av1c([0x81, 0x08, 0x4c, 0x00])).unwrap();
+        assert_eq!(config.seq_profile, Av1Profile::Main);
+        assert_eq!(config.seq_level_idx_0, 8);
+        assert_eq!(config.seq_tier_0, 0);
+        assert!(config.high_bitdepth);
+        assert!(!config.twelve_bit);
+        assert!(!config.monochrome);
+        assert!(config.chroma_subsampling_x);
+        assert!(config.chroma_subsampling_y);
+        assert_eq!(config.chroma_sample_position, 0);
+        assert_eq!(config.initial_presentation_delay_minus_one, None);
+        assert_eq!(config.config_obus.len(), 13);
+
+        let seq = config.sequence_header.unwrap();
+        assert_eq!(seq.seq_profile, config.seq_profile);
+        assert_eq!(seq.seq_level_idx[0], config.seq_level_idx_0);
+        assert_eq!(seq.seq_tier[0], config.seq_tier_0);
+        assert_eq!(seq.color_config.bit_depth, 10);
+        assert_eq!(seq.max_frame_width_minus_1, 1919);
+        assert_eq!(seq.max_frame_height_minus_1, 1079);
+    }
+
+    #[test]
+    fn av1c_without_config_obus() {
+        let config = parse_av1c(&[0x81, 0x08, 0x4c, 0x13]).unwrap();
+        assert_eq!(config.initial_presentation_delay_minus_one, Some(3));
+        assert!(config.config_obus.is_empty());
+        assert_eq!(config.sequence_header, None);
+    }
+
+    #[test]
+    fn av1c_invalid() {
+        assert!(matches!(
+            parse_av1c(&av1c([0x01, 0x08, 0x4c, 0x00])),
+            Err(Error::Invalid(_))
+        ));
+        assert!(matches!(
+            parse_av1c(&av1c([0x82, 0x08, 0x4c, 0x00])),
+            Err(Error::Unsupported(_))
+        ));
+        assert!(matches!(parse_av1c(&[0x81, 0x08]), Err(Error::Eof)));
+    }
+}
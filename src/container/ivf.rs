@@ -0,0 +1,276 @@
+use std::io::{self, ErrorKind, Read, Write};
+
+use crate::Error;
+
+const IVF_SIGNATURE: [u8; 4] = *b"DKIF";
+const IVF_HEADER_LEN: usize = 32;
+const IVF_FRAME_HEADER_LEN: usize = 12;
+const AV1_FOURCC: [u8; 4] = *b"AV01";
+
+/// The 32 byte IVF file header
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IvfHeader {
+    pub version: u16,
+    pub fourcc: [u8; 4],
+    pub width: u16,
+    pub height: u16,
+    /// The frame rate is `frame_rate / time_scale` frames per second, which is also the
+    /// time base of the frame timestamps
+    pub frame_rate: u32,
+    pub time_scale: u32,
+    /// The number of frames, which muxers don't always fill in
+    pub frame_count: u32,
+}
+
+/// An IVF frame, which holds one temporal unit in the low overhead bitstream format
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IvfFrame {
+    pub pts: u64,
+    pub data: Vec<u8>,
+}
+
+/// Iterates over the frames of an IVF file
+pub struct IvfReader<R> {
+    reader: R,
+    header: IvfHeader,
+    done: bool,
+}
+
+impl<R: Read> IvfReader<R> {
+    pub fn header(&self) -> &IvfHeader {
+        &self.header
+    }
+
+    fn read_frame(&mut self) -> Result<Option<IvfFrame>, Error> {
+        let mut frame_header = [0; IVF_FRAME_HEADER_LEN];
+        if !read_exact_or_eof(&mut self.reader, &mut frame_header)? {
+            return Ok(None);
+        }
+
+        let size = u32::from_le_bytes(frame_header[..4].try_into().unwrap());
+        let pts = u64::from_le_bytes(frame_header[4..].try_into().unwrap());
+        // Read through take() so a corrupt size doesn't allocate more than the file holds
+        let mut data = Vec::new();
+        (&mut self.reader)
+            .take(size as u64)
+            .read_to_end(&mut data)?;
+        if data.len() != size as usize {
+            return Err(Error::Eof);
+        }
+        Ok(Some(IvfFrame { pts, data }))
+    }
+}
+
+impl<R: Read> Iterator for IvfReader<R> {
+    type Item = Result<IvfFrame, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+
+        let frame = self.read_frame().transpose();
+        // Frames can't be found again after a truncated or failed read
+        if !matches!(frame, Some(Ok(_))) {
+            self.done = true;
+        }
+        frame
+    }
+}
+
+/// Reads the header of an IVF file holding AV1, returning a reader over its frames
+pub fn read_ivf<R: Read>(mut reader: R) -> Result<IvfReader<R>, Error> {
+    let mut buf = [0; IVF_HEADER_LEN];
+    reader.read_exact(&mut buf)?;
+    if buf[..4] != IVF_SIGNATURE {
+        return Err(Error::Invalid("missing the IVF signature"));
+    }
+
+    let le_u16 = |offset: usize| u16::from_le_bytes([buf[offset], buf[offset + 1]]);
+    let le_u32 = |offset: usize| u32::from_le_bytes(buf[offset..offset + 4].try_into().unwrap());
+    let header = IvfHeader {
+        version: le_u16(4),
+        fourcc: buf[8..12].try_into().unwrap(),
+        width: le_u16(12),
+        height: le_u16(14),
+        frame_rate: le_u32(16),
+        time_scale: le_u32(20),
+        frame_count: le_u32(24),
+    };
+    if header.fourcc != AV1_FOURCC {
+        return Err(Error::Unsupported("IVF fourcc isn't AV01"));
+    }
+
+    // Skip over any header fields newer than the ones above
+    let header_len = le_u16(6) as usize;
+    if header_len < IVF_HEADER_LEN {
+        return Err(Error::Invalid("IVF header is shorter than 32 bytes"));
+    }
+    let skip = (header_len - IVF_HEADER_LEN) as u64;
+    if std::io::copy(&mut (&mut reader).take(skip), &mut std::io::sink())? != skip {
+        return Err(Error::Eof);
+    }
+
+    Ok(IvfReader {
+        reader,
+        header,
+        done: false,
+    })
+}
+
+/// Writes an IVF file holding AV1, with one temporal unit per frame
+///
+/// The frames are numbered from 0 at 30 frames per second.
+pub fn write_ivf<W: Write>(w: &mut W, width: u16, height: u16, frames: &[&[u8]]) -> io::Result<()> {
+    let too_large = |_| io::Error::new(ErrorKind::InvalidInput, "too large for an IVF file");
+    let frame_count = u32::try_from(frames.len()).map_err(too_large)?;
+
+    let mut header = Vec::with_capacity(IVF_HEADER_LEN);
+    header.extend_from_slice(&IVF_SIGNATURE);
+    header.extend_from_slice(&0u16.to_le_bytes());
+    header.extend_from_slice(&(IVF_HEADER_LEN as u16).to_le_bytes());
+    header.extend_from_slice(&AV1_FOURCC);
+    header.extend_from_slice(&width.to_le_bytes());
+    header.extend_from_slice(&height.to_le_bytes());
+    header.extend_from_slice(&30u32.to_le_bytes());
+    header.extend_from_slice(&1u32.to_le_bytes());
+    header.extend_from_slice(&frame_count.to_le_bytes());
+    header.extend_from_slice(&[0; 4]);
+    w.write_all(&header)?;
+
+    for (pts, data) in frames.iter().enumerate() {
+        let size = u32::try_from(data.len()).map_err(too_large)?;
+        let mut frame_header = [0; IVF_FRAME_HEADER_LEN];
+        frame_header[..4].copy_from_slice(&size.to_le_bytes());
+        frame_header[4..].copy_from_slice(&(pts as u64).to_le_bytes());
+        w.write_all(&frame_header)?;
+        w.write_all(data)?;
+    }
+    Ok(())
+}
+
+/// Fills `buf`, returning false if the reader is already at EOF
+fn read_exact_or_eof<R: Read>(reader: &mut R, buf: &mut [u8]) -> Result<bool, Error> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        match reader.read(&mut buf[filled..]) {
+            Ok(0) if filled == 0 => return Ok(false),
+            Ok(0) => return Err(Error::Eof),
+            Ok(n) => filled += n,
+            Err(err) if err.kind() == ErrorKind::Interrupted => {}
+            Err(err) => return Err(err.into()),
+        }
+    }
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::av1::obu::{iter_obus, ObuType};
+
+    fn ivf_file(fourcc: &[u8; 4], frames: &[(u64, &[u8])]) -> Vec<u8> {
+        let mut buf = Vec::new();
+        buf.extend_from_slice(b"DKIF");
+        buf.extend_from_slice(&0u16.to_le_bytes());
+        buf.extend_from_slice(&32u16.to_le_bytes());
+        buf.extend_from_slice(fourcc);
+        buf.extend_from_slice(&352u16.to_le_bytes());
+        buf.extend_from_slice(&288u16.to_le_bytes());
+        buf.extend_from_slice(&30u32.to_le_bytes());
+        buf.extend_from_slice(&1u32.to_le_bytes());
+        buf.extend_from_slice(&(frames.len() as u32).to_le_bytes());
+        buf.extend_from_slice(&[0; 4]);
+        for (pts, data) in frames {
+            buf.extend_from_slice(&(data.len() as u32).to_le_bytes());
+            buf.extend_from_slice(&pts.to_le_bytes());
+            buf.extend_from_slice(data);
+        }
+        buf
+    }
+
+    #[test]
+    fn two_frames() {
+        // A temporal delimiter and padding OBU, then just a temporal delimiter
+        let first: &[u8] = &[0x12, 0x00, 0x7a, 0x02, 0xaa, 0xbb];
+        let second: &[u8] = &[0x12, 0x00];
+        let file = ivf_file(b"AV01", &[(0, first), (1, second)]);
+
+        let mut ivf = read_ivf(file.as_slice()).unwrap();
+        assert_eq!(
+            *ivf.header(),
+            IvfHeader {
+                version: 0,
+                fourcc: *b"AV01",
+                width: 352,
+                height: 288,
+                frame_rate: 30,
+                time_scale: 1,
+                frame_count: 2,
+            }
+        );
+
+        let frame = ivf.next().unwrap().unwrap();
+        assert_eq!(frame.pts, 0);
+        let obu_types: Vec<ObuType> = iter_obus(&frame.data)
+            .map(|obu| obu.unwrap().header.obu_type)
+            .collect();
+        assert_eq!(obu_types, [ObuType::TemporalDelimiter, ObuType::Padding]);
+
+        let frame = ivf.next().unwrap().unwrap();
+        assert_eq!(
+            frame,
+            IvfFrame {
+                pts: 1,
+                data: second.to_vec()
+            }
+        );
+        assert!(ivf.next().is_none());
+    }
+
+    #[test]
+    fn not_av1() {
+        let file = ivf_file(b"VP90", &[]);
+        assert!(matches!(
+            read_ivf(file.as_slice()),
+            Err(Error::Unsupported(_))
+        ));
+
+        let mut file = ivf_file(b"AV01", &[]);
+        file[0] = b'R';
+        assert!(matches!(read_ivf(file.as_slice()), Err(Error::Invalid(_))));
+        assert!(matches!(read_ivf(&file[..16]), Err(Error::Eof)));
+    }
+
+    #[test]
+    fn truncated_frame() {
+        let file = ivf_file(b"AV01", &[(0, &[0x12, 0x00]), (1, &[0x12, 0x00])]);
+
+        // Truncated in the middle of the frame data, then of the frame header
+        for len in [file.len() - 1, file.len() - 4] {
+            let mut ivf = read_ivf(&file[..len]).unwrap();
+            assert!(ivf.next().unwrap().is_ok());
+            assert!(matches!(ivf.next(), Some(Err(Error::Eof))));
+            assert!(ivf.next().is_none());
+        }
+    }
+
+    #[test]
+    fn write_then_read() {
+        let first: &[u8] = &[0x12, 0x00, 0x7a, 0x02, 0xaa, 0xbb];
+        let second: &[u8] = &[0x12, 0x00];
+        let mut file = Vec::new();
+        write_ivf(&mut file, 352, 288, &[first, second, &[]]).unwrap();
+        assert_eq!(
+            file,
+            ivf_file(b"AV01", &[(0, first), (1, second), (2, &[])])
+        );
+
+        let ivf = read_ivf(file.as_slice()).unwrap();
+        assert_eq!(ivf.header().width, 352);
+        assert_eq!(ivf.header().height, 288);
+        assert_eq!(ivf.header().frame_count, 3);
+        let frames: Vec<Vec<u8>> = ivf.map(|frame| frame.unwrap().data).collect();
+        assert_eq!(frames, [first, second, &[]]);
+    }
+}
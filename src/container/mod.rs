@@ -0,0 +1,4 @@
+pub mod av1c;
+#[cfg(feature = "std")]
+pub mod ivf;
+pub mod webm;
@@ -0,0 +1,31 @@
+use crate::container::av1c::{parse_av1c, Av1CodecConfig};
+use crate::Error;
+
+/// Parses the `CodecPrivate` element of an AV1 track in WebM or Matroska, which holds the
+/// same AV1CodecConfigurationRecord as the payload of an ISOBMFF `av1C` box
+pub fn parse_webm_codec_private(buf: &[u8]) -> Result<Av1CodecConfig, Error> {
+    parse_av1c(buf)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::av1::test_fixtures::SEQ_64X64;
+    use alloc::vec;
+
+    #[test]
+    fn codec_private_matches_av1c() {
+        let mut codec_private = vec![0x81, 0x1f, 0x0c, 0x00, 0x0a, 0x0a];
+        codec_private.extend_from_slice(&SEQ_64X64);
+
+        // The same record in an MP4 av1C box, after its size and type
+        let mut av1c_box = (8 + codec_private.len() as u32).to_be_bytes().to_vec();
+        av1c_box.extend_from_slice(b"av1C");
+        av1c_box.extend_from_slice(&codec_private);
+
+        let config = parse_webm_codec_private(&codec_private).unwrap();
+        assert_eq!(config, parse_av1c(&av1c_box[8..]).unwrap());
+        assert_eq!(config.seq_level_idx_0, 31);
+        assert!(config.sequence_header.is_some());
+    }
+}
@@ -0,0 +1,69 @@
+use core::fmt;
+
+#[derive(Debug)]
+pub enum Error {
+    /// The underlying reader failed
+    #[cfg(feature = "std")]
+    Io(std::io::Error),
+    /// The bitstream violates a constraint of the spec
+    Invalid(&'static str),
+    /// The bitstream uses a feature that isn't supported
+    Unsupported(&'static str),
+    /// The bitstream ended in the middle of a syntax element
+    Eof,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            #[cfg(feature = "std")]
+            Self::Io(err) => write!(f, "I/O error: {}", err),
+            Self::Invalid(reason) => write!(f, "invalid bitstream: {}", reason),
+            Self::Unsupported(reason) => write!(f, "unsupported bitstream: {}", reason),
+            Self::Eof => write!(f, "unexpected end of bitstream"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl From<std::io::Error> for Error {
+    fn from(err: std::io::Error) -> Self {
+        match err.kind() {
+            std::io::ErrorKind::UnexpectedEof => Self::Eof,
+            _ => Self::Io(err),
+        }
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn from_io_error() {
+        let err = Error::from(std::io::Error::from(std::io::ErrorKind::UnexpectedEof));
+        assert!(matches!(err, Error::Eof));
+
+        let err = Error::from(std::io::Error::from(std::io::ErrorKind::BrokenPipe));
+        assert!(matches!(err, Error::Io(_)));
+    }
+
+    #[test]
+    fn display() {
+        assert_eq!(
+            Error::Invalid("reserved obu_type").to_string(),
+            "invalid bitstream: reserved obu_type"
+        );
+        assert_eq!(Error::Eof.to_string(), "unexpected end of bitstream");
+    }
+}
@@ -0,0 +1,14 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+pub mod av1;
+pub mod bits;
+#[cfg(feature = "async")]
+pub mod bits_async;
+#[cfg(feature = "std")]
+pub mod bits_writer;
+pub mod container;
+mod error;
+
+pub use error::Error;
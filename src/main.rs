@@ -1,5 +1,111 @@
-mod bits;
+use std::process::ExitCode;
 
-fn main() {
-    println!("Hello, world!");
+use ranalyzer::av1::annexb::iter_annexb;
+use ranalyzer::av1::obu::{iter_obus, parse_sequence_header, Obu, ObuParser, ObuType};
+use ranalyzer::av1::stats::StreamStats;
+use ranalyzer::bits::BitstreamReader;
+use ranalyzer::Error;
+
+const USAGE: &str = "usage: ranalyzer [--annexb] [--summary] <file>";
+
+struct Args {
+    annexb: bool,
+    summary: bool,
+    path: String,
+}
+
+fn parse_args() -> Result<Args, String> {
+    let mut annexb = false;
+    let mut summary = false;
+    let mut path = None;
+    for arg in std::env::args().skip(1) {
+        match arg.as_str() {
+            "--annexb" => annexb = true,
+            "--summary" => summary = true,
+            "-h" | "--help" => return Err(USAGE.to_string()),
+            _ if arg.starts_with('-') => return Err(format!("unknown option {}\n{}", arg, USAGE)),
+            _ if path.is_none() => path = Some(arg),
+            _ => return Err(USAGE.to_string()),
+        }
+    }
+
+    Ok(Args {
+        annexb,
+        summary,
+        path: path.ok_or_else(|| USAGE.to_string())?,
+    })
+}
+
+fn print_obu(buf: &[u8], obu: &Obu) -> Result<(), Error> {
+    let offset = obu.buf().as_ptr() as usize - buf.as_ptr() as usize;
+    println!("{:>8} {}", offset, obu);
+
+    if obu.header.obu_type == ObuType::SequenceHeader {
+        let mut reader = BitstreamReader::from_slice(obu.payload());
+        let seq = parse_sequence_header(&mut reader)?;
+        println!("{:>8} {}", "", seq);
+    }
+    Ok(())
+}
+
+fn obus(buf: &[u8], annexb: bool) -> Result<Vec<Obu<'_>>, Error> {
+    if annexb {
+        let mut obus = Vec::new();
+        for temporal_unit in iter_annexb(buf) {
+            obus.extend_from_slice(temporal_unit?.obus());
+        }
+        Ok(obus)
+    } else {
+        iter_obus(buf).collect()
+    }
+}
+
+fn dump(buf: &[u8], obus: &[Obu]) -> Result<(), Error> {
+    for obu in obus {
+        print_obu(buf, obu)?;
+    }
+    Ok(())
+}
+
+fn summary(obus: &[Obu]) -> Result<(), Error> {
+    let mut parser = ObuParser::new();
+    let mut stats = StreamStats::new();
+    for obu in obus {
+        stats.add(obu, &parser.parse(obu)?);
+    }
+    print!("{}", stats);
+    Ok(())
+}
+
+fn main() -> ExitCode {
+    let args = match parse_args() {
+        Ok(args) => args,
+        Err(message) => {
+            eprintln!("{}", message);
+            return ExitCode::from(2);
+        }
+    };
+
+    let buf = match std::fs::read(&args.path) {
+        Ok(buf) => buf,
+        Err(err) => {
+            eprintln!("error: {}: {}", args.path, err);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let result = obus(&buf, args.annexb).and_then(|obus| {
+        if args.summary {
+            summary(&obus)
+        } else {
+            dump(&buf, &obus)
+        }
+    });
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("error: {}: {}", args.path, err);
+            ExitCode::FAILURE
+        }
+    }
 }
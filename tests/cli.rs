@@ -0,0 +1,81 @@
+use std::process::{Command, Output};
+
+fn ranalyzer(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_ranalyzer"))
+        .args(args)
+        .current_dir(env!("CARGO_MANIFEST_DIR"))
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn dump_low_overhead() {
+    let output = ranalyzer(&["tests/fixtures/64x64.obu"]);
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let lines: Vec<&str> = stdout.lines().collect();
+    assert_eq!(lines.len(), 8);
+    assert_eq!(lines[0], "       0 OBU_TEMPORAL_DELIMITER ext=none size=0");
+    assert_eq!(lines[1], "       2 OBU_SEQUENCE_HEADER ext=none size=10");
+    assert!(lines[2].contains("profile=Main size=64x64 bit_depth=8"));
+    assert!(lines[3].ends_with("OBU_FRAME ext=none size=1398"));
+}
+
+#[test]
+fn dump_annexb() {
+    let low_overhead = ranalyzer(&["tests/fixtures/64x64.obu"]);
+    let annexb = ranalyzer(&["--annexb", "tests/fixtures/64x64.annexb.obu"]);
+    assert!(annexb.status.success());
+
+    // The offsets differ between the framings but the OBUs are the same
+    let strip_offsets = |output: &Output| -> Vec<String> {
+        String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .map(|line| {
+                line.trim_start()
+                    .trim_start_matches(char::is_numeric)
+                    .to_string()
+            })
+            .collect()
+    };
+    assert_eq!(strip_offsets(&annexb), strip_offsets(&low_overhead));
+}
+
+#[test]
+fn parse_error() {
+    let output = ranalyzer(&["--annexb", "tests/fixtures/64x64.obu"]);
+    assert!(!output.status.success());
+
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.starts_with("error: tests/fixtures/64x64.obu: invalid bitstream"));
+}
+
+#[test]
+fn missing_file() {
+    let output = ranalyzer(&["tests/fixtures/missing.obu"]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8(output.stderr)
+        .unwrap()
+        .starts_with("error: tests/fixtures/missing.obu"));
+}
+
+#[test]
+fn summary() {
+    let output = ranalyzer(&["--summary", "tests/fixtures/64x64.obu"]);
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let field = |name: &str| -> String {
+        let line = stdout
+            .lines()
+            .find(|line| line.trim_start().starts_with(name))
+            .unwrap();
+        line.split_whitespace().last().unwrap().to_string()
+    };
+    assert_eq!(field("temporal units"), "3");
+    assert_eq!(field("resolution"), "64x64");
+    assert_eq!(field("KEY_FRAME"), "1");
+    assert_eq!(field("INTER_FRAME"), "2");
+    assert_eq!(field("bytes"), "3728");
+}
@@ -0,0 +1,18 @@
+use std::process::Command;
+
+#[test]
+fn builds_without_std() {
+    // A separate target directory, as the one running this test is locked by cargo
+    let output = Command::new(env!("CARGO"))
+        .args(["build", "--lib", "--no-default-features", "--offline"])
+        .arg("--target-dir")
+        .arg(concat!(env!("CARGO_TARGET_TMPDIR"), "/no_std"))
+        .current_dir(env!("CARGO_MANIFEST_DIR"))
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+}